@@ -0,0 +1,330 @@
+use std::{ops::ControlFlow, path::Path};
+
+use miette::Result;
+
+use crate::{
+    format,
+    interpreter::Interpreter,
+    print_table,
+    settings::{Settings, OPTIONS},
+    Repl,
+};
+
+/// A command of the REPL, like `:set base hex`.
+pub struct Command {
+    /// The names of the command, the first one being the main one.
+    pub names: &'static [&'static str],
+    /// The arguments of the command, as shown in the help.
+    pub args: &'static str,
+    pub help: &'static str,
+    /// Whether the argument of the command is a file name.
+    pub takes_file: bool,
+    /// Runs the command with its arguments, breaking if the REPL should quit.
+    run: fn(&mut Repl, &str) -> Result<ControlFlow<()>>,
+}
+
+impl Command {
+    /// Returns the command named `name`.
+    pub fn find(name: &str) -> Option<&'static Command> {
+        COMMANDS
+            .iter()
+            .find(|command| command.names.contains(&name))
+    }
+
+    /// Returns the line describing the command in the help, e.g. `:q | :quit - Quit the REPL`.
+    fn usage(&self) -> String {
+        let args = match self.args {
+            "" => String::new(),
+            args => format!(" {args}"),
+        };
+        format!("{}{args} - {}", self.names.join(" | "), self.help)
+    }
+}
+
+/// The commands of the REPL, in the order they are listed in the help.
+pub const COMMANDS: &[Command] = &[
+    Command {
+        names: &[":q", ":quit"],
+        args: "",
+        help: "Quit the REPL",
+        takes_file: false,
+        run: |_, _| Ok(ControlFlow::Break(())),
+    },
+    Command {
+        names: &[":h", ":help"],
+        args: "[topic]",
+        help: "Display this help message, or the help of a command, an option or `settings`",
+        takes_file: false,
+        run: help,
+    },
+    Command {
+        names: &[":ast"],
+        args: "<expr>",
+        help: "Display the syntax tree of an expression or statement",
+        takes_file: false,
+        run: |_, args| {
+            let stmt = Interpreter::parse(args).map_err(|e| with_source(e, args))?;
+            println!("{stmt}");
+            Ok(ControlFlow::Continue(()))
+        },
+    },
+    Command {
+        names: &[":explain"],
+        args: "<expr>",
+        help: "Display the steps of evaluating an expression and the conversions done in each",
+        takes_file: false,
+        run: |repl, args| {
+            let trace = repl
+                .interpreter
+                .explain(args)
+                .map_err(|e| with_source(e, args))?;
+            for line in format::as_trace(args, &trace) {
+                println!("{line}");
+            }
+            Ok(ControlFlow::Continue(()))
+        },
+    },
+    Command {
+        names: &[":diff"],
+        args: "<a> <b>",
+        help: "Compare the bits of two values, separated by a space or a comma, like diff(a, b)",
+        takes_file: false,
+        run: diff,
+    },
+    Command {
+        names: &[":tokens"],
+        args: "<expr>",
+        help: "Display the tokens of an expression or statement with their locations",
+        takes_file: false,
+        run: tokens,
+    },
+    Command {
+        names: &[":vars"],
+        args: "",
+        help: "List the variables and layouts of the session",
+        takes_file: false,
+        run: vars,
+    },
+    Command {
+        names: &[":set"],
+        args: "[option] [value]",
+        help: "Set an option, or display the value of one or all options",
+        takes_file: false,
+        run: set,
+    },
+    Command {
+        names: &[":clear"],
+        args: "",
+        help: "Remove all variables and layouts of the session",
+        takes_file: false,
+        run: |repl, _| {
+            repl.clear();
+            println!("Cleared all variables and layouts");
+            Ok(ControlFlow::Continue(()))
+        },
+    },
+    Command {
+        names: &[":load-regs"],
+        args: "<file>",
+        help: "Load register layouts from an SVD or TOML file",
+        takes_file: true,
+        run: |repl, path| {
+            let n = repl.load_regs(Path::new(path))?;
+            println!("Loaded {} from {path}", count(n, "register layout"));
+            Ok(ControlFlow::Continue(()))
+        },
+    },
+    Command {
+        names: &[":include"],
+        args: "<file>",
+        help: "Import the constants of a C header",
+        takes_file: true,
+        run: |repl, path| {
+            let n = repl.include(Path::new(path))?;
+            println!("Included {} from {path}", count(n, "constant"));
+            Ok(ControlFlow::Continue(()))
+        },
+    },
+    Command {
+        names: &[":save"],
+        args: "<file>",
+        help: "Save the layouts and variables of the session as a script",
+        takes_file: true,
+        run: |repl, path| {
+            let n = repl.save(Path::new(path))?;
+            println!("Saved {} to {path}", count(n, "definition"));
+            Ok(ControlFlow::Continue(()))
+        },
+    },
+    Command {
+        names: &[":load"],
+        args: "<file>",
+        help: "Replay a script saved with :save",
+        takes_file: true,
+        run: |repl, path| {
+            let n = repl.load(Path::new(path))?;
+            println!("Loaded {} from {path}", count(n, "definition"));
+            Ok(ControlFlow::Continue(()))
+        },
+    },
+    Command {
+        names: &[":emit"],
+        args: "<lang> [name...]",
+        help:
+            "Emit the last result or the given variables as constants in C, Rust, Python or Verilog",
+        takes_file: false,
+        run: |repl, args| {
+            println!("{}", repl.emit_command(args)?);
+            Ok(ControlFlow::Continue(()))
+        },
+    },
+];
+
+/// Returns `n` with `noun` in the singular or plural, e.g. `1 definition` or `2 definitions`.
+fn count(n: usize, noun: &str) -> String {
+    match n {
+        1 => format!("1 {noun}"),
+        n => format!("{n} {noun}s"),
+    }
+}
+
+/// Runs the command `line` of the REPL, which starts with `:`, reporting errors on stderr. Breaks if the
+/// REPL should quit.
+pub fn run(repl: &mut Repl, line: &str) -> ControlFlow<()> {
+    let (name, args) = line.split_once(' ').unwrap_or((line, ""));
+    let result = match Command::find(name) {
+        Some(command) => (command.run)(repl, args.trim()),
+        None => Err(miette::miette!(
+            "Unknown command '{name}', type :help for a list of commands"
+        )),
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("{e:?}");
+        ControlFlow::Continue(())
+    })
+}
+
+/// Attaches the input `source` to `error`, so that its labels are shown.
+fn with_source(
+    error: impl miette::Diagnostic + Send + Sync + 'static,
+    source: &str,
+) -> miette::Report {
+    miette::Report::new(error).with_source_code(source.to_string())
+}
+
+fn help(repl: &mut Repl, topic: &str) -> Result<ControlFlow<()>> {
+    match topic {
+        "" => {
+            println!("Commands:");
+            for command in COMMANDS {
+                println!("  {}", command.usage());
+            }
+            println!(
+                "Type :help <command> for the help of a command, or :help settings for the options"
+            );
+        }
+        "settings" => {
+            println!("Options:");
+            for (option, description) in OPTIONS {
+                println!("  {option} - {description}");
+            }
+            println!("Set them with :set <option> <value>");
+        }
+        _ => {
+            let name = format!(":{}", topic.trim_start_matches(':'));
+            if let Some(command) = Command::find(&name) {
+                println!("{}", command.usage());
+                if command.names == [":set"] {
+                    println!("Options: {}", OPTIONS.map(|(option, _)| option).join(", "));
+                }
+            } else if let Some((option, description)) =
+                OPTIONS.iter().find(|(option, _)| *option == topic)
+            {
+                println!("{option} - {description}");
+                println!("Values: {}", Settings::values(option)?);
+                println!("Current value: {}", repl.settings.get(option)?);
+            } else {
+                miette::bail!("Unknown help topic '{topic}'");
+            }
+        }
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+fn diff(repl: &mut Repl, args: &str) -> Result<ControlFlow<()>> {
+    let operands = match args.split_once(',') {
+        Some(_) => args.to_string(),
+        None => match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [a, b] => format!("{a}, {b}"),
+            _ => miette::bail!("Expected two values, like :diff 0x1234 0x1634"),
+        },
+    };
+    Ok(repl.eval_line(&format!("diff({operands})")))
+}
+
+fn tokens(_: &mut Repl, expr: &str) -> Result<ControlFlow<()>> {
+    let tokens = Interpreter::tokens(expr).map_err(|e| with_source(e, expr))?;
+    let header = ["Location", "Text", "Token"].map(String::from).to_vec();
+    let rows = tokens.iter().map(|token| {
+        let loc = token.loc();
+        vec![
+            format!("{}..{}", loc.start, loc.end),
+            expr[loc.clone()].to_string(),
+            format!("{:?}", token.kind()),
+        ]
+    });
+    print_table(std::iter::once(header).chain(rows).collect());
+    Ok(ControlFlow::Continue(()))
+}
+
+fn vars(repl: &mut Repl, _: &str) -> Result<ControlFlow<()>> {
+    let definitions = repl.interpreter.definitions();
+    let layouts = repl.interpreter.layouts().map(|layout| layout.name());
+    let layouts = layouts.collect::<Vec<_>>();
+    if definitions.is_empty() && layouts.is_empty() {
+        println!("There are no variables or layouts yet");
+        return Ok(ControlFlow::Continue(()));
+    }
+
+    if !definitions.is_empty() {
+        let header = ["Name", "Value", "Definition"].map(String::from).to_vec();
+        let rows = definitions.iter().map(|(name, definition)| {
+            vec![
+                name.to_string(),
+                definition.value().to_string(),
+                definition.expr().to_string(),
+            ]
+        });
+        print_table(std::iter::once(header).chain(rows).collect());
+    }
+    if !layouts.is_empty() {
+        println!("Layouts: {}", layouts.join(", "));
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+fn set(repl: &mut Repl, args: &str) -> Result<ControlFlow<()>> {
+    let mut args = args.split_whitespace();
+    match (args.next(), args.next(), args.next()) {
+        (None, _, _) => println!("{}", repl.settings),
+        (Some(option), None, _) => println!("{option} = {}", repl.settings.get(option)?),
+        (Some(option), Some(value), None) => repl.settings.set(option, value)?,
+        _ => miette::bail!("Expected an option and a value, like :set base hex"),
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_command_find() {
+        assert_eq!(Command::find(":quit").unwrap().names, [":q", ":quit"]);
+        assert_eq!(
+            Command::find(":set").unwrap().usage(),
+            ":set [option] [value] - Set an option, or display the value of one or all options"
+        );
+        assert!(Command::find(":explode").is_none());
+    }
+}
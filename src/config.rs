@@ -0,0 +1,164 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use thiserror::Error;
+use toml_edit::ImDocument;
+
+use crate::settings::{SettingError, Settings};
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ConfigError {
+    #[error("Failed to read {path}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid config file {}", src.name())]
+    Toml {
+        #[source_code]
+        src: NamedSource<Arc<str>>,
+        #[label("{message}")]
+        span: Option<SourceSpan>,
+        message: String,
+    },
+    #[error("Invalid entry in {}", src.name())]
+    Entry {
+        #[source_code]
+        src: NamedSource<Arc<str>>,
+        #[label("{error}")]
+        span: Option<SourceSpan>,
+        error: Box<SettingError>,
+    },
+}
+
+/// Returns the path of the config file in the XDG config directory, i.e. `$XDG_CONFIG_HOME` or
+/// `~/.config`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("bitweiser").join("config.toml"))
+}
+
+/// Applies the options of the TOML config file at `path` to `settings`, returning its prelude script.
+///
+/// The keys of the file are the options of `:set`, with strings or integers as their values, e.g.
+/// `base = "hex"` or `width = 32`, along with a `prelude` of statements to evaluate at startup.
+pub fn load(path: &Path, settings: &mut Settings) -> Result<Option<String>, ConfigError> {
+    let name = path.display().to_string();
+    let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: name.clone(),
+        source,
+    })?;
+    parse(&name, &text, settings)
+}
+
+/// Applies the options of the config file `text`, named `name` in diagnostics, to `settings`, returning
+/// its prelude script.
+fn parse(name: &str, text: &str, settings: &mut Settings) -> Result<Option<String>, ConfigError> {
+    let source = Arc::<str>::from(text);
+    let src = || NamedSource::new(name, source.clone());
+    let doc = ImDocument::parse(text).map_err(|e| ConfigError::Toml {
+        src: src(),
+        span: e.span().map(SourceSpan::from),
+        message: e.message().trim().to_string(),
+    })?;
+
+    let mut prelude = None;
+    for (key, item) in doc.iter() {
+        let key_span = doc.get_key_value(key).and_then(|(key, _)| key.span());
+        let value_span = item.span();
+        let raw = value_span.clone().map_or("", |span| text[span].trim());
+        let invalid = |error: SettingError, span: Option<Range<usize>>| ConfigError::Entry {
+            src: src(),
+            span: span.map(SourceSpan::from),
+            error: Box::new(error),
+        };
+
+        if key == "prelude" {
+            let script = item.as_str().ok_or_else(|| {
+                let error = SettingError::InvalidValue {
+                    option: "prelude",
+                    value: raw.to_string(),
+                    expected: "a string of statements".to_string(),
+                };
+                invalid(error, value_span.clone())
+            })?;
+            prelude = Some(script.to_string());
+            continue;
+        }
+
+        let value = match (item.as_str(), item.as_integer()) {
+            (Some(value), _) => value.to_string(),
+            (_, Some(value)) => value.to_string(),
+            _ => raw.to_string(),
+        };
+        settings.set(key, &value).map_err(|error| match error {
+            SettingError::UnknownOption(_) => invalid(error, key_span),
+            _ => invalid(error, value_span),
+        })?;
+    }
+    Ok(prelude)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::settings::Base;
+
+    #[test]
+    fn test_parse() {
+        let mut settings = Settings::default();
+        let prelude = parse(
+            "config.toml",
+            "# defaults\nbase = \"hex\"\nwidth = 32\nprelude = \"\"\"\nlet MASK = 0xff\n\"\"\"\n",
+            &mut settings,
+        )
+        .unwrap();
+        assert_eq!(settings.base, Base::Hex);
+        assert_eq!(settings.width, Some(32));
+        assert_eq!(prelude.as_deref(), Some("let MASK = 0xff\n"));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let mut settings = Settings::default();
+        let label = |text: &str| {
+            let error = parse("config.toml", text, &mut Settings::default()).unwrap_err();
+            let label = error.labels().unwrap().next().unwrap();
+            (
+                label.offset(),
+                label.len(),
+                label.label().unwrap().to_string(),
+            )
+        };
+        assert_eq!(
+            label("base = \"hex\"\nunits = \"metric\""),
+            (
+                21,
+                8,
+                "Invalid value 'metric' for units, expected one of both, si, iec".into()
+            )
+        );
+        assert_eq!(
+            label("colours = \"never\""),
+            (0, 7, "Unknown option 'colours'".into())
+        );
+        assert_eq!(
+            label("width = [8]"),
+            (
+                8,
+                3,
+                "Invalid value '[8]' for width, expected auto or 1 to 128".into()
+            )
+        );
+        assert_eq!(label("base = hex").0, 7);
+        assert!(parse("config.toml", "", &mut settings).unwrap().is_none());
+    }
+}
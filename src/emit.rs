@@ -0,0 +1,358 @@
+use clap::ValueEnum;
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::interpreter::{
+    float_type::FloatType,
+    int_type::IntType,
+    token::FullUnit,
+    value::{Scalar, Type, Value},
+};
+
+/// A language to emit constants in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    C,
+    Rust,
+    Python,
+    Verilog,
+}
+
+#[derive(Debug, Clone, PartialEq, Error, Diagnostic)]
+pub enum EmitError {
+    #[error("Cannot emit {0} as a constant")]
+    Unsupported(&'static str),
+}
+
+/// A value as it is emitted, i.e. an integer of a sized type, a float or a byte array.
+#[derive(Debug, Clone, PartialEq)]
+enum Constant {
+    Int(IntType, i128),
+    Float(FloatType, f64),
+    Bytes(Vec<u8>),
+}
+
+impl TryFrom<&Value> for Constant {
+    type Error = EmitError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Scalar(scalar) => Self::try_from(scalar),
+            Value::Bytes(bytes) => Ok(Constant::Bytes(bytes.clone())),
+            Value::Bitfield(bitfield) => Self::try_from(bitfield.scalar()),
+            Value::Diff(diff) => Self::try_from(diff.xor()),
+            Value::Layout(_) => Err(EmitError::Unsupported("a layout")),
+            Value::Table(_) => Err(EmitError::Unsupported("a table")),
+        }
+    }
+}
+
+impl TryFrom<&Scalar> for Constant {
+    type Error = EmitError;
+
+    /// Converts the scalar into its constant. Sizes are emitted in bytes, fixed point numbers as their raw
+    /// integers and characters as their encoding.
+    fn try_from(scalar: &Scalar) -> Result<Self, Self::Error> {
+        if scalar.unit().is_some() {
+            let bytes = scalar.clone().convert_to(FullUnit::byte());
+            return match bytes.value() {
+                value if value.fract() == 0.0 && value.abs() < i128::MAX as f64 => {
+                    Self::try_from(&Scalar::new(value as i128, None))
+                }
+                value => Self::float(FloatType::F64, value),
+            };
+        }
+
+        let ty = match scalar.ty() {
+            Some(Type::Float(ty)) => return Self::float(ty, scalar.value()),
+            Some(Type::Fixed(ty)) => ty.raw_type(),
+            Some(Type::Char(_)) => {
+                let bytes = scalar.byte_width().unwrap_or(4);
+                IntType::unsigned(bytes * 8)
+            }
+            Some(ty) => ty.int_type().unwrap_or(IntType::unsigned(128)),
+            None => match scalar.int() {
+                Some(value) => fitting_type(value),
+                None => return Self::float(FloatType::F64, scalar.value()),
+            },
+        };
+        let bits = scalar
+            .bits()
+            .ok_or(EmitError::Unsupported("an invalid character"))?;
+        Ok(Constant::Int(ty, ty.wrap(bits as i128)))
+    }
+}
+
+impl Constant {
+    fn float(ty: FloatType, value: f64) -> Result<Self, EmitError> {
+        if !value.is_finite() {
+            return Err(EmitError::Unsupported("a NaN or infinite float"));
+        }
+        Ok(Constant::Float(ty, value))
+    }
+}
+
+/// Returns the narrowest of the 32, 64 and 128 bit integer types holding `value`, which is signed if the
+/// value is negative.
+fn fitting_type(value: i128) -> IntType {
+    let bits = match value {
+        value if value < 0 => 129 - value.leading_ones(),
+        value => 128 - value.leading_zeros(),
+    };
+    IntType::new(value < 0, bits.next_power_of_two().clamp(32, 128))
+}
+
+/// Returns the width of the native integer type holding integers of `bits` bits, e.g. 16 for 12 bits.
+fn native_bits(bits: u32) -> u32 {
+    bits.next_power_of_two().max(8)
+}
+
+/// Formats `value` as a float literal, which always has a decimal point or exponent.
+fn float_literal(value: f64) -> String {
+    let literal = format!("{value:?}");
+    match literal.contains(['.', 'e']) {
+        true => literal,
+        false => format!("{literal}.0"),
+    }
+}
+
+/// Renders `value` as a constant named `name` in `lang`, preceded by a comment with the expression `expr`
+/// it is the result of.
+pub fn emit(lang: Lang, name: &str, expr: &str, value: &Value) -> Result<String, EmitError> {
+    let constant = Constant::try_from(value)?;
+    let definition = match lang {
+        Lang::C => c(name, &constant),
+        Lang::Rust => rust(name, &constant),
+        Lang::Python => python(name, &constant),
+        Lang::Verilog => verilog(name, &constant)?,
+    };
+    let comment = match lang {
+        Lang::Python => "#",
+        _ => "//",
+    };
+    Ok(format!("{comment} {expr}\n{definition}"))
+}
+
+fn c(name: &str, constant: &Constant) -> String {
+    match constant {
+        Constant::Int(ty, value) => {
+            let bits = native_bits(ty.bits());
+            let c_type = match (ty.is_signed(), bits) {
+                (true, 128) => "__int128".to_string(),
+                (false, 128) => "unsigned __int128".to_string(),
+                (true, bits) => format!("int{bits}_t"),
+                (false, bits) => format!("uint{bits}_t"),
+            };
+            let literal = match (ty.is_signed(), bits) {
+                // There are no 128 bit literals, so they are built from their upper and lower halves
+                (_, 128) => {
+                    let bits = *value as u128;
+                    let (hi, lo) = (bits >> 64, bits as u64);
+                    format!("({c_type})((unsigned __int128)0x{hi:016X}ULL << 64 | 0x{lo:016X}ULL)")
+                }
+                (true, bits) => {
+                    let suffix = if bits == 64 { "LL" } else { "" };
+                    match IntType::signed(bits).wrap(*value) {
+                        // The negation of the minimum overflows, as the literal itself is positive
+                        value if value == -1 << (bits - 1) => {
+                            format!("({}{suffix} - 1)", value + 1)
+                        }
+                        value => format!("{value}{suffix}"),
+                    }
+                }
+                (false, bits) => {
+                    let suffix = if bits == 64 { "ULL" } else { "U" };
+                    format!("0x{:01$X}{suffix}", value, bits as usize / 4)
+                }
+            };
+            format!("static const {c_type} {name} = {literal};")
+        }
+        Constant::Float(FloatType::F32, value) => {
+            format!("static const float {name} = {}f;", float_literal(*value))
+        }
+        Constant::Float(_, value) => {
+            format!("static const double {name} = {};", float_literal(*value))
+        }
+        Constant::Bytes(bytes) => {
+            let bytes = bytes
+                .iter()
+                .map(|b| format!("0x{b:02X}"))
+                .collect::<Vec<_>>();
+            format!(
+                "static const uint8_t {name}[{}] = {{ {} }};",
+                bytes.len(),
+                bytes.join(", ")
+            )
+        }
+    }
+}
+
+fn rust(name: &str, constant: &Constant) -> String {
+    match constant {
+        Constant::Int(ty, value) => {
+            let bits = native_bits(ty.bits());
+            let rust_type = match ty.is_signed() {
+                true => format!("i{bits}"),
+                false => format!("u{bits}"),
+            };
+            let literal = match ty.is_signed() {
+                true => format!("{value}_{rust_type}"),
+                false => format!("0x{:01$X}_{rust_type}", value, bits as usize / 4),
+            };
+            format!("pub const {name}: {rust_type} = {literal};")
+        }
+        Constant::Float(FloatType::F32, value) => {
+            format!("pub const {name}: f32 = {}_f32;", float_literal(*value))
+        }
+        Constant::Float(_, value) => {
+            format!("pub const {name}: f64 = {}_f64;", float_literal(*value))
+        }
+        Constant::Bytes(bytes) => {
+            let bytes = bytes
+                .iter()
+                .map(|b| format!("0x{b:02X}"))
+                .collect::<Vec<_>>();
+            format!(
+                "pub const {name}: [u8; {}] = [{}];",
+                bytes.len(),
+                bytes.join(", ")
+            )
+        }
+    }
+}
+
+fn python(name: &str, constant: &Constant) -> String {
+    match constant {
+        Constant::Int(ty, value) if ty.is_signed() => format!("{name} = {value}"),
+        Constant::Int(ty, value) => {
+            format!("{name} = 0x{:01$X}", value, ty.bits().div_ceil(4) as usize)
+        }
+        Constant::Float(_, value) => format!("{name} = {}", float_literal(*value)),
+        Constant::Bytes(bytes) => {
+            let hex = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+            format!("{name} = bytes.fromhex(\"{hex}\")")
+        }
+    }
+}
+
+fn verilog(name: &str, constant: &Constant) -> Result<String, EmitError> {
+    let definition = match constant {
+        // Verilog supports any width, so integers keep the exact width of their type
+        Constant::Int(ty, value) if ty.is_signed() => {
+            let bits = ty.bits();
+            let sign = if *value < 0 { "-" } else { "" };
+            format!(
+                "localparam signed [{}:0] {name} = {sign}{bits}'sd{};",
+                bits - 1,
+                value.unsigned_abs()
+            )
+        }
+        Constant::Int(ty, value) => {
+            let bits = ty.bits();
+            let digits = bits.div_ceil(4) as usize;
+            format!(
+                "localparam [{}:0] {name} = {bits}'h{:02$X};",
+                bits - 1,
+                ty.to_bits(*value),
+                digits
+            )
+        }
+        Constant::Float(_, value) => format!("localparam real {name} = {};", float_literal(*value)),
+        Constant::Bytes(bytes) if bytes.is_empty() => {
+            return Err(EmitError::Unsupported("an empty byte array"));
+        }
+        Constant::Bytes(bytes) => {
+            let bits = bytes.len() * 8;
+            let hex = bytes.iter().map(|b| format!("{b:02X}")).collect::<String>();
+            format!("localparam [{}:0] {name} = {bits}'h{hex};", bits - 1)
+        }
+    };
+    Ok(definition)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn emit_expr(lang: Lang, expr: &str) -> Result<String, EmitError> {
+        let value = Interpreter::new().interpret(expr).unwrap();
+        emit(lang, "MASK", expr, &value)
+    }
+
+    #[test]
+    fn test_emit_c() {
+        assert_eq!(
+            emit_expr(Lang::C, "1 << 24"),
+            Ok("// 1 << 24\nstatic const uint32_t MASK = 0x01000000U;".into())
+        );
+        assert_eq!(
+            emit_expr(Lang::C, "1 << 40").unwrap(),
+            "// 1 << 40\nstatic const uint64_t MASK = 0x0000010000000000ULL;"
+        );
+        assert_eq!(
+            emit_expr(Lang::C, "-128 as i8").unwrap(),
+            "// -128 as i8\nstatic const int8_t MASK = (-127 - 1);"
+        );
+        assert_eq!(
+            emit_expr(Lang::C, "1.5 as f32").unwrap(),
+            "// 1.5 as f32\nstatic const float MASK = 1.5f;"
+        );
+        assert_eq!(
+            emit_expr(Lang::C, "[1, 0xff]").unwrap(),
+            "// [1, 0xff]\nstatic const uint8_t MASK[2] = { 0x01, 0xFF };"
+        );
+        assert_eq!(
+            emit_expr(Lang::C, "1 << 100").unwrap(),
+            "// 1 << 100\nstatic const unsigned __int128 MASK = (unsigned __int128)((unsigned __int128)0x0000001000000000ULL << 64 | 0x0000000000000000ULL);"
+        );
+    }
+
+    #[test]
+    fn test_emit_rust() {
+        assert_eq!(
+            emit_expr(Lang::Rust, "4 KiB").unwrap(),
+            "// 4 KiB\npub const MASK: u32 = 0x00001000_u32;"
+        );
+        assert_eq!(
+            emit_expr(Lang::Rust, "-5").unwrap(),
+            "// -5\npub const MASK: i32 = -5_i32;"
+        );
+        assert_eq!(
+            emit_expr(Lang::Rust, "0o755 as perms").unwrap(),
+            "// 0o755 as perms\npub const MASK: u16 = 0x01ED_u16;"
+        );
+        assert_eq!(
+            emit_expr(Lang::Rust, "2.5").unwrap(),
+            "// 2.5\npub const MASK: f64 = 2.5_f64;"
+        );
+    }
+
+    #[test]
+    fn test_emit_python() {
+        assert_eq!(
+            emit_expr(Lang::Python, "0xff as u8").unwrap(),
+            "# 0xff as u8\nMASK = 0xFF"
+        );
+        assert_eq!(
+            emit_expr(Lang::Python, "\"ab\"").unwrap(),
+            "# \"ab\"\nMASK = bytes.fromhex(\"6162\")"
+        );
+    }
+
+    #[test]
+    fn test_emit_verilog() {
+        assert_eq!(
+            emit_expr(Lang::Verilog, "0o755 as perms").unwrap(),
+            "// 0o755 as perms\nlocalparam [11:0] MASK = 12'h1ED;"
+        );
+        assert_eq!(
+            emit_expr(Lang::Verilog, "-3 as i16").unwrap(),
+            "// -3 as i16\nlocalparam signed [15:0] MASK = -16'sd3;"
+        );
+        assert_eq!(
+            emit_expr(Lang::Verilog, "\"\""),
+            Err(EmitError::Unsupported("an empty byte array"))
+        );
+    }
+}
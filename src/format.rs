@@ -1,50 +1,348 @@
 use core::f64;
 
-use crate::interpreter::unit_prefix::UnitPrefix;
-
-pub(crate) fn as_bin(num: u64) -> String {
-    let bin = format!("{:b}", num);
-    let pad = if bin.len() % 8 != 0 {
-        8 - bin.len() % 8
-    } else {
-        0
-    };
+use clap::ValueEnum;
+use colored::Colorize;
 
-    // Insert spaces every 8 characters
-    let bin = bin
-        .chars()
-        .rev()
-        .enumerate()
-        .fold(String::new(), |mut acc, (i, c)| {
-            if i % 8 == 0 && i != 0 {
-                acc.push(' ');
+use crate::interpreter::{
+    fixed_point::QFormat, float_type::FloatType, trace::Trace, unit_prefix::UnitPrefix,
+};
+
+/// The separator between the groups of digits of binary renderings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Separator {
+    #[default]
+    Space,
+    #[value(alias = "_")]
+    Underscore,
+}
+
+/// The digits of binary renderings that are coloured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Highlight {
+    #[default]
+    None,
+    /// The set bits
+    Ones,
+    /// Every other nibble, to tell them apart
+    Nibbles,
+}
+
+/// The layout and colouring of binary renderings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BinFormat {
+    /// The number of digits to pad to, instead of whole bytes.
+    pub width: Option<u32>,
+    /// The number of digits between separators.
+    pub group: usize,
+    pub separator: Separator,
+    pub highlight: Highlight,
+}
+
+impl Default for BinFormat {
+    fn default() -> Self {
+        Self {
+            width: None,
+            group: 8,
+            separator: Separator::Space,
+            highlight: Highlight::None,
+        }
+    }
+}
+
+impl BinFormat {
+    /// Returns the characters of the binary rendering of `num`, along with the bit index of the digits,
+    /// which are padded with zeros to the width or to whole bytes.
+    fn cells(&self, num: u128) -> Vec<(char, Option<usize>)> {
+        let bin = format!("{num:b}");
+        let len = match self.width {
+            Some(width) => bin.len().max(width as usize),
+            None => bin.len().div_ceil(8) * 8,
+        };
+        let separator = match self.separator {
+            Separator::Space => ' ',
+            Separator::Underscore => '_',
+        };
+
+        let mut cells = Vec::new();
+        for (i, digit) in format!("{bin:0>len$}").chars().enumerate() {
+            let index = len - 1 - i;
+            cells.push((digit, Some(index)));
+            if index % self.group == 0 && index != 0 {
+                cells.push((separator, None));
             }
-            acc.push(c);
-            acc
-        })
-        .chars()
-        .rev()
-        .collect::<String>();
+        }
+        cells
+    }
+
+    /// Formats `num` in binary, with a separator between groups of digits and highlighted digits.
+    pub(crate) fn format(&self, num: u128) -> String {
+        self.format_marked(num, 0)
+    }
 
-    // Pad with 0 to multiple of 8 length
-    let mut padded_bin = "0".repeat(pad);
-    padded_bin.push_str(&bin);
+    /// Formats `num` like [`BinFormat::format`], with the digits of the bits set in `marked` in bold red
+    /// instead, e.g. the bits that differ from another number.
+    pub(crate) fn format_marked(&self, num: u128, marked: u128) -> String {
+        self.cells(num)
+            .into_iter()
+            .map(|(c, index)| match (index, self.highlight) {
+                (Some(index), _) if marked >> index & 1 == 1 => {
+                    c.to_string().red().bold().to_string()
+                }
+                (Some(_), Highlight::Ones) if c == '1' => c.to_string().yellow().to_string(),
+                (Some(index), Highlight::Nibbles) if index / 4 % 2 == 1 => {
+                    c.to_string().cyan().to_string()
+                }
+                _ => c.to_string(),
+            })
+            .collect()
+    }
 
-    padded_bin
+    /// Returns the ruler to print above the binary rendering of `num`, showing the bit index of the first
+    /// digit of each group and of the last digit, where there is room.
+    pub(crate) fn ruler(&self, num: u128) -> String {
+        let cells = self.cells(num);
+        let mut ruler = vec![' '; cells.len()];
+        // The position after the last index and a space, where the next index may start
+        let mut free = 0;
+        for (pos, (_, index)) in cells.iter().enumerate() {
+            let Some(index) = index else {
+                continue;
+            };
+            let label = index.to_string();
+            let end = pos + label.len();
+            let first = pos == 0 || (index + 1) % self.group == 0;
+            if (first || *index == 0) && pos >= free && end <= cells.len() {
+                ruler.splice(pos..end, label.chars());
+                free = end + 1;
+            }
+        }
+        ruler.into_iter().collect::<String>().trim_end().to_string()
+    }
+}
+
+/// Formats a number in binary as laid out by `format`.
+pub(crate) fn as_bin(num: u128, format: &BinFormat) -> String {
+    format.format(num)
 }
 
-pub(crate) fn as_dec_size(num: u64) -> String {
+/// Formats a size in bytes with the largest decimal prefix, with `precision` fractional digits or one
+/// if it isn't a whole multiple of the prefix.
+pub(crate) fn as_dec_size(num: u64, precision: Option<usize>) -> String {
     let prefix = UnitPrefix::dec_from_num(num);
 
     let fnum = num as f64 / u64::from(prefix) as f64;
-    let digits = (num % 1000 != 0) as usize;
+    let digits = precision.unwrap_or(!num.is_multiple_of(1000) as usize);
     format!("{:.1$} {2}B", fnum, digits, prefix)
 }
 
-pub(crate) fn as_bin_size(num: u64) -> String {
+/// Formats a size in bytes with the largest binary prefix, like [`as_dec_size`].
+pub(crate) fn as_bin_size(num: u64, precision: Option<usize>) -> String {
     let prefix = UnitPrefix::bin_from_num(num);
 
     let fnum = num as f64 / u64::from(prefix) as f64;
-    let digits = (num % 1024 != 0) as usize;
+    let digits = precision.unwrap_or(!num.is_multiple_of(1024) as usize);
     format!("{:.1$} {2}B", fnum, digits, prefix)
 }
+
+/// Formats an IPv4 address in binary, with its octets separated by dots.
+pub(crate) fn as_ipv4_bin(addr: u32) -> String {
+    addr.to_be_bytes()
+        .map(|octet| format!("{octet:08b}"))
+        .join(".")
+}
+
+/// Formats `rows` as lines of left aligned columns, separated by two spaces.
+pub(crate) fn as_table(rows: &[Vec<String>]) -> Vec<String> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    rows.iter()
+        .map(|row| {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Formats `rows` as lines of comma separated values, quoting the cells containing commas, quotes or
+/// line breaks.
+pub(crate) fn as_csv(rows: &[Vec<String>]) -> Vec<String> {
+    let cell = |cell: &String| match cell.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", cell.replace('"', "\"\"")),
+        false => cell.clone(),
+    };
+    rows.iter()
+        .map(|row| row.iter().map(cell).collect::<Vec<_>>().join(","))
+        .collect()
+}
+
+/// Returns a line marking the first `prefix` characters of each group of `width` characters, with the
+/// groups separated by `sep` characters.
+fn prefix_marks(prefixes: impl Iterator<Item = usize>, width: usize, sep: usize) -> String {
+    let marks = prefixes
+        .map(|prefix| format!("{:1$}", "^".repeat(prefix), width))
+        .collect::<Vec<_>>()
+        .join(&" ".repeat(sep));
+    marks.trim_end().to_string()
+}
+
+/// Formats the UTF-8 encoding of `c` in binary, along with a line marking the prefix bits of each byte.
+pub(crate) fn as_utf8_bin(c: char) -> (String, String) {
+    let mut buf = [0; 4];
+    let bytes = c.encode_utf8(&mut buf).as_bytes();
+    let packed = bytes.iter().fold(0, |acc, b| acc << 8 | *b as u128);
+
+    // ASCII bytes start with a 0, all others with their number of leading ones followed by a 0
+    let prefixes = bytes.iter().map(|b| b.leading_ones() as usize + 1);
+    (
+        as_bin(packed, &BinFormat::default()),
+        prefix_marks(prefixes, 8, 1),
+    )
+}
+
+/// Formats the UTF-16 code units of `c` in binary, along with a line marking the prefix bits of
+/// surrogates.
+pub(crate) fn as_utf16_bin(c: char) -> (String, String) {
+    let mut buf = [0; 2];
+    let units = c.encode_utf16(&mut buf);
+
+    let bin = units
+        .iter()
+        .map(|unit| as_bin(*unit as u128 | 1 << 16, &BinFormat::default())[9..].to_string())
+        .collect::<Vec<_>>()
+        .join("  ");
+    let prefixes = units.iter().map(|unit| match unit {
+        0xd800..=0xdfff => 6,
+        _ => 0,
+    });
+    (bin, prefix_marks(prefixes, 17, 2))
+}
+
+/// Formats bytes as space separated hex pairs, e.g. `78 56 34 12`.
+pub(crate) fn as_hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Formats bytes as the lines of a hexdump, with 16 bytes per line, their offset and their ASCII
+/// representation.
+pub(crate) fn as_hexdump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let ascii = chunk
+                .iter()
+                .map(|&b| match b {
+                    b' '..=b'~' => b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            format!("{:08x}  {:<47}  |{}|", i * 16, as_hex_bytes(chunk), ascii)
+        })
+        .collect()
+}
+
+/// Formats the raw value of a fixed point number with a binary point between its integer and fractional
+/// bits.
+pub(crate) fn as_fixed_bin(ty: QFormat, raw: u64) -> String {
+    let bin = format!("{:01$b}", raw, ty.bits() as usize);
+    let (int, frac) = bin.split_at(ty.int_bits() as usize);
+    format!("{int}.{frac}")
+}
+
+/// Formats the bit pattern of a float with its sign, exponent and mantissa separated by spaces.
+pub(crate) fn as_float_bin(ty: FloatType, bits: u64) -> String {
+    let fields = ty.fields(bits);
+    format!(
+        "{} {:0e$b} {:0m$b}",
+        fields.sign as u8,
+        fields.exponent,
+        fields.mantissa,
+        e = ty.exponent_bits() as usize,
+        m = ty.mantissa_bits() as usize,
+    )
+}
+
+/// Formats the evaluation `trace` of `input` as the lines of a tree, with a line per step showing its
+/// source, value and location, followed by the notes on its conversions.
+pub(crate) fn as_trace(input: &str, trace: &Trace) -> Vec<String> {
+    let mut lines = Vec::new();
+    push_trace(input, trace, "", "", &mut lines);
+    lines
+}
+
+/// Adds the lines of `trace` to `lines`, with `lead` in front of its first line and `indent` in front of
+/// the others.
+fn push_trace(input: &str, trace: &Trace, lead: &str, indent: &str, lines: &mut Vec<String>) {
+    let loc = &trace.loc;
+    let source = input.get(loc.clone()).unwrap_or(input).trim();
+    lines.push(format!(
+        "{lead}{source} = {}  [{}..{}]",
+        trace.value, loc.start, loc.end
+    ));
+
+    let branch = if trace.steps.is_empty() { "  " } else { "│ " };
+    for note in &trace.notes {
+        lines.push(format!("{indent}{branch}{note}"));
+    }
+    for (i, step) in trace.steps.iter().enumerate() {
+        let (lead, next) = match i + 1 == trace.steps.len() {
+            true => ("└─ ", "   "),
+            false => ("├─ ", "│  "),
+        };
+        let lead = format!("{indent}{lead}");
+        push_trace(input, step, &lead, &format!("{indent}{next}"), lines);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bin_format() {
+        let format = BinFormat::default();
+        assert_eq!(format.format(0x123), "00000001 00100011");
+        assert_eq!(format.ruler(0x123), "15       7      0");
+
+        let format = BinFormat {
+            width: Some(12),
+            group: 4,
+            separator: Separator::Underscore,
+            ..format
+        };
+        assert_eq!(format.format(0x123), "0001_0010_0011");
+        assert_eq!(format.ruler(0x123), "11   7    3  0");
+        assert_eq!(format.format(0x12345), "1_0010_0011_0100_0101");
+        assert_eq!(format.ruler(0x12345), "16     11   7    3  0");
+    }
+
+    #[test]
+    fn test_as_csv() {
+        let rows = [
+            vec!["i".into(), "value".into()],
+            vec!["0".into(), "CTRL { EN: 1, S: \"a\" }".into()],
+        ];
+        assert_eq!(
+            as_csv(&rows),
+            ["i,value", "0,\"CTRL { EN: 1, S: \"\"a\"\" }\""]
+        );
+    }
+}
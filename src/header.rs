@@ -0,0 +1,363 @@
+use std::{ops::Range, path::Path, sync::Arc};
+
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use thiserror::Error;
+
+use crate::interpreter::Interpreter;
+
+/// A constant of a C header that could not be imported.
+#[derive(Debug, Error, Diagnostic)]
+#[error("Skipped {name}")]
+#[diagnostic(severity(Warning))]
+pub struct Skipped {
+    name: String,
+    reason: String,
+    #[source_code]
+    src: NamedSource<Arc<str>>,
+    #[label("{reason}")]
+    span: SourceSpan,
+}
+
+/// A constant defined by a C header, with its value translated into an expression of the interpreter or
+/// the reason why it is not supported.
+#[derive(Debug, PartialEq)]
+struct Definition {
+    name: String,
+    expr: Result<String, &'static str>,
+    span: Range<usize>,
+}
+
+/// Imports the constants `#define`d by the C header at `path` and the members of its `enum`s into
+/// `interpreter`, returning the number of imported constants along with the skipped ones.
+pub fn include(
+    path: &Path,
+    interpreter: &mut Interpreter,
+) -> std::io::Result<(usize, Vec<Skipped>)> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(import(
+        &path.display().to_string(),
+        text.into(),
+        interpreter,
+    ))
+}
+
+/// Imports the constants of the C header `text`, named `name` in warnings, into `interpreter`.
+fn import(name: &str, text: Arc<str>, interpreter: &mut Interpreter) -> (usize, Vec<Skipped>) {
+    let src = NamedSource::new(name, text.clone());
+    let mut count = 0;
+    let mut skipped = Vec::new();
+    for def in scan(&text) {
+        let value = def.expr.map_err(str::to_string).and_then(|expr| {
+            let value = interpreter.interpret(&expr).map_err(|e| {
+                // The cause of syntax errors is more telling than the error itself
+                std::error::Error::source(&e).map_or(e.to_string(), ToString::to_string)
+            })?;
+            Ok((expr, value))
+        });
+        match value {
+            Ok((expr, value)) => {
+                interpreter.define_value(def.name, expr, value);
+                count += 1;
+            }
+            Err(reason) => skipped.push(Skipped {
+                name: def.name,
+                reason,
+                src: src.clone(),
+                span: def.span.into(),
+            }),
+        }
+    }
+    (count, skipped)
+}
+
+/// Returns the constants defined by the C header `text`, in the order of their definitions.
+fn scan(text: &str) -> Vec<Definition> {
+    let code = strip_comments(text);
+    let mut defs = defines(&code);
+    defs.extend(enum_members(&code));
+    defs.sort_by_key(|def| def.span.start);
+    defs
+}
+
+/// Replaces comments and line continuations in `text` by spaces, keeping the positions of all other
+/// characters.
+fn strip_comments(text: &str) -> String {
+    let mut code = text.as_bytes().to_vec();
+    let mut i = 0;
+    while i < code.len() {
+        // Comments keep their line breaks, while line continuations join their lines
+        let (len, keep_lines) = match &code[i..] {
+            [b'/', b'/', rest @ ..] => {
+                let len = rest.iter().position(|c| *c == b'\n').unwrap_or(rest.len());
+                (2 + len, true)
+            }
+            [b'/', b'*', rest @ ..] => {
+                let len = rest.windows(2).position(|w| w == b"*/");
+                (2 + len.map_or(rest.len(), |len| len + 2), true)
+            }
+            [b'\\', b'\r', b'\n', ..] => (3, false),
+            [b'\\', b'\n', ..] => (2, false),
+            [quote @ (b'"' | b'\''), rest @ ..] => {
+                i += 1 + literal_len(*quote, rest);
+                continue;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+
+        for c in code[i..i + len]
+            .iter_mut()
+            .filter(|c| !keep_lines || **c != b'\n')
+        {
+            *c = b' ';
+        }
+        i += len;
+    }
+
+    // Comments are blanked out as a whole, so all remaining multi-byte characters are intact
+    String::from_utf8(code).unwrap_or_default()
+}
+
+/// Returns the length of the rest of a string or character literal after its opening `quote`, including
+/// the closing one.
+fn literal_len(quote: u8, rest: &[u8]) -> usize {
+    let mut escaped = false;
+    for (i, c) in rest.iter().enumerate() {
+        match c {
+            b'\n' => return i,
+            c if *c == quote && !escaped => return i + 1,
+            _ => escaped = *c == b'\\' && !escaped,
+        }
+    }
+    rest.len()
+}
+
+fn identifier_len(s: &str) -> usize {
+    match s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        true => s
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(s.len()),
+        false => 0,
+    }
+}
+
+/// Returns the object-like macros defined by `#define NAME <expr>` in `code`, skipping empty ones like
+/// include guards.
+fn defines(code: &str) -> Vec<Definition> {
+    let mut defs = Vec::new();
+    let mut line_start = 0;
+    for line in code.split('\n') {
+        let start = line_start;
+        line_start += line.len() + 1;
+
+        let Some(rest) = line.trim_start().strip_prefix('#') else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let Some(rest) = rest
+            .strip_prefix("define")
+            .filter(|rest| rest.starts_with(|c: char| c.is_ascii_whitespace()))
+        else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let name_len = identifier_len(rest);
+        if name_len == 0 {
+            continue;
+        }
+
+        let name_start = start + line.len() - rest.len();
+        let value = &rest[name_len..];
+        let expr = match value.trim() {
+            _ if value.starts_with('(') => Err("Function-like macros are not supported"),
+            "" => continue,
+            value => Ok(translate(value)),
+        };
+        defs.push(Definition {
+            name: rest[..name_len].into(),
+            expr,
+            span: name_start..start + line.trim_end().len(),
+        });
+    }
+    defs
+}
+
+/// Returns the position of the next occurrence of the keyword `word` in `code` after `from`.
+fn find_keyword(code: &str, word: &str, from: usize) -> Option<usize> {
+    let is_word_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    code[from..]
+        .match_indices(word)
+        .map(|(pos, _)| from + pos)
+        .find(|pos| {
+            !code[..*pos].ends_with(is_word_char)
+                && !code[pos + word.len()..].starts_with(is_word_char)
+        })
+}
+
+/// Returns the members of the `enum`s in `code`, numbered like C does, i.e. counting up from the previous
+/// member or from 0.
+fn enum_members(code: &str) -> Vec<Definition> {
+    let mut defs = Vec::new();
+    let mut from = 0;
+    while let Some(pos) = find_keyword(code, "enum", from) {
+        from = pos + "enum".len();
+
+        // The enum may be named, e.g. `enum mode { ... }`, but not just a type, e.g. `enum mode m;`
+        let rest = code[from..].trim_start();
+        let Some(body) = rest[identifier_len(rest)..].trim_start().strip_prefix('{') else {
+            continue;
+        };
+        let body_start = code.len() - body.len();
+        let Some(body_len) = body.find('}') else {
+            break;
+        };
+        from = body_start + body_len;
+
+        let mut previous: Option<&str> = None;
+        let mut member_start = body_start;
+        for member in body[..body_len].split(',') {
+            let start = member_start + member.len() - member.trim_start().len();
+            member_start += member.len() + 1;
+            let member = member.trim();
+            if member.is_empty() {
+                continue;
+            }
+
+            let (name, value) = match member.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim())),
+                None => (member, None),
+            };
+            let expr = match (value, previous) {
+                _ if identifier_len(name) != name.len() => Err("Unsupported enum member"),
+                (Some(value), _) => Ok(translate(value)),
+                (None, Some(previous)) => Ok(format!("{previous} + 1")),
+                (None, None) => Ok("0".into()),
+            };
+            defs.push(Definition {
+                name: name.into(),
+                expr,
+                span: start..start + member.len(),
+            });
+            previous = Some(name);
+        }
+    }
+    defs
+}
+
+/// Translates the C integer and float literals in `expr` into the syntax of the interpreter, i.e. drops
+/// their suffixes like `UL` and prefixes octal literals with `0o`.
+fn translate(expr: &str) -> String {
+    let mut result = String::new();
+    let mut rest = expr;
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '0'..='9' => {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+                    .unwrap_or(rest.len());
+                result.push_str(&translate_number(&rest[..len]));
+                rest = &rest[len..];
+                continue;
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => identifier_len(rest),
+            '"' | '\'' => 1 + literal_len(c as u8, &rest.as_bytes()[1..]),
+            c => c.len_utf8(),
+        };
+        result.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    result
+}
+
+fn translate_number(literal: &str) -> String {
+    let lower = literal.to_ascii_lowercase();
+    if let Some(digits) = lower
+        .strip_prefix("0x")
+        .or_else(|| lower.strip_prefix("0b"))
+    {
+        let prefix = &lower[..2];
+        return format!("{prefix}{}", digits.trim_end_matches(['u', 'l']));
+    }
+    if lower.contains('.') {
+        return lower.trim_end_matches(['f', 'l']).to_string();
+    }
+
+    let digits = lower.trim_end_matches(['u', 'l']);
+    match digits.strip_prefix('0') {
+        Some(octal) if !octal.is_empty() => format!("0o{octal}"),
+        _ => digits.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strip_comments() {
+        let text = "#define A 1 // one\n/* two\n € */ #define B \"/*\" \\\n  2";
+        let code = strip_comments(text);
+        assert_eq!(code.len(), text.len());
+        assert_eq!(
+            code,
+            "#define A 1       \n      \n        #define B \"/*\"     2"
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            translate("(0x1UL << RCC_CR_HSION_Pos)"),
+            "(0x1 << RCC_CR_HSION_Pos)"
+        );
+        assert_eq!(translate("017 | 0U | 10u"), "0o17 | 0 | 10");
+        assert_eq!(translate("0XFFu & 0B101"), "0xff & 0b101");
+        assert_eq!(translate("1.5f + X2 + '0'"), "1.5 + X2 + '0'");
+    }
+
+    #[test]
+    fn test_scan() {
+        let defs = scan(
+            "#ifndef REGS_H\n\
+             #define REGS_H\n\
+             #define PIN(n) (1 << (n))\n\
+             typedef enum {\n  OFF,\n  SLOW = 4,\n  FAST,\n} mode_t;\n\
+             enum mode m;\n\
+             # define MASK (SLOW | FAST)\n",
+        );
+        let defs = defs
+            .iter()
+            .map(|def| (def.name.as_str(), def.expr.clone(), def.span.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            defs,
+            [
+                ("PIN", Err("Function-like macros are not supported"), 38..55),
+                ("OFF", Ok("0".into()), 73..76),
+                ("SLOW", Ok("4".into()), 80..88),
+                ("FAST", Ok("SLOW + 1".into()), 92..96),
+                ("MASK", Ok("(SLOW | FAST)".into()), 130..148),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import() {
+        let mut interpreter = Interpreter::new();
+        let (count, skipped) = import(
+            "regs.h",
+            "#define SHIFT 4U\n#define MASK (0x3UL << SHIFT)\n#define BAD (uint32_t)1\n".into(),
+            &mut interpreter,
+        );
+        assert_eq!(count, 2);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].name, "BAD");
+        assert_eq!(skipped[0].reason, "Expected end of expression, found '1'");
+        assert_eq!(skipped[0].span, (55..70).into());
+
+        let value = interpreter.interpret("MASK >> SHIFT").unwrap();
+        assert_eq!(value.scalar().unwrap().int(), Some(3));
+    }
+}
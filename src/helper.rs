@@ -0,0 +1,444 @@
+use std::{borrow::Cow, cell::Cell, ops::Range};
+
+use colored::Colorize;
+use rustyline::{
+    completion::{Completer, FilenameCompleter, Pair},
+    highlight::{CmdKind, Highlighter},
+    hint::{Hint, Hinter},
+    validate::Validator,
+    Context, Helper,
+};
+
+use crate::{
+    commands::{Command, COMMANDS},
+    interpreter::{
+        lexer::Lexer,
+        token::{Token, TokenKind},
+        Interpreter,
+    },
+    settings::OPTIONS,
+};
+
+/// The unit prefixes, combined with `B` and `b` for completion.
+const UNIT_PREFIXES: &[&str] = &[
+    "", "k", "M", "G", "T", "P", "E", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei",
+];
+
+/// The highlighting style of a part of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Decimal,
+    Hex,
+    Octal,
+    Binary,
+    Unit,
+    Literal,
+    Address,
+    Keyword,
+    Operator,
+    Function,
+    Comment,
+    Bracket,
+    Error,
+}
+
+impl Style {
+    fn apply(self, text: &str) -> String {
+        let text = match self {
+            Style::Decimal => text.yellow(),
+            Style::Hex => text.magenta(),
+            Style::Octal => text.bright_magenta(),
+            Style::Binary => text.cyan(),
+            Style::Unit => text.green(),
+            Style::Literal => text.bright_green(),
+            Style::Address => text.bright_cyan(),
+            Style::Keyword => text.bold(),
+            Style::Operator => text.bright_blue(),
+            Style::Function => text.bright_white().bold(),
+            Style::Comment => text.bright_black(),
+            Style::Bracket => text.bright_blue().bold().underline(),
+            Style::Error => text.red(),
+        };
+        text.to_string()
+    }
+}
+
+/// Returns the style of `token`, whose text in the input is `text`, if it is highlighted.
+fn token_style(token: &Token, text: &str) -> Option<Style> {
+    use TokenKind as TK;
+
+    let style = match token.kind() {
+        TK::Integer(_) => match text.get(..2).map(str::to_ascii_lowercase).as_deref() {
+            _ if text.starts_with('\'') => Style::Literal,
+            Some("0x") => Style::Hex,
+            Some("0o") => Style::Octal,
+            Some("0b") => Style::Binary,
+            _ => Style::Decimal,
+        },
+        TK::Float(_) => Style::Decimal,
+        TK::Unit(_) => Style::Unit,
+        TK::Bytes(_) => Style::Literal,
+        TK::Address(..) => Style::Address,
+        TK::QFormat(_) | TK::As | TK::Layout | TK::Let => Style::Keyword,
+        TK::Identifier(name) if Interpreter::is_function(&name) => Style::Function,
+        TK::Identifier(_) | TK::Eof => return None,
+        TK::LeftParen
+        | TK::RightParen
+        | TK::LeftBracket
+        | TK::RightBracket
+        | TK::LeftBrace
+        | TK::RightBrace
+        | TK::Colon
+        | TK::Comma => return None,
+        _ => Style::Operator,
+    };
+    Some(style)
+}
+
+/// Returns the styled parts of `line`, in order, using the lexer of the interpreter so that highlighting
+/// agrees with what the parser accepts.
+///
+/// Everything from the first invalid token on is styled as an error. If the cursor at `pos` is on or
+/// right after a bracket, the bracket and its matching one are highlighted.
+fn styles(line: &str, pos: Option<usize>) -> Vec<(Range<usize>, Style)> {
+    let mut tokens = Vec::new();
+    let mut error = None;
+    for token in Lexer::new(line) {
+        match token {
+            Ok(token) if token.kind() == TokenKind::Eof => break,
+            Ok(token) => tokens.push(token),
+            Err(e) => {
+                error = Some(e.loc()..line.len());
+                break;
+            }
+        }
+    }
+
+    let brackets = pos.and_then(|pos| matching_brackets(&tokens, pos));
+    let mut styles = Vec::new();
+    let mut end = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        styles.extend(comment(line, end..token.loc().start));
+        end = token.loc().end;
+
+        let style = match brackets {
+            Some((open, close)) if i == open || i == close => Some(Style::Bracket),
+            _ => token_style(token, &line[token.loc()]),
+        };
+        styles.extend(style.map(|style| (token.loc(), style)));
+    }
+    match error {
+        Some(error) => styles.push((error, Style::Error)),
+        None => styles.extend(comment(line, end..line.len())),
+    }
+    styles
+}
+
+/// Returns the comment in the whitespace between two tokens of `line` at `gap`.
+fn comment(line: &str, gap: Range<usize>) -> Option<(Range<usize>, Style)> {
+    let start = gap.start + line[gap.clone()].find('#')?;
+    Some((start..gap.end, Style::Comment))
+}
+
+/// Returns the indices of the bracket token at or right before `pos` and its matching bracket, ordered
+/// by their position.
+fn matching_brackets(tokens: &[Token], pos: usize) -> Option<(usize, usize)> {
+    use TokenKind as TK;
+
+    let depth = |kind: TokenKind| match kind {
+        TK::LeftParen | TK::LeftBracket | TK::LeftBrace => 1,
+        TK::RightParen | TK::RightBracket | TK::RightBrace => -1,
+        _ => 0,
+    };
+    // Like in editors, the bracket under the cursor takes precedence over the one before it
+    let bracket_at = |pos: &dyn Fn(&Token) -> bool| {
+        tokens
+            .iter()
+            .enumerate()
+            .find(|(_, token)| depth(token.kind()) != 0 && pos(token))
+    };
+    let (index, token) = bracket_at(&|token| token.loc().start == pos)
+        .or_else(|| bracket_at(&|token| token.loc().end == pos))?;
+
+    let direction = depth(token.kind());
+    let mut level = 0;
+    let mut indices: Box<dyn Iterator<Item = usize>> = match direction {
+        1 => Box::new(index..tokens.len()),
+        _ => Box::new((0..=index).rev()),
+    };
+    let matching = indices.find(|i| {
+        level += depth(tokens[*i].kind()) * direction;
+        level == 0
+    })?;
+    Some((index.min(matching), index.max(matching)))
+}
+
+/// A preview of the result of the current line, shown after the cursor.
+pub struct Preview(String);
+
+impl Hint for Preview {
+    fn display(&self) -> &str {
+        &self.0
+    }
+
+    // Previews are not part of the input, so they can't be accepted like other hints
+    fn completion(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The rustyline helper of the REPL, which highlights, completes and bracket-matches its input and
+/// previews its result.
+pub struct ReplHelper {
+    /// A snapshot of the interpreter of the REPL, to preview results and complete names with.
+    interpreter: Interpreter,
+    files: FilenameCompleter,
+    /// Whether to highlight brackets, which is disabled for finished lines.
+    brackets: Cell<bool>,
+}
+
+impl ReplHelper {
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+            files: FilenameCompleter::new(),
+            brackets: Cell::new(true),
+        }
+    }
+
+    /// Updates the snapshot of the interpreter of the REPL, e.g. after a new layout was declared.
+    pub fn set_interpreter(&mut self, interpreter: &Interpreter) {
+        self.interpreter = interpreter.clone();
+    }
+
+    /// Returns the start and the candidates completing the word of `line` ending at `pos`.
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        if line.starts_with(':') && !line[..pos].contains(' ') {
+            let names = COMMANDS.iter().flat_map(|command| command.names);
+            let names = names.filter(|name| name.starts_with(&line[..pos]));
+            return (0, names.map(|name| name.to_string()).collect());
+        }
+        if let Some(option) = line[..pos].strip_prefix(":set ") {
+            if !option.contains(' ') {
+                let options = OPTIONS.iter().map(|(name, _)| name);
+                let options = options.filter(|name| name.starts_with(option));
+                return (5, options.map(|name| name.to_string()).collect());
+            }
+        }
+
+        let start = line[..pos]
+            .rfind(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+
+        // The word is a unit if it follows a number, e.g. `4Ki`
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            let number_len = word
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(word.len());
+            let (start, word) = (start + number_len, &word[number_len..]);
+            let units = UNIT_PREFIXES
+                .iter()
+                .flat_map(|prefix| [format!("{prefix}B"), format!("{prefix}b")])
+                .filter(|unit| unit.to_lowercase().starts_with(&word.to_lowercase()));
+            return (start, units.collect());
+        }
+
+        let names = self
+            .interpreter
+            .names()
+            .filter(|name| name.starts_with(word));
+        (start, names.map(String::from).collect())
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let command = line
+            .split_once(' ')
+            .and_then(|(name, _)| Command::find(name));
+        if command.is_some_and(|command| command.takes_file) {
+            return self.files.complete(line, pos, ctx);
+        }
+
+        let (start, candidates) = self.candidates(line, pos);
+        let candidates = candidates
+            .into_iter()
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        // Commands are not expressions
+        if line.starts_with(':') {
+            return Cow::Borrowed(line);
+        }
+
+        let pos = self.brackets.get().then_some(pos);
+        let mut highlighted = String::new();
+        let mut end = 0;
+        for (range, style) in styles(line, pos) {
+            highlighted.push_str(&line[end..range.start]);
+            highlighted.push_str(&style.apply(&line[range.clone()]));
+            end = range.end;
+        }
+        highlighted.push_str(&line[end..]);
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(hint.bright_black().to_string())
+    }
+
+    fn highlight_char(&self, line: &str, _pos: usize, kind: CmdKind) -> bool {
+        self.brackets.set(kind != CmdKind::ForcedRefresh);
+        !line.is_empty()
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = Preview;
+
+    /// Previews the result of the line, or why it has none, if the cursor is at its end.
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<Preview> {
+        if pos < line.len() || line.trim().is_empty() || line.starts_with(':') {
+            return None;
+        }
+        let preview = match self.interpreter.preview(line) {
+            Ok(value) => format!(" = {value}"),
+            // The cause of syntax errors is more telling than the error itself
+            Err(e) => {
+                let error =
+                    std::error::Error::source(&e).map_or(e.to_string(), ToString::to_string);
+                format!("  ({error})")
+            }
+        };
+        Some(Preview(preview))
+    }
+}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rustyline::history::DefaultHistory;
+
+    fn styled(line: &str, pos: Option<usize>) -> Vec<(&str, Style)> {
+        styles(line, pos)
+            .into_iter()
+            .map(|(range, style)| (&line[range], style))
+            .collect()
+    }
+
+    #[test]
+    fn test_styles() {
+        assert_eq!(
+            styled("0x1f + 0b1 * 4KiB as u8 # mask", None),
+            [
+                ("0x1f", Style::Hex),
+                ("+", Style::Operator),
+                ("0b1", Style::Binary),
+                ("*", Style::Operator),
+                ("4", Style::Decimal),
+                ("KiB", Style::Unit),
+                ("as", Style::Keyword),
+                ("# mask", Style::Comment),
+            ]
+        );
+        assert_eq!(
+            styled("hex(\"ab\") + 0o9", None),
+            [
+                ("hex", Style::Function),
+                ("\"ab\"", Style::Literal),
+                ("+", Style::Operator),
+                ("9", Style::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_styles_brackets() {
+        let line = "(1 + (2)) * [3]";
+        assert_eq!(
+            styled(line, Some(0)),
+            [
+                ("(", Style::Bracket),
+                ("1", Style::Decimal),
+                ("+", Style::Operator),
+                ("2", Style::Decimal),
+                (")", Style::Bracket),
+                ("*", Style::Operator),
+                ("3", Style::Decimal),
+            ]
+        );
+        assert_eq!(
+            styled(line, Some(7)),
+            [
+                ("1", Style::Decimal),
+                ("+", Style::Operator),
+                ("(", Style::Bracket),
+                ("2", Style::Decimal),
+                (")", Style::Bracket),
+                ("*", Style::Operator),
+                ("3", Style::Decimal),
+            ]
+        );
+        assert_eq!(styled("(1", Some(0))[0], ("1", Style::Decimal));
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("layout CTRL { EN: 0 }").unwrap();
+        let mut helper = ReplHelper::new();
+        helper.set_interpreter(&interpreter);
+        assert_eq!(
+            helper.candidates("1 + ho", 6),
+            (4, vec!["hostmask".to_string(), "hosts".to_string()])
+        );
+        assert_eq!(
+            helper.candidates("0 as CT", 7),
+            (5, vec!["CTRL".to_string()])
+        );
+        assert_eq!(
+            helper.candidates(":load-r", 7),
+            (0, vec![":load-regs".to_string()])
+        );
+        assert_eq!(
+            helper.candidates(":in", 3),
+            (0, vec![":include".to_string()])
+        );
+        assert_eq!(
+            helper.candidates("4ki", 3),
+            (1, vec!["KiB".to_string(), "Kib".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_hint() {
+        let helper = ReplHelper::new();
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+        let hint = |line: &str| helper.hint(line, line.len(), &ctx).map(|hint| hint.0);
+        assert_eq!(hint("3.5 GiB as MB"), Some(" = 3758.096384MB".into()));
+        assert_eq!(hint("1 + foo"), Some("  (Unknown variable)".into()));
+        assert_eq!(hint(":help"), None);
+        assert_eq!(helper.hint("1 + 2", 1, &ctx).map(|hint| hint.0), None);
+    }
+}
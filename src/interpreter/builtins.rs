@@ -0,0 +1,459 @@
+use super::{
+    byte_order::swap_bytes,
+    bytes,
+    char_type::CharType,
+    diff::Diff,
+    int_type::IntType,
+    ip_type::{IpType, IpVersion},
+    num::Number,
+    value::{Scalar, Type, Value, ValueErrorKind},
+};
+
+type Builtin = fn(&[Value]) -> Result<Value, ValueErrorKind>;
+
+/// All built-in functions, with their name and number of arguments.
+const BUILTINS: &[(&str, usize, Builtin)] = &[
+    ("sext", 2, sext),
+    ("zext", 2, zext),
+    ("trunc", 2, trunc),
+    ("bswap16", 1, bswap::<16>),
+    ("bswap32", 1, bswap::<32>),
+    ("bswap64", 1, bswap::<64>),
+    ("hex", 1, hex),
+    ("unhex", 1, unhex),
+    ("base64", 1, base64),
+    ("unbase64", 1, unbase64),
+    ("utf8", 1, utf8),
+    ("utf16", 1, utf16),
+    ("netmask", 1, netmask),
+    ("hostmask", 1, hostmask),
+    ("network", 2, network),
+    ("broadcast", 1, broadcast),
+    ("hosts", 1, hosts),
+    ("contains", 2, contains),
+    ("decode", 2, decode),
+    ("diff", 2, diff),
+];
+
+/// Returns the names of all built-in functions.
+pub fn names<'a>() -> impl Iterator<Item = &'a str> {
+    BUILTINS.iter().map(|(name, _, _)| -> &'a str { name })
+}
+
+/// Calls the built-in function `name` with `args`.
+pub fn call(name: &str, args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let (_, arity, builtin) = BUILTINS
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .ok_or(ValueErrorKind::UnknownFunction)?;
+
+    if args.len() != *arity {
+        return Err(ValueErrorKind::WrongArgumentCount {
+            expected: *arity,
+            found: args.len(),
+        });
+    }
+
+    builtin(args)
+}
+
+/// Returns the integer value of `value`, which has to be a unitless integer.
+fn bitwise_int(value: &Value) -> Result<i128, ValueErrorKind> {
+    let value = value.scalar()?;
+    if value.unit().is_some() {
+        return Err(ValueErrorKind::BitwiseWithUnit);
+    }
+
+    value.try_int()
+}
+
+fn bit_width(value: &Value) -> Result<u32, ValueErrorKind> {
+    match bitwise_int(value)? {
+        bits @ 1..=128 => Ok(bits as u32),
+        _ => Err(ValueErrorKind::InvalidBitWidth),
+    }
+}
+
+/// `sext(x, from_bits)`: Sign extends the lowest `from_bits` bits of `x` into the type of `x`.
+fn sext(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let (value, from_bits) = (bitwise_int(&args[0])?, bit_width(&args[1])?);
+    let value = IntType::signed(from_bits).wrap(value);
+    Ok(Scalar::typed(Number::Int(value), None, args[0].ty()).into())
+}
+
+/// `zext(x, from_bits)`: Zero extends the lowest `from_bits` bits of `x` into the type of `x`.
+fn zext(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let (value, from_bits) = (bitwise_int(&args[0])?, bit_width(&args[1])?);
+    let value = IntType::unsigned(from_bits).wrap(value);
+    Ok(Scalar::typed(Number::Int(value), None, args[0].ty()).into())
+}
+
+/// `trunc(x, bits)`: Truncates `x` to its lowest `bits` bits, keeping the signedness of its type.
+fn trunc(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let (value, bits) = (bitwise_int(&args[0])?, bit_width(&args[1])?);
+    let signed = matches!(args[0].ty(), Some(Type::Int(ty)) if ty.is_signed());
+    let ty = IntType::new(signed, bits);
+    Ok(Scalar::typed(Number::Int(value), None, Some(ty.into())).into())
+}
+
+/// `bswapN(x)`: Reverses the order of the lowest `N / 8` bytes of `x`, keeping the type of `x`.
+fn bswap<const BITS: u32>(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let value = bitwise_int(&args[0])?;
+    let value = swap_bytes(value as u128, BITS / 8) as i128;
+    Ok(Scalar::typed(Number::Int(value), None, args[0].ty()).into())
+}
+
+/// `hex(bytes)`: Encodes a byte array as a string of hex digits.
+fn hex(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    Ok(Value::Bytes(bytes::hex(args[0].as_bytes()?)))
+}
+
+/// `unhex(string)`: Decodes a string of hex digits into a byte array.
+fn unhex(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    bytes::unhex(args[0].as_bytes()?)
+        .map(Value::Bytes)
+        .ok_or(ValueErrorKind::InvalidEncoding("hex string"))
+}
+
+/// `base64(bytes)`: Encodes a byte array as a base64 string.
+fn base64(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    Ok(Value::Bytes(bytes::base64(args[0].as_bytes()?)))
+}
+
+/// `unbase64(string)`: Decodes a base64 string into a byte array.
+fn unbase64(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    bytes::unbase64(args[0].as_bytes()?)
+        .map(Value::Bytes)
+        .ok_or(ValueErrorKind::InvalidEncoding("base64 string"))
+}
+
+/// `utf8(x)`: Views the code point or UTF-8 encoded byte array `x` as its UTF-8 encoding.
+fn utf8(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    args[0].clone().try_char(CharType::Utf8)
+}
+
+/// `utf16(x)`: Views the code point or big endian UTF-16 encoded byte array `x` as its UTF-16 encoding.
+fn utf16(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    args[0].clone().try_char(CharType::Utf16)
+}
+
+/// Returns the type and bits of `value`, which has to be an IP address.
+fn address(value: &Value) -> Result<(IpType, u128), ValueErrorKind> {
+    let value = value.scalar()?;
+    match (value.ty(), value.bits()) {
+        (Some(Type::Ip(ty)), Some(bits)) => Ok((ty, bits)),
+        _ => Err(ValueErrorKind::ExpectedAddress),
+    }
+}
+
+/// Returns the type of the network given by `value`, which is either an IP network or the prefix length of
+/// an IPv4 network.
+fn network_type(value: &Value) -> Result<IpType, ValueErrorKind> {
+    if let Ok((ty, _)) = address(value) {
+        return Ok(ty);
+    }
+    prefix(IpType::new(IpVersion::V4), value)
+}
+
+/// Returns `ty` with the prefix length given by `value`.
+fn prefix(ty: IpType, value: &Value) -> Result<IpType, ValueErrorKind> {
+    u32::try_from(bitwise_int(value)?)
+        .ok()
+        .and_then(|prefix| ty.with_prefix(prefix))
+        .ok_or(ValueErrorKind::InvalidPrefix { bits: ty.bits() })
+}
+
+fn ip(bits: u128, ty: IpType) -> Value {
+    Scalar::typed(Number::Int(bits as i128), None, Some(ty.into())).into()
+}
+
+/// `netmask(net)`: Returns the netmask of an IP network or of an IPv4 prefix length.
+fn netmask(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let ty = network_type(&args[0])?;
+    Ok(ip(ty.netmask(), ty.address()))
+}
+
+/// `hostmask(net)`: Returns the hostmask of an IP network or of an IPv4 prefix length.
+fn hostmask(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let ty = network_type(&args[0])?;
+    Ok(ip(ty.hostmask(), ty.address()))
+}
+
+/// `network(addr, prefix)`: Returns the network with the prefix length `prefix` containing `addr`.
+fn network(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let (ty, bits) = address(&args[0])?;
+    let ty = prefix(ty, &args[1])?;
+    Ok(ip(bits & ty.netmask(), ty))
+}
+
+/// `broadcast(net)`: Returns the last address of an IP network.
+fn broadcast(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let (ty, bits) = address(&args[0])?;
+    Ok(ip(bits | ty.hostmask(), ty.address()))
+}
+
+/// `hosts(net)`: Returns the number of usable host addresses of an IP network. Fails for IPv6 networks
+/// with more hosts than fit into an integer, i.e. `/0` and `/1` ones.
+fn hosts(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let (ty, _) = address(&args[0])?;
+    let hosts = ty.hosts().and_then(|hosts| i128::try_from(hosts).ok());
+    let hosts = hosts.ok_or(ValueErrorKind::TooManyHosts)?;
+    Ok(Scalar::new(hosts, None).into())
+}
+
+/// `contains(net, addr)`: Returns 1 if the IP network `net` contains `addr` and 0 otherwise.
+fn contains(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    let ((net_ty, net), (addr_ty, addr)) = (address(&args[0])?, address(&args[1])?);
+    if net_ty.version() != addr_ty.version() {
+        return Err(ValueErrorKind::IpVersionMismatch);
+    }
+    let contained = net & net_ty.netmask() == addr & net_ty.netmask();
+    Ok(Scalar::new(contained as i128, None).into())
+}
+
+/// `decode(layout, x)`: Decodes `x` according to a register layout.
+fn decode(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    match &args[0] {
+        Value::Layout(layout) => args[1].clone().try_decode(layout),
+        _ => Err(ValueErrorKind::ExpectedLayout),
+    }
+}
+
+/// `diff(a, b)`: Compares the bits of `a` and `b`, naming them after the fields of either if it is a
+/// bitfield.
+fn diff(args: &[Value]) -> Result<Value, ValueErrorKind> {
+    Diff::try_new(args[0].clone(), args[1].clone()).map(Value::Diff)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn int(value: i128) -> Value {
+        Scalar::new(value, None).into()
+    }
+
+    fn typed(value: i128, ty: IntType) -> Value {
+        Scalar::new(value, None).cast(ty).into()
+    }
+
+    #[test]
+    fn test_call_errors() {
+        assert_eq!(call("nope", &[]), Err(ValueErrorKind::UnknownFunction));
+        assert_eq!(
+            call("sext", &[int(1)]),
+            Err(ValueErrorKind::WrongArgumentCount {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            call("sext", &[int(1), int(0)]),
+            Err(ValueErrorKind::InvalidBitWidth)
+        );
+        assert_eq!(
+            call("zext", &[int(1), Scalar::new(1.5, None).into()]),
+            Err(ValueErrorKind::NotAnInteger)
+        );
+    }
+
+    #[test]
+    fn test_sext() {
+        assert_eq!(
+            call("sext", &[int(0xfff), int(12)])
+                .unwrap()
+                .into_scalar()
+                .unwrap()
+                .int(),
+            Some(-1)
+        );
+        assert_eq!(
+            call("sext", &[int(0x7ff), int(12)])
+                .unwrap()
+                .into_scalar()
+                .unwrap()
+                .int(),
+            Some(0x7ff)
+        );
+
+        let value = typed(0x800, IntType::unsigned(32));
+        let value = call("sext", &[value, int(12)])
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.bits(), Some(0xffff_f800));
+        assert_eq!(value.ty(), Some(IntType::unsigned(32).into()));
+    }
+
+    #[test]
+    fn test_zext() {
+        assert_eq!(
+            call("zext", &[int(-1), int(12)])
+                .unwrap()
+                .into_scalar()
+                .unwrap()
+                .int(),
+            Some(0xfff)
+        );
+
+        let value = typed(-1, IntType::signed(32));
+        let value = call("zext", &[value, int(8)])
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0xff));
+        assert_eq!(value.ty(), Some(IntType::signed(32).into()));
+    }
+
+    #[test]
+    fn test_trunc() {
+        let value = call("trunc", &[int(0x1234), int(8)])
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x34));
+        assert_eq!(value.ty(), Some(IntType::unsigned(8).into()));
+
+        let value = typed(0xff, IntType::signed(32));
+        let value = call("trunc", &[value, int(8)])
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(-1));
+        assert_eq!(value.ty(), Some(IntType::signed(8).into()));
+    }
+
+    fn ip(text: &str) -> Value {
+        crate::interpreter::Interpreter::new()
+            .interpret(text)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_networks() {
+        assert_eq!(call("netmask", &[int(24)]), Ok(ip("255.255.255.0")));
+        assert_eq!(
+            call("hostmask", &[ip("10.0.0.0/8")]),
+            Ok(ip("0.255.255.255"))
+        );
+        assert_eq!(
+            call("netmask", &[int(33)]),
+            Err(ValueErrorKind::InvalidPrefix { bits: 32 })
+        );
+        assert_eq!(
+            call("network", &[int(1), int(8)]),
+            Err(ValueErrorKind::ExpectedAddress)
+        );
+
+        assert_eq!(call("hosts", &[ip("192.168.0.0/24")]), Ok(int(254)));
+        assert_eq!(call("hosts", &[ip("10.0.0.0/31")]), Ok(int(2)));
+        assert_eq!(call("hosts", &[ip("fe80::/64")]), Ok(int(1 << 64)));
+        assert_eq!(call("hosts", &[ip("::/2")]), Ok(int(1 << 126)));
+        assert_eq!(
+            call("hosts", &[ip("8000::/1")]),
+            Err(ValueErrorKind::TooManyHosts)
+        );
+        assert_eq!(
+            call("hosts", &[ip("::/0")]),
+            Err(ValueErrorKind::TooManyHosts)
+        );
+
+        assert_eq!(
+            call("contains", &[ip("10.0.0.0/8"), ip("10.255.0.1")]),
+            Ok(int(1))
+        );
+        assert_eq!(
+            call("contains", &[ip("10.0.0.0/8"), ip("11.0.0.1")]),
+            Ok(int(0))
+        );
+        assert_eq!(
+            call("contains", &[ip("::/0"), ip("11.0.0.1")]),
+            Err(ValueErrorKind::IpVersionMismatch)
+        );
+    }
+
+    #[test]
+    fn test_encodings() {
+        let bytes = Value::Bytes(vec![0xde, 0xad]);
+        assert_eq!(call("hex", &[bytes]), Ok(Value::Bytes(b"dead".to_vec())));
+        assert_eq!(
+            call("unhex", &[Value::Bytes(b"dead".to_vec())]),
+            Ok(Value::Bytes(vec![0xde, 0xad]))
+        );
+        assert_eq!(
+            call("base64", &[Value::Bytes(b"hi".to_vec())]),
+            Ok(Value::Bytes(b"aGk=".to_vec()))
+        );
+        assert_eq!(
+            call("unbase64", &[Value::Bytes(b"aGk=".to_vec())]),
+            Ok(Value::Bytes(b"hi".to_vec()))
+        );
+
+        assert_eq!(
+            call("unhex", &[Value::Bytes(b"xyz".to_vec())]),
+            Err(ValueErrorKind::InvalidEncoding("hex string"))
+        );
+        assert_eq!(call("hex", &[int(1)]), Err(ValueErrorKind::ExpectedBytes));
+        assert_eq!(
+            call("sext", &[Value::Bytes(vec![1]), int(1)]),
+            Err(ValueErrorKind::ExpectedNumber)
+        );
+    }
+
+    #[test]
+    fn test_utf() {
+        let value = call("utf8", &[int(0x1f600)])
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.bits(), Some(0xf09f_9880));
+        assert_eq!(value.ty(), Some(CharType::Utf8.into()));
+
+        let value = call("utf16", &[int(0x1f600)])
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.bits(), Some(0xd83d_de00));
+
+        let value = call("utf16", &[Value::Bytes(vec![0x20, 0xac])]).unwrap();
+        assert_eq!(value.into_scalar().unwrap().char(), Some('€'));
+
+        assert_eq!(
+            call("utf8", &[int(-1)]),
+            Err(ValueErrorKind::InvalidCodePoint)
+        );
+    }
+
+    #[test]
+    fn test_bswap() {
+        assert_eq!(
+            call("bswap16", &[int(0x1234)])
+                .unwrap()
+                .into_scalar()
+                .unwrap()
+                .int(),
+            Some(0x3412)
+        );
+        assert_eq!(
+            call("bswap32", &[int(0x1234_5678)])
+                .unwrap()
+                .into_scalar()
+                .unwrap()
+                .int(),
+            Some(0x7856_3412)
+        );
+        assert_eq!(
+            call("bswap64", &[int(0x0102_0304_0506_0708)])
+                .unwrap()
+                .into_scalar()
+                .unwrap()
+                .int(),
+            Some(0x0807_0605_0403_0201)
+        );
+
+        let value = typed(0xff, IntType::signed(32));
+        let value = call("bswap32", &[value]).unwrap().into_scalar().unwrap();
+        assert_eq!(value.int(), Some(-0x0100_0000));
+        assert_eq!(value.ty(), Some(IntType::signed(32).into()));
+    }
+}
@@ -0,0 +1,82 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use super::int_type::mask;
+
+/// The order of the bytes of a value in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    Little,
+    Big,
+}
+
+/// Reverses the order of the lowest `bytes` bytes of `bits`, discarding all higher bytes.
+pub const fn swap_bytes(bits: u128, bytes: u32) -> u128 {
+    match bytes {
+        0 => 0,
+        _ => (bits & mask(bytes * 8)).swap_bytes() >> (128 - bytes * 8),
+    }
+}
+
+impl ByteOrder {
+    /// Returns the lowest `bytes` bytes of `bits` in memory order.
+    pub fn to_bytes(self, bits: u128, bytes: u32) -> Vec<u8> {
+        let le = bits.to_le_bytes();
+        let mut bytes = le[..bytes as usize].to_vec();
+        if self == Self::Big {
+            bytes.reverse();
+        }
+        bytes
+    }
+}
+
+impl TryFrom<&str> for ByteOrder {
+    type Error = miette::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let val = match value {
+            "le" => Self::Little,
+            "be" => Self::Big,
+            _ => miette::bail!("Invalid byte order: {}", value),
+        };
+        Ok(val)
+    }
+}
+
+impl Display for ByteOrder {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Little => write!(f, "LE"),
+            Self::Big => write!(f, "BE"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_byte_order_from_str() {
+        assert_eq!(ByteOrder::try_from("le").unwrap(), ByteOrder::Little);
+        assert_eq!(ByteOrder::try_from("be").unwrap(), ByteOrder::Big);
+        assert!(ByteOrder::try_from("me").is_err());
+    }
+
+    #[test]
+    fn test_swap_bytes() {
+        assert_eq!(swap_bytes(0x1234_5678, 4), 0x7856_3412);
+        assert_eq!(swap_bytes(0x1234_5678, 2), 0x7856);
+        assert_eq!(swap_bytes(0x12, 1), 0x12);
+        assert_eq!(swap_bytes(u128::MAX - 0xff, 16), u128::MAX >> 8);
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        assert_eq!(
+            ByteOrder::Little.to_bytes(0x1234_5678, 4),
+            [0x78, 0x56, 0x34, 0x12]
+        );
+        assert_eq!(ByteOrder::Big.to_bytes(0x1234, 4), [0, 0, 0x12, 0x34]);
+    }
+}
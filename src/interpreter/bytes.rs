@@ -0,0 +1,92 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Encodes `bytes` as lowercase hex digits.
+pub fn hex(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .flat_map(|b| format!("{b:02x}").into_bytes())
+        .collect()
+}
+
+/// Decodes hex digits into bytes, ignoring ASCII whitespace between them.
+///
+/// Returns `None` if `text` contains anything but hex digits or an odd number of them.
+pub fn unhex(text: &[u8]) -> Option<Vec<u8>> {
+    let digits = text
+        .iter()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| (*c as char).to_digit(16).map(|d| d as u8))
+        .collect::<Option<Vec<_>>>()?;
+
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    Some(
+        digits
+            .chunks(2)
+            .map(|pair| pair[0] << 4 | pair[1])
+            .collect(),
+    )
+}
+
+/// Encodes `bytes` as standard, padded base64.
+pub fn base64(bytes: &[u8]) -> Vec<u8> {
+    STANDARD.encode(bytes).into_bytes()
+}
+
+/// Decodes standard, padded base64 into bytes, ignoring ASCII whitespace.
+pub fn unbase64(text: &[u8]) -> Option<Vec<u8>> {
+    let text = text
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    STANDARD.decode(text).ok()
+}
+
+/// Formats `bytes` as a literal that lexes back into them, i.e. `b"..."` for printable ASCII and `x"..."`
+/// otherwise.
+pub fn literal(bytes: &[u8]) -> String {
+    let printable = bytes.iter().all(|b| matches!(b, b' '..=b'~'));
+    if bytes.is_empty() || !printable {
+        return format!("x\"{}\"", String::from_utf8_lossy(&hex(bytes)));
+    }
+
+    let escaped = bytes.iter().fold(String::new(), |mut acc, b| {
+        if matches!(b, b'"' | b'\\') {
+            acc.push('\\');
+        }
+        acc.push(*b as char);
+        acc
+    });
+    format!("b\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(&[0xde, 0xad, 0x01]), b"dead01");
+        assert_eq!(unhex(b"DEAD01"), Some(vec![0xde, 0xad, 0x01]));
+        assert_eq!(unhex(b"de ad\n01"), Some(vec![0xde, 0xad, 0x01]));
+        assert_eq!(unhex(b"dea"), None);
+        assert_eq!(unhex(b"xy"), None);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b"abcd"), b"YWJjZA==");
+        assert_eq!(unbase64(b"YWJj\nZA=="), Some(b"abcd".to_vec()));
+        assert_eq!(unbase64(b"YWJjZA="), None);
+    }
+
+    #[test]
+    fn test_literal() {
+        assert_eq!(literal(b"abc"), "b\"abc\"");
+        assert_eq!(literal(b"a\"\\"), "b\"a\\\"\\\\\"");
+        assert_eq!(literal(&[0x12, 0x34]), "x\"1234\"");
+        assert_eq!(literal(&[]), "x\"\"");
+    }
+}
@@ -0,0 +1,162 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use super::byte_order::ByteOrder;
+
+/// A view of a Unicode code point, either as the character itself or as one of its encodings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharType {
+    /// The character, stored as its UTF-32 code point.
+    Char,
+    Utf8,
+    Utf16,
+}
+
+impl CharType {
+    /// Returns the number of bytes of `c` in this encoding.
+    pub fn byte_width(self, c: char) -> u32 {
+        match self {
+            Self::Char => 4,
+            Self::Utf8 => c.len_utf8() as u32,
+            Self::Utf16 => c.len_utf16() as u32 * 2,
+        }
+    }
+
+    /// Returns the encoding of `c`, with its first byte or code unit being the most significant one.
+    pub fn encode(self, c: char) -> u128 {
+        match self {
+            Self::Char => c as u128,
+            Self::Utf8 => c
+                .encode_utf8(&mut [0; 4])
+                .bytes()
+                .fold(0, |acc, b| acc << 8 | b as u128),
+            Self::Utf16 => c
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .fold(0, |acc, unit| acc << 16 | *unit as u128),
+        }
+    }
+
+    /// Returns the bytes of the encoding of `c` in memory order. Byte order only applies to UTF-32 and to
+    /// the bytes within UTF-16 code units.
+    pub fn to_bytes(self, c: char, order: ByteOrder) -> Vec<u8> {
+        match self {
+            Self::Char => order.to_bytes(c as u128, 4),
+            Self::Utf8 => c.encode_utf8(&mut [0; 4]).as_bytes().to_vec(),
+            Self::Utf16 => c
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .flat_map(|unit| order.to_bytes(*unit as u128, 2))
+                .collect(),
+        }
+    }
+
+    /// Decodes `bytes` holding exactly one character, reading UTF-16 code units as big endian.
+    pub fn decode(self, bytes: &[u8]) -> Option<char> {
+        let decoded = match self {
+            Self::Char => {
+                let bytes = <[u8; 4]>::try_from(bytes).ok()?;
+                return char::from_u32(u32::from_be_bytes(bytes));
+            }
+            Self::Utf8 => std::str::from_utf8(bytes).ok()?.to_string(),
+            Self::Utf16 => {
+                if !bytes.len().is_multiple_of(2) {
+                    return None;
+                }
+                let units = bytes
+                    .chunks(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+                char::decode_utf16(units)
+                    .collect::<Result<String, _>>()
+                    .ok()?
+            }
+        };
+
+        let mut chars = decoded.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&str> for CharType {
+    type Error = miette::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let val = match value {
+            "char" => Self::Char,
+            "utf8" => Self::Utf8,
+            "utf16" => Self::Utf16,
+            _ => miette::bail!("Invalid character type: {}", value),
+        };
+        Ok(val)
+    }
+}
+
+impl Display for CharType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Char => write!(f, "char"),
+            Self::Utf8 => write!(f, "utf8"),
+            Self::Utf16 => write!(f, "utf16"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_char_type_from_str() {
+        assert_eq!(CharType::try_from("char").unwrap(), CharType::Char);
+        assert_eq!(CharType::try_from("utf16").unwrap(), CharType::Utf16);
+        assert!(CharType::try_from("utf32").is_err());
+    }
+
+    #[test]
+    fn test_char_type_encode() {
+        assert_eq!(CharType::Char.encode('€'), 0x20ac);
+        assert_eq!(CharType::Utf8.encode('€'), 0xe2_82ac);
+        assert_eq!(CharType::Utf8.encode('A'), 0x41);
+        assert_eq!(CharType::Utf16.encode('€'), 0x20ac);
+        assert_eq!(CharType::Utf16.encode('😀'), 0xd83d_de00);
+
+        assert_eq!(CharType::Utf8.byte_width('😀'), 4);
+        assert_eq!(CharType::Utf16.byte_width('A'), 2);
+    }
+
+    #[test]
+    fn test_char_type_to_bytes() {
+        assert_eq!(
+            CharType::Char.to_bytes('€', ByteOrder::Little),
+            [0xac, 0x20, 0, 0]
+        );
+        assert_eq!(
+            CharType::Utf8.to_bytes('€', ByteOrder::Little),
+            [0xe2, 0x82, 0xac]
+        );
+        assert_eq!(
+            CharType::Utf16.to_bytes('😀', ByteOrder::Little),
+            [0x3d, 0xd8, 0x00, 0xde]
+        );
+        assert_eq!(
+            CharType::Utf16.to_bytes('😀', ByteOrder::Big),
+            [0xd8, 0x3d, 0xde, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_char_type_decode() {
+        assert_eq!(CharType::Utf8.decode(&[0xe2, 0x82, 0xac]), Some('€'));
+        assert_eq!(
+            CharType::Utf16.decode(&[0xd8, 0x3d, 0xde, 0x00]),
+            Some('😀')
+        );
+        assert_eq!(CharType::Char.decode(&[0, 0, 0, 0x41]), Some('A'));
+        assert_eq!(CharType::Utf8.decode(b"ab"), None);
+        assert_eq!(CharType::Utf8.decode(&[0xe2, 0x82]), None);
+        assert_eq!(CharType::Utf16.decode(&[0xd8, 0x3d]), None);
+    }
+}
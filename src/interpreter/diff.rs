@@ -0,0 +1,175 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use super::{
+    int_type::{mask, IntType},
+    layout::{Field, Layout},
+    num::Number,
+    value::{Scalar, Value, ValueErrorKind},
+};
+
+/// A comparison of the bits of two integers, e.g. of two dumps of a register.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff {
+    left: Scalar,
+    right: Scalar,
+    /// The layout of the compared bitfields, which names the changed bits.
+    layout: Option<Layout>,
+    /// The XOR of both values, i.e. the changed bits, as an unsigned integer of the width of the wider one.
+    xor: Scalar,
+}
+
+impl Diff {
+    /// Compares the bits of `left` and `right`, which have to be integers without a unit. If either of them
+    /// is a bitfield, the changed bits are named after the fields of its layout.
+    pub fn try_new(left: Value, right: Value) -> Result<Self, ValueErrorKind> {
+        let layout = [&left, &right].into_iter().find_map(|value| match value {
+            Value::Bitfield(bitfield) => Some(bitfield.layout().clone()),
+            _ => None,
+        });
+        let (left, right) = (left.into_scalar()?, right.into_scalar()?);
+        // Checks that both are integers without a unit
+        left.try_bitxor(right.clone())?;
+
+        let width = |scalar: &Scalar| scalar.byte_width().unwrap_or(1) * 8;
+        let bits = width(&left).max(width(&right));
+        let changed =
+            (left.bits().unwrap_or_default() ^ right.bits().unwrap_or_default()) & mask(bits);
+        let ty = IntType::unsigned(bits);
+        let xor = Scalar::typed(Number::Int(changed as i128), None, Some(ty.into()));
+        Ok(Self {
+            left,
+            right,
+            layout,
+            xor,
+        })
+    }
+
+    pub fn layout(&self) -> Option<&Layout> {
+        self.layout.as_ref()
+    }
+
+    /// Returns the XOR of both values, as an unsigned integer of the width of the comparison.
+    pub fn xor(&self) -> &Scalar {
+        &self.xor
+    }
+
+    /// Returns the number of bits both values are compared at, i.e. the width of the wider one.
+    pub fn bits(&self) -> u32 {
+        self.xor.byte_width().unwrap_or(1) * 8
+    }
+
+    /// Returns the bit pattern of the left value at the width of the comparison.
+    pub fn left_bits(&self) -> u128 {
+        self.left.bits().unwrap_or_default() & mask(self.bits())
+    }
+
+    /// Returns the bit pattern of the right value at the width of the comparison.
+    pub fn right_bits(&self) -> u128 {
+        self.right.bits().unwrap_or_default() & mask(self.bits())
+    }
+
+    /// Returns the bits that differ between both values.
+    pub fn changed_bits(&self) -> u128 {
+        self.xor.bits().unwrap_or_default()
+    }
+
+    /// Returns the indices of the bits that differ, from the most significant one.
+    pub fn changed(&self) -> Vec<u32> {
+        let changed = self.changed_bits();
+        (0..self.bits())
+            .rev()
+            .filter(|index| changed >> index & 1 == 1)
+            .collect()
+    }
+
+    /// Returns the number of bits that differ, i.e. the popcount of the XOR.
+    pub fn count(&self) -> u32 {
+        self.changed_bits().count_ones()
+    }
+
+    /// Returns the field of the layout spanning the bit `index`, if there is one.
+    pub fn field(&self, index: u32) -> Option<&Field> {
+        self.layout()?
+            .fields()
+            .iter()
+            .find(|field| field.get(1 << index) != 0)
+    }
+
+    /// Returns the fields of the layout with differing bits.
+    pub fn changed_fields(&self) -> Vec<&Field> {
+        let changed = self.changed_bits();
+        self.layout()
+            .map(Layout::fields)
+            .unwrap_or_default()
+            .iter()
+            .filter(|field| field.get(changed) != 0)
+            .collect()
+    }
+}
+
+impl Display for Diff {
+    /// Formats the diff as the number of differing bits and their indices, e.g. `2 bits differ: 10, 4`.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let changed = self.changed();
+        match changed.len() {
+            0 => write!(f, "No bits differ"),
+            1 => write!(f, "1 bit differs: {}", changed[0]),
+            count => {
+                let indices = changed.iter().map(u32::to_string).collect::<Vec<_>>();
+                write!(f, "{count} bits differ: {}", indices.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    #[test]
+    fn test_diff() {
+        let int = |value: i128| Value::from(Scalar::new(value, None));
+        let diff = Diff::try_new(int(0x1234), int(0x1634)).unwrap();
+        assert_eq!(diff.bits(), 16);
+        assert_eq!(diff.changed(), [10]);
+        assert_eq!(diff.to_string(), "1 bit differs: 10");
+
+        let diff = Diff::try_new(
+            int(-1),
+            Scalar::new(0i128, None).cast(IntType::unsigned(8)).into(),
+        );
+        let diff = diff.unwrap();
+        assert_eq!(diff.bits(), 64);
+        assert_eq!(diff.count(), 64);
+        assert_eq!(diff.xor().to_string(), "18446744073709551615: u64");
+
+        assert_eq!(
+            Diff::try_new(int(1), Scalar::new(1.5, None).into()),
+            Err(ValueErrorKind::NotAnInteger)
+        );
+    }
+
+    #[test]
+    fn test_diff_fields() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret("layout CTRL { EN: 0, MODE: 2..1, DIV: 15..8 }")
+            .unwrap();
+        let value = interpreter
+            .interpret("diff(decode(CTRL, 0x1A05), 0x1B02)")
+            .unwrap();
+        let Value::Diff(diff) = value else {
+            panic!("Expected a diff, got {value}");
+        };
+        assert_eq!(diff.to_string(), "4 bits differ: 8, 2, 1, 0");
+        assert_eq!(diff.field(2).map(Field::name), Some("MODE"));
+        assert_eq!(diff.field(5), None);
+        let fields = diff.changed_fields();
+        assert_eq!(
+            fields.iter().map(|field| field.name()).collect::<Vec<_>>(),
+            ["EN", "MODE", "DIV"]
+        );
+    }
+}
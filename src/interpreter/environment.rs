@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use super::{layout::Layout, value::Value};
+
+/// A named value, along with the expression defining it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    expr: String,
+    value: Value,
+    /// The position of the definition among all definitions, to keep track of their order.
+    order: usize,
+}
+
+impl Definition {
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+}
+
+/// A definition in the history of a session.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Layout(Layout),
+    /// The named value `name`, defined as the value of `expr`.
+    Value {
+        name: String,
+        expr: String,
+    },
+}
+
+/// The definitions made during an interpreter session.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    layouts: BTreeMap<String, Layout>,
+    values: BTreeMap<String, Definition>,
+    /// The number of values defined so far, including redefinitions.
+    defined: usize,
+    /// The definitions in the order they were made, including the redefined ones later definitions may
+    /// depend on.
+    history: Vec<Entry>,
+}
+
+impl Environment {
+    pub fn layout(&self, name: &str) -> Option<&Layout> {
+        self.layouts.get(name)
+    }
+
+    /// Returns the layouts, in order of their names.
+    pub fn layouts(&self) -> impl Iterator<Item = &Layout> {
+        self.layouts.values()
+    }
+
+    /// Defines `layout`, replacing any previous layout of the same name.
+    pub fn define_layout(&mut self, layout: Layout) {
+        self.layouts
+            .insert(layout.name().to_string(), layout.clone());
+        self.history.push(Entry::Layout(layout));
+    }
+
+    /// Returns the names of all layouts and named values.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.layouts
+            .keys()
+            .chain(self.values.keys())
+            .map(String::as_str)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&Definition> {
+        self.values.get(name)
+    }
+
+    /// Defines the named value `name` as the value of `expr`, replacing any previous value of the same
+    /// name.
+    pub fn define_value(&mut self, name: String, expr: String, value: Value) {
+        let order = self.defined;
+        self.defined += 1;
+        // A redefinition right after the previous definition replaces it in the history, unless it refers
+        // to its previous value, which keeps the history of e.g. a loop variable from growing
+        let replaces = matches!(
+            self.history.last(),
+            Some(Entry::Value { name: last, .. }) if *last == name && !mentions(&expr, &name)
+        );
+        if replaces {
+            self.history.pop();
+        }
+        self.history.push(Entry::Value {
+            name: name.clone(),
+            expr: expr.clone(),
+        });
+        self.values.insert(name, Definition { expr, value, order });
+    }
+
+    /// Returns the named values with their definitions, in the order they were defined.
+    pub fn definitions(&self) -> Vec<(&str, &Definition)> {
+        let mut definitions = self
+            .values
+            .iter()
+            .map(|(name, definition)| (name.as_str(), definition))
+            .collect::<Vec<_>>();
+        definitions.sort_by_key(|(_, definition)| definition.order);
+        definitions
+    }
+
+    /// Returns the definitions in the order they were made. Evaluating them in order recreates the
+    /// environment, even if values were redefined after others were defined in terms of them.
+    pub fn history(&self) -> &[Entry] {
+        &self.history
+    }
+}
+
+/// Returns whether the expression `expr` contains the identifier `name` as a whole word.
+fn mentions(expr: &str, name: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    expr.match_indices(name).any(|(i, _)| {
+        let before = expr[..i].chars().next_back();
+        let after = expr[i + name.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
@@ -1,12 +1,118 @@
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
 
 use super::token::Token;
 
+/// A top level statement, i.e. an expression or a declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Expr(Expr),
+    Layout(LayoutDecl),
+    Let(LetDecl),
+}
+
+impl Display for Stmt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stmt::Expr(expr) => write!(f, "{}", expr),
+            Stmt::Layout(decl) => write!(f, "{}", decl),
+            Stmt::Let(decl) => write!(f, "{}", decl),
+        }
+    }
+}
+
+/// The definition of a named value, e.g. `let MASK = 0xff << 8`, along with the location of the
+/// expression in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LetDecl {
+    pub name: Token,
+    pub expr: Expr,
+    pub loc: Range<usize>,
+}
+
+impl Display for LetDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(let {} {})", self.name, self.expr)
+    }
+}
+
+/// The declaration of a bitfield layout, e.g. `layout CTRL { EN: 0, MODE: 2..1 { OFF: 0, ON: 1 } }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutDecl {
+    pub name: Token,
+    pub fields: Vec<FieldDecl>,
+}
+
+impl Display for LayoutDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(layout {}", self.name)?;
+        for field in &self.fields {
+            write!(f, " {}", field)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A field of a layout declaration, spanning the bits from `first` to `last` (or just `first`), with
+/// named values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDecl {
+    pub name: Token,
+    pub first: Token,
+    pub last: Option<Token>,
+    pub variants: Vec<(Token, Token)>,
+}
+
+impl Display for FieldDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({} {}", self.name, self.first)?;
+        if let Some(last) = &self.last {
+            write!(f, "..{}", last)?;
+        }
+        for (name, value) in &self.variants {
+            write!(f, " ({} {})", name, value)?;
+        }
+        write!(f, ")")
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Operator(OperatorExpr),
     Grouping(Box<Expr>),
-    Literal { kind: Token, unit: Option<Token> },
+    Literal {
+        kind: Token,
+        unit: Option<Token>,
+    },
+    Variable(Token),
+    Call {
+        callee: Token,
+        args: Vec<Expr>,
+    },
+    Array {
+        bracket: Token,
+        elements: Vec<Expr>,
+    },
+    Bitfield {
+        layout: Token,
+        fields: Vec<(Token, Expr)>,
+    },
+    /// The value of `expr` decoded according to a layout, e.g. `0x83.RCC_CR`.
+    Decode {
+        expr: Box<Expr>,
+        layout: Token,
+    },
+    /// The values of `body` with `var` bound to each integer from `start` up to `end`, e.g.
+    /// `table(i, 0..16, 1 << i)`.
+    Table {
+        callee: Token,
+        var: Token,
+        start: Box<Expr>,
+        /// The `..` of the range, which includes `end` if it is followed by `=`.
+        dots: Token,
+        inclusive: bool,
+        end: Box<Expr>,
+        body: Box<Expr>,
+    },
 }
 
 impl Display for Expr {
@@ -21,10 +127,77 @@ impl Display for Expr {
                 }
                 write!(f, "")
             }
+            Expr::Variable(name) => write!(f, "{}", name),
+            Expr::Call { callee, args } => {
+                write!(f, "({}", callee)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Array { elements, .. } => {
+                write!(f, "(bytes")?;
+                for element in elements {
+                    write!(f, " {}", element)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Bitfield { layout, fields } => {
+                write!(f, "(fields {}", layout)?;
+                for (name, value) in fields {
+                    write!(f, " ({} {})", name, value)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Decode { expr, layout } => write!(f, "(. {} {})", expr, layout),
+            Expr::Table {
+                var,
+                start,
+                inclusive,
+                end,
+                body,
+                ..
+            } => {
+                let dots = if *inclusive { "..=" } else { ".." };
+                write!(f, "(table {} ({} {} {}) {})", var, dots, start, end, body)
+            }
         }
     }
 }
 
+impl Expr {
+    /// Returns the location of the expression in the input, from its first to its last token. Closing
+    /// brackets aren't tokens of the expression, so they are not included.
+    pub fn loc(&self) -> Range<usize> {
+        let (start, end) = match self {
+            Expr::Operator(OperatorExpr::ArithmeticOrLogical { left, right, .. }) => {
+                (left.loc(), right.loc())
+            }
+            Expr::Operator(OperatorExpr::TypeCast { expr, target }) => (expr.loc(), target.loc()),
+            Expr::Operator(OperatorExpr::Unary { operator, right }) => {
+                (operator.loc(), right.loc())
+            }
+            Expr::Grouping(expr) => (expr.loc(), expr.loc()),
+            Expr::Literal { kind, unit } => (kind.loc(), unit.as_ref().unwrap_or(kind).loc()),
+            Expr::Variable(name) => (name.loc(), name.loc()),
+            Expr::Call { callee, args } => {
+                (callee.loc(), args.last().map_or(callee.loc(), Expr::loc))
+            }
+            Expr::Array { bracket, elements } => (
+                bracket.loc(),
+                elements.last().map_or(bracket.loc(), Expr::loc),
+            ),
+            Expr::Bitfield { layout, fields } => (
+                layout.loc(),
+                fields.last().map_or(layout.loc(), |(_, expr)| expr.loc()),
+            ),
+            Expr::Decode { expr, layout } => (expr.loc(), layout.loc()),
+            Expr::Table { callee, body, .. } => (callee.loc(), body.loc()),
+        };
+        start.start..end.end
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum OperatorExpr {
     ArithmeticOrLogical {
@@ -34,7 +207,7 @@ pub enum OperatorExpr {
     },
     TypeCast {
         expr: Box<Expr>,
-        unit: Token,
+        target: Token,
     },
     Unary {
         operator: Token,
@@ -52,8 +225,8 @@ impl Display for OperatorExpr {
             } => {
                 write!(f, "({} {} {})", operator, left, right)
             }
-            OperatorExpr::TypeCast { expr: left, unit } => {
-                write!(f, "(as {} {})", left, unit)
+            OperatorExpr::TypeCast { expr: left, target } => {
+                write!(f, "(as {} {})", left, target)
             }
             OperatorExpr::Unary { operator, right } => {
                 write!(f, "({} {})", operator, right)
@@ -63,20 +236,47 @@ impl Display for OperatorExpr {
 }
 
 // Grammar:
-// expression   -> term EOF ;
+// statement    -> ( layout | let | bitor ) EOF ;
+// let          -> "let" IDENTIFIER "=" bitor ;
+// layout       -> "layout" IDENTIFIER "{" ( field ( "," field )* ","? )? "}" ;
+// field        -> IDENTIFIER ":" INTEGER ( ".." INTEGER )? ( "{" ( variant ( "," variant )* ","? )? "}" )? ;
+// variant      -> IDENTIFIER ":" INTEGER ;
+// bitor        -> bitxor ( "|" bitxor )* ;
+// bitxor       -> bitand ( "^" bitand )* ;
+// bitand       -> shift ( "&" shift )* ;
+// shift        -> term ( ( "<<" | ">>" | ">>>" ) term )* ;
 // term         -> factor ( ( "-" | "+" ) factor )* ;
-// factor       -> unitcast ( ( "/" | "*" ) unitcast )* ;
-// unitcast     -> unary ( "as" UNIT )? ;
-// unary        -> "-" unary | primary ;
-// primary      -> NUMBER ( UNIT )? | "(" expression ")" ;
+// factor       -> typecast ( ( "/" | "*" ) typecast )* ;
+// typecast     -> unary ( "as" ( UNIT | IDENTIFIER | QFORMAT ) )* ;
+// unary        -> ( "-" | "~" ) unary | decode ;
+// decode       -> primary ( "." IDENTIFIER )* ;
+// primary      -> NUMBER ( UNIT )? | BYTES | ADDRESS | array | table | call | bitfield | IDENTIFIER
+//               | "(" bitor ")" ;
+// array        -> "[" ( bitor ( "," bitor )* )? "]" ;
+// table        -> "table" "(" IDENTIFIER "," range "," bitor ")" ;
+// range        -> bitor ".." "="? bitor ;
+// call         -> IDENTIFIER "(" ( bitor ( "," bitor )* )? ")" ;
+// bitfield     -> IDENTIFIER "{" ( IDENTIFIER ":" bitor ( "," IDENTIFIER ":" bitor )* ","? )? "}" ;
 //
-// NUMBER   -> BINARY | OCTAL | DECIMAL | HEX ;
+// NUMBER   -> INTEGER | FLOAT ;
+// INTEGER  -> BINARY | OCTAL | DECIMAL | HEX ;
 // BINARY   -> "0b" [01]+ ;
 // OCTAL    -> "0o" [0-7]+ ;
 // DECIMAL  -> [0-9]+ ;
 // HEX      -> "0x" [0-9a-fA-F]+ ;
+// FLOAT    -> [0-9]+ "." [0-9]+ ;
+//
+// BYTES    -> "b"? "\"" ( [^"\\] | ESCAPE )* "\"" | "x\"" [0-9a-fA-F \t\n]* "\"" ;
+// ESCAPE   -> "\\" ( "n" | "r" | "t" | "0" | "\\" | "\"" | "x" [0-9a-fA-F]{2} ) ;
+//
+// ADDRESS  -> ( IPV4 | IPV6 ) ( "/" [0-9]+ )? ;
+// IPV4     -> [0-9]+ "." [0-9]+ "." [0-9]+ "." [0-9]+ ;
+// IPV6     -> [0-9a-fA-F:.]* ":" [0-9a-fA-F:.]* ;
 //
 // UNIT     -> UNITPREFIX? "b" | "B" ;
 // UNITPREFIX -> DECUNITPREFIX | BINUNITPREFIX ;
 // BINUNITPREFIX -> DECUNITPREFIX "i" ;
 // DECUNITPREFIX -> "k" | "m" | "g" | "t" | "p" | "e" | "K" | "M" | "G" | "T" | "P" | "E" ;
+//
+// IDENTIFIER -> [a-zA-Z_] [a-zA-Z0-9_]* ;
+// QFORMAT  -> "u"? "q" [0-9]+ ( "." [0-9]+ )? ;
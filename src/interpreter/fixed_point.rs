@@ -0,0 +1,188 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use super::int_type::IntType;
+
+/// A fixed point format in Q notation.
+///
+/// `qM.N` has `M` integer bits, including the sign bit, and `N` fractional bits, `uqM.N` is its unsigned
+/// counterpart. `qN` is short for `q1.N` and `uqN` for `uq0.N`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QFormat {
+    signed: bool,
+    int_bits: u32,
+    frac_bits: u32,
+}
+
+/// The result of encoding a number into a fixed point format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Encoded {
+    pub raw: i128,
+    pub rounded: bool,
+    pub saturated: bool,
+}
+
+impl QFormat {
+    /// The maximum number of bits of a fixed point format.
+    pub const MAX_BITS: u32 = 64;
+
+    /// Creates a new format, returning `None` if the format is empty, too wide or signed without a sign
+    /// bit.
+    pub const fn new(signed: bool, int_bits: u32, frac_bits: u32) -> Option<Self> {
+        let bits = int_bits as u64 + frac_bits as u64;
+        if bits == 0 || bits > Self::MAX_BITS as u64 || (signed && int_bits == 0) {
+            return None;
+        }
+
+        Some(Self {
+            signed,
+            int_bits,
+            frac_bits,
+        })
+    }
+
+    pub const fn is_signed(self) -> bool {
+        self.signed
+    }
+
+    pub const fn int_bits(self) -> u32 {
+        self.int_bits
+    }
+
+    pub const fn frac_bits(self) -> u32 {
+        self.frac_bits
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.int_bits + self.frac_bits
+    }
+
+    /// Returns the integer type holding the raw values of this format.
+    pub const fn raw_type(self) -> IntType {
+        IntType::new(self.signed, self.bits())
+    }
+
+    /// Returns the value of one least significant bit.
+    pub fn resolution(self) -> f64 {
+        (-(self.frac_bits as f64)).exp2()
+    }
+
+    pub fn min(self) -> f64 {
+        self.decode(self.min_raw())
+    }
+
+    pub fn max(self) -> f64 {
+        self.decode(self.max_raw())
+    }
+
+    fn min_raw(self) -> i128 {
+        match self.signed {
+            true => -(1 << (self.bits() - 1)),
+            false => 0,
+        }
+    }
+
+    fn max_raw(self) -> i128 {
+        match self.signed {
+            true => (1 << (self.bits() - 1)) - 1,
+            false => (1 << self.bits()) - 1,
+        }
+    }
+
+    /// Returns the number represented by the raw value `raw`.
+    pub fn decode(self, raw: i128) -> f64 {
+        self.raw_type().wrap(raw) as f64 * self.resolution()
+    }
+
+    /// Encodes `value`, rounding to nearest with ties away from zero and saturating at the bounds of the
+    /// format.
+    pub fn encode(self, value: f64) -> Encoded {
+        let exact = value / self.resolution();
+        let rounded = exact.round();
+
+        // NaN saturates to zero, like a float to integer cast
+        let raw = (rounded as i128).clamp(self.min_raw(), self.max_raw());
+        Encoded {
+            raw,
+            rounded: rounded != exact,
+            saturated: raw as f64 != rounded,
+        }
+    }
+
+    /// Rounds `value` to the nearest value representable in this format.
+    pub fn round(self, value: f64) -> f64 {
+        self.decode(self.encode(value).raw)
+    }
+}
+
+impl Display for QFormat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let sign = if self.signed { "" } else { "u" };
+        write!(f, "{}q{}.{}", sign, self.int_bits, self.frac_bits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn q(signed: bool, int_bits: u32, frac_bits: u32) -> QFormat {
+        QFormat::new(signed, int_bits, frac_bits).unwrap()
+    }
+
+    #[test]
+    fn test_q_format_new() {
+        assert!(QFormat::new(true, 0, 15).is_none());
+        assert!(QFormat::new(false, 0, 0).is_none());
+        assert!(QFormat::new(false, 32, 33).is_none());
+        assert_eq!(q(false, 0, 16).bits(), 16);
+        assert_eq!(q(true, 8, 8).raw_type(), IntType::signed(16));
+    }
+
+    #[test]
+    fn test_q_format_decode() {
+        assert_eq!(q(true, 1, 15).decode(0x4000), 0.5);
+        assert_eq!(q(true, 1, 15).decode(0x8000), -1.0);
+        assert_eq!(q(true, 1, 15).decode(-0x8000), -1.0);
+        assert_eq!(q(true, 8, 8).decode(0x0180), 1.5);
+        assert_eq!(q(false, 0, 8).decode(0xff), 255.0 / 256.0);
+    }
+
+    #[test]
+    fn test_q_format_encode() {
+        let encoded = q(true, 1, 15).encode(0.75);
+        assert_eq!(
+            encoded,
+            Encoded {
+                raw: 0x6000,
+                rounded: false,
+                saturated: false
+            }
+        );
+
+        let encoded = q(true, 1, 15).encode(0.1);
+        assert_eq!(encoded.raw, 3277);
+        assert!(encoded.rounded && !encoded.saturated);
+
+        let encoded = q(true, 1, 15).encode(1.0);
+        assert_eq!(encoded.raw, 0x7fff);
+        assert!(encoded.saturated);
+
+        let encoded = q(false, 8, 8).encode(-1.0);
+        assert_eq!(encoded.raw, 0);
+        assert!(encoded.saturated);
+    }
+
+    #[test]
+    fn test_q_format_range() {
+        assert_eq!(q(true, 1, 15).min(), -1.0);
+        assert_eq!(q(true, 1, 15).max(), 1.0 - 2f64.powi(-15));
+        assert_eq!(q(false, 8, 8).max(), 256.0 - 1.0 / 256.0);
+    }
+
+    #[test]
+    fn test_display_q_format() {
+        assert_eq!(format!("{}", q(true, 1, 31)), "q1.31");
+        assert_eq!(format!("{}", q(false, 8, 8)), "uq8.8");
+    }
+}
@@ -0,0 +1,309 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+/// An IEEE-754 binary floating point format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatType {
+    F16,
+    BF16,
+    F32,
+    F64,
+}
+
+/// The classification of a floating point bit pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatClass {
+    Zero,
+    Subnormal,
+    Normal,
+    Infinite,
+    NaN,
+}
+
+/// The fields of a floating point bit pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloatFields {
+    pub sign: bool,
+    /// The biased exponent, as stored in the bit pattern.
+    pub exponent: u64,
+    pub mantissa: u64,
+    pub class: FloatClass,
+}
+
+/// Returns `2^exp`, which has to be in the range of normal `f64` values.
+fn pow2(exp: i64) -> f64 {
+    f64::from_bits(((exp + 1023) as u64) << 52)
+}
+
+/// Shifts `value` right by `shift` bits, rounding to nearest, ties to even.
+fn round_shr(value: u64, shift: u32) -> u64 {
+    if shift == 0 {
+        return value;
+    }
+    if shift >= 64 {
+        // Only used for significands with at most 53 bits, so this is always below half
+        return 0;
+    }
+
+    let shifted = value >> shift;
+    let rest = value & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    if rest > half || (rest == half && shifted & 1 == 1) {
+        shifted + 1
+    } else {
+        shifted
+    }
+}
+
+impl FloatType {
+    pub const fn bits(self) -> u32 {
+        match self {
+            Self::F16 | Self::BF16 => 16,
+            Self::F32 => 32,
+            Self::F64 => 64,
+        }
+    }
+
+    pub const fn exponent_bits(self) -> u32 {
+        match self {
+            Self::F16 => 5,
+            Self::BF16 | Self::F32 => 8,
+            Self::F64 => 11,
+        }
+    }
+
+    pub const fn mantissa_bits(self) -> u32 {
+        self.bits() - self.exponent_bits() - 1
+    }
+
+    pub const fn bias(self) -> i64 {
+        (1 << (self.exponent_bits() - 1)) - 1
+    }
+
+    const fn max_exponent(self) -> u64 {
+        (1 << self.exponent_bits()) - 1
+    }
+
+    /// Splits `bits` into its sign, exponent and mantissa and classifies it.
+    pub fn fields(self, bits: u64) -> FloatFields {
+        let mantissa = bits & ((1 << self.mantissa_bits()) - 1);
+        let exponent = (bits >> self.mantissa_bits()) & self.max_exponent();
+        let sign = (bits >> (self.bits() - 1)) & 1 == 1;
+
+        let class = match (exponent, mantissa) {
+            (0, 0) => FloatClass::Zero,
+            (0, _) => FloatClass::Subnormal,
+            (e, 0) if e == self.max_exponent() => FloatClass::Infinite,
+            (e, _) if e == self.max_exponent() => FloatClass::NaN,
+            _ => FloatClass::Normal,
+        };
+
+        FloatFields {
+            sign,
+            exponent,
+            mantissa,
+            class,
+        }
+    }
+
+    /// Returns the value of the bit pattern `bits`.
+    pub fn decode(self, bits: u64) -> f64 {
+        if self == Self::F64 {
+            return f64::from_bits(bits);
+        }
+
+        let fields = self.fields(bits);
+        let exponent = fields.exponent as i64;
+        let value = match fields.class {
+            FloatClass::Infinite => f64::INFINITY,
+            FloatClass::NaN => f64::NAN,
+            FloatClass::Zero | FloatClass::Subnormal => {
+                fields.mantissa as f64 * pow2(1 - self.bias() - self.mantissa_bits() as i64)
+            }
+            FloatClass::Normal => {
+                let significand = fields.mantissa | 1 << self.mantissa_bits();
+                significand as f64 * pow2(exponent - self.bias() - self.mantissa_bits() as i64)
+            }
+        };
+
+        if fields.sign {
+            -value
+        } else {
+            value
+        }
+    }
+
+    /// Returns the bit pattern of `value`, rounded to nearest, ties to even.
+    pub fn encode(self, value: f64) -> u64 {
+        match self {
+            Self::F64 => return value.to_bits(),
+            Self::F32 => return (value as f32).to_bits().into(),
+            Self::F16 | Self::BF16 => {}
+        }
+
+        let mantissa_bits = self.mantissa_bits();
+        let sign = (value.is_sign_negative() as u64) << (self.bits() - 1);
+        if value.is_nan() {
+            return sign | self.max_exponent() << mantissa_bits | 1 << (mantissa_bits - 1);
+        }
+        if value.is_infinite() {
+            return sign | self.max_exponent() << mantissa_bits;
+        }
+        if value == 0.0 {
+            return sign;
+        }
+
+        // `value` is `significand * 2^exp`
+        let bits = value.abs().to_bits();
+        let (significand, exp) = match (bits >> 52) as i64 {
+            0 => (bits, -1074),
+            e => (bits & ((1 << 52) - 1) | 1 << 52, e - 1075),
+        };
+        let msb = 63 - significand.leading_zeros() as i64 + exp;
+
+        // The exponent of the least significant bit of the result, subnormals have a fixed one
+        let lsb = (msb - mantissa_bits as i64).max(1 - self.bias() - mantissa_bits as i64);
+        let shift = (lsb - exp).clamp(0, 64) as u32;
+        let significand = round_shr(significand, shift);
+
+        // The exponent field is one less than the biased exponent, as the implicit leading bit of the
+        // significand is added onto it. This also handles subnormals rounding up into the normal range.
+        let exponent = (lsb + mantissa_bits as i64 + self.bias() - 1) as u64;
+        let encoded = (exponent << mantissa_bits) + significand;
+
+        if encoded >> mantissa_bits >= self.max_exponent() {
+            return sign | self.max_exponent() << mantissa_bits;
+        }
+        sign | encoded
+    }
+
+    /// Rounds `value` to the nearest value representable in this format.
+    pub fn round(self, value: f64) -> f64 {
+        self.decode(self.encode(value))
+    }
+}
+
+impl TryFrom<&str> for FloatType {
+    type Error = miette::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let val = match value {
+            "f16" => Self::F16,
+            "bf16" => Self::BF16,
+            "f32" => Self::F32,
+            "f64" => Self::F64,
+            _ => miette::bail!("Invalid float type: {}", value),
+        };
+        Ok(val)
+    }
+}
+
+impl Display for FloatType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::F16 => write!(f, "f16"),
+            Self::BF16 => write!(f, "bf16"),
+            Self::F32 => write!(f, "f32"),
+            Self::F64 => write!(f, "f64"),
+        }
+    }
+}
+
+impl Display for FloatClass {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Zero => write!(f, "Zero"),
+            Self::Subnormal => write!(f, "Subnormal"),
+            Self::Normal => write!(f, "Normal"),
+            Self::Infinite => write!(f, "Infinite"),
+            Self::NaN => write!(f, "NaN"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_float_type_from_str() {
+        assert_eq!(FloatType::try_from("bf16").unwrap(), FloatType::BF16);
+        assert_eq!(FloatType::try_from("f64").unwrap(), FloatType::F64);
+        assert!(FloatType::try_from("f8").is_err());
+    }
+
+    #[test]
+    fn test_float_fields() {
+        let fields = FloatType::F32.fields(0xbf80_0000);
+        assert_eq!(
+            fields,
+            FloatFields {
+                sign: true,
+                exponent: 127,
+                mantissa: 0,
+                class: FloatClass::Normal
+            }
+        );
+
+        assert_eq!(FloatType::F16.fields(0x0000).class, FloatClass::Zero);
+        assert_eq!(FloatType::F16.fields(0x03ff).class, FloatClass::Subnormal);
+        assert_eq!(FloatType::F16.fields(0x7c00).class, FloatClass::Infinite);
+        assert_eq!(FloatType::BF16.fields(0xffc1).class, FloatClass::NaN);
+    }
+
+    #[test]
+    fn test_float_decode() {
+        assert_eq!(FloatType::F32.decode(0x3f80_0000), 1.0);
+        assert_eq!(FloatType::F16.decode(0x3c00), 1.0);
+        assert_eq!(FloatType::F16.decode(0x7bff), 65504.0);
+        assert_eq!(FloatType::F16.decode(0x0001), 2f64.powi(-24));
+        assert_eq!(FloatType::F16.decode(0xfc00), f64::NEG_INFINITY);
+        assert_eq!(FloatType::BF16.decode(0x4049), 3.140625);
+        assert!(FloatType::BF16.decode(0x7fc0).is_nan());
+    }
+
+    #[test]
+    fn test_float_encode() {
+        assert_eq!(FloatType::F16.encode(1.0), 0x3c00);
+        assert_eq!(FloatType::F16.encode(-2.0), 0xc000);
+        assert_eq!(FloatType::F16.encode(-0.0), 0x8000);
+        assert_eq!(FloatType::F16.encode(65504.0), 0x7bff);
+        // Ties to even rounds up into infinity
+        assert_eq!(FloatType::F16.encode(65520.0), 0x7c00);
+        assert_eq!(FloatType::F16.encode(1e10), 0x7c00);
+        assert_eq!(FloatType::F16.encode(2f64.powi(-24)), 0x0001);
+        assert_eq!(FloatType::F16.encode(2f64.powi(-25)), 0x0000);
+        assert_eq!(FloatType::F16.encode(1.5 * 2f64.powi(-25)), 0x0001);
+        // The largest subnormal rounds up into the smallest normal
+        assert_eq!(
+            FloatType::F16.encode(2f64.powi(-14) - 2f64.powi(-26)),
+            0x0400
+        );
+        assert_eq!(FloatType::F16.encode(f64::MIN_POSITIVE / 2.0), 0x0000);
+        assert_eq!(FloatType::F16.encode(f64::NAN), 0x7e00);
+        assert_eq!(FloatType::BF16.encode(std::f64::consts::PI), 0x4049);
+        assert_eq!(FloatType::BF16.encode(f64::INFINITY), 0x7f80);
+        assert_eq!(FloatType::F32.encode(1.5), 0x3fc0_0000);
+        assert_eq!(FloatType::F64.encode(1.0), 0x3ff0_0000_0000_0000);
+    }
+
+    #[test]
+    fn test_float_round_trip() {
+        for ty in [FloatType::F16, FloatType::BF16] {
+            for bits in 0..=u16::MAX as u64 {
+                if ty.fields(bits).class != FloatClass::NaN {
+                    assert_eq!(ty.encode(ty.decode(bits)), bits, "{ty} {bits:#x}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bf16_matches_f32_rounding() {
+        for value in [0.1, 1.0 / 3.0, 1234.5678, -7.0e-20, 3.0e38] {
+            let f32_bits = (value as f32).to_bits();
+            let rounded = (f32_bits + 0x7fff + ((f32_bits >> 16) & 1)) >> 16;
+            assert_eq!(FloatType::BF16.encode(value), u64::from(rounded), "{value}");
+        }
+    }
+}
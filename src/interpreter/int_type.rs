@@ -0,0 +1,139 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+/// A fixed width integer type, e.g. `u8`, `i32` or `u12`.
+///
+/// Values of a given type are always kept wrapped into the range of the type. 128 bit values are
+/// stored as their two's complement bit pattern, i.e. a `u128` above `i128::MAX` is negative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntType {
+    signed: bool,
+    bits: u32,
+}
+
+impl IntType {
+    pub const fn new(signed: bool, bits: u32) -> Self {
+        Self { signed, bits }
+    }
+
+    pub const fn unsigned(bits: u32) -> Self {
+        Self::new(false, bits)
+    }
+
+    pub const fn signed(bits: u32) -> Self {
+        Self::new(true, bits)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.bits
+    }
+
+    pub const fn is_signed(self) -> bool {
+        self.signed
+    }
+
+    /// Returns the wider of the two types, preferring `self` if both have the same width.
+    pub fn max(self, other: Self) -> Self {
+        if other.bits > self.bits {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// Wraps `value` into the range of this type.
+    pub const fn wrap(self, value: i128) -> i128 {
+        if self.bits >= 128 {
+            return value;
+        }
+
+        let bits = self.to_bits(value);
+        if self.signed && bits >> (self.bits - 1) & 1 == 1 {
+            bits as i128 - (1 << self.bits)
+        } else {
+            bits as i128
+        }
+    }
+
+    /// Returns the bit pattern of `value` in this type.
+    pub const fn to_bits(self, value: i128) -> u128 {
+        value as u128 & mask(self.bits)
+    }
+}
+
+/// Returns a mask with the lowest `bits` bits set.
+pub const fn mask(bits: u32) -> u128 {
+    match bits {
+        0 => 0,
+        128.. => u128::MAX,
+        _ => (1 << bits) - 1,
+    }
+}
+
+impl TryFrom<&str> for IntType {
+    type Error = miette::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (signed, bits) = match value.split_at_checked(1) {
+            Some(("u", bits)) => (false, bits),
+            Some(("i", bits)) => (true, bits),
+            _ => miette::bail!("Invalid integer type: {}", value),
+        };
+
+        if !bits.bytes().all(|c| c.is_ascii_digit()) {
+            miette::bail!("Invalid integer type: {}", value);
+        }
+
+        match bits.parse() {
+            Ok(bits @ 1..=128) => Ok(Self::new(signed, bits)),
+            _ => miette::bail!("Invalid integer type: {}", value),
+        }
+    }
+}
+
+impl Display for IntType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let sign = if self.signed { "i" } else { "u" };
+        write!(f, "{}{}", sign, self.bits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_int_type_from_str() {
+        assert_eq!(IntType::try_from("u8").unwrap(), IntType::unsigned(8));
+        assert_eq!(IntType::try_from("i128").unwrap(), IntType::signed(128));
+        assert_eq!(IntType::try_from("u12").unwrap(), IntType::unsigned(12));
+        assert!(IntType::try_from("u0").is_err());
+        assert!(IntType::try_from("u129").is_err());
+        assert!(IntType::try_from("u+8").is_err());
+        assert!(IntType::try_from("f32").is_err());
+        assert!(IntType::try_from("").is_err());
+    }
+
+    #[test]
+    fn test_int_type_wrap() {
+        assert_eq!(IntType::unsigned(8).wrap(256), 0);
+        assert_eq!(IntType::unsigned(8).wrap(-1), 255);
+        assert_eq!(IntType::signed(8).wrap(255), -1);
+        assert_eq!(IntType::signed(8).wrap(128), -128);
+        assert_eq!(IntType::signed(16).wrap(0x7fff), 0x7fff);
+        assert_eq!(IntType::unsigned(128).wrap(-1), -1);
+    }
+
+    #[test]
+    fn test_int_type_to_bits() {
+        assert_eq!(IntType::signed(8).to_bits(-1), 0xff);
+        assert_eq!(IntType::unsigned(32).to_bits(-2), 0xffff_fffe);
+        assert_eq!(IntType::unsigned(128).to_bits(-1), u128::MAX);
+    }
+
+    #[test]
+    fn test_display_int_type() {
+        assert_eq!(format!("{}", IntType::unsigned(16)), "u16");
+        assert_eq!(format!("{}", IntType::signed(64)), "i64");
+    }
+}
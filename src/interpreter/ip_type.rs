@@ -0,0 +1,171 @@
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use super::int_type::{mask, IntType};
+
+/// The version of an IP address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    pub const fn bits(self) -> u32 {
+        match self {
+            Self::V4 => 32,
+            Self::V6 => 128,
+        }
+    }
+}
+
+/// A view of an integer as an IP address, optionally with the prefix length of the network it is in,
+/// e.g. `192.168.1.0/24`.
+///
+/// An address without a prefix length is treated as a network of just itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpType {
+    version: IpVersion,
+    prefix: Option<u32>,
+}
+
+impl IpType {
+    pub const fn new(version: IpVersion) -> Self {
+        Self {
+            version,
+            prefix: None,
+        }
+    }
+
+    /// Returns the type of the network with the prefix length `prefix`, if the prefix fits the address.
+    pub fn with_prefix(self, prefix: u32) -> Option<Self> {
+        (prefix <= self.bits()).then_some(Self {
+            prefix: Some(prefix),
+            ..self
+        })
+    }
+
+    /// Returns the type of a plain address, i.e. without a prefix length.
+    pub const fn address(self) -> Self {
+        Self::new(self.version)
+    }
+
+    pub const fn version(self) -> IpVersion {
+        self.version
+    }
+
+    pub const fn prefix(self) -> Option<u32> {
+        self.prefix
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.version.bits()
+    }
+
+    /// Returns the integer type holding the address.
+    pub const fn storage_type(self) -> IntType {
+        IntType::unsigned(self.bits())
+    }
+
+    /// Returns the mask of the network part of the address.
+    pub fn netmask(self) -> u128 {
+        mask(self.bits()) & !self.hostmask()
+    }
+
+    /// Returns the mask of the host part of the address.
+    pub fn hostmask(self) -> u128 {
+        mask(self.bits() - self.prefix.unwrap_or(self.bits()))
+    }
+
+    /// Returns the number of usable host addresses in the network, or `None` if there are 2^128 of them.
+    ///
+    /// IPv4 networks reserve their first and last address, except for point-to-point `/31` networks and
+    /// single hosts.
+    pub fn hosts(self) -> Option<u128> {
+        let host_bits = self.bits() - self.prefix.unwrap_or(self.bits());
+        match (self.version, host_bits) {
+            (IpVersion::V4, 0 | 1) => Some(1 << host_bits),
+            (IpVersion::V4, _) => Some((1 << host_bits) - 2),
+            (IpVersion::V6, _) => 1u128.checked_shl(host_bits),
+        }
+    }
+
+    /// Formats the address `bits` along with the prefix length, e.g. `10.0.0.0/8` or `fe80::1`.
+    pub fn format(self, bits: u128) -> String {
+        let addr = match self.version {
+            IpVersion::V4 => Ipv4Addr::from(bits as u32).to_string(),
+            IpVersion::V6 => Ipv6Addr::from(bits).to_string(),
+        };
+        match self.prefix {
+            Some(prefix) => format!("{addr}/{prefix}"),
+            None => addr,
+        }
+    }
+}
+
+impl TryFrom<&str> for IpType {
+    type Error = miette::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let val = match value {
+            "ipv4" => Self::new(IpVersion::V4),
+            "ipv6" => Self::new(IpVersion::V6),
+            _ => miette::bail!("Invalid address type: {}", value),
+        };
+        Ok(val)
+    }
+}
+
+impl Display for IpType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.version {
+            IpVersion::V4 => write!(f, "ipv4"),
+            IpVersion::V6 => write!(f, "ipv6"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const V4: IpType = IpType::new(IpVersion::V4);
+    const V6: IpType = IpType::new(IpVersion::V6);
+
+    #[test]
+    fn test_ip_type_from_str() {
+        assert_eq!(IpType::try_from("ipv4").unwrap(), V4);
+        assert_eq!(IpType::try_from("ipv6").unwrap(), V6);
+        assert!(IpType::try_from("ipv5").is_err());
+    }
+
+    #[test]
+    fn test_ip_type_masks() {
+        let net = V4.with_prefix(24).unwrap();
+        assert_eq!(net.netmask(), 0xffff_ff00);
+        assert_eq!(net.hostmask(), 0xff);
+        assert_eq!(V4.netmask(), 0xffff_ffff);
+        assert_eq!(V4.with_prefix(0).unwrap().netmask(), 0);
+        assert_eq!(V6.with_prefix(64).unwrap().netmask(), u128::MAX << 64);
+        assert_eq!(V4.with_prefix(33), None);
+    }
+
+    #[test]
+    fn test_ip_type_hosts() {
+        assert_eq!(V4.with_prefix(24).unwrap().hosts(), Some(254));
+        assert_eq!(V4.with_prefix(31).unwrap().hosts(), Some(2));
+        assert_eq!(V4.hosts(), Some(1));
+        assert_eq!(V6.with_prefix(64).unwrap().hosts(), Some(1 << 64));
+        assert_eq!(V6.with_prefix(0).unwrap().hosts(), None);
+    }
+
+    #[test]
+    fn test_ip_type_format() {
+        let net = V4.with_prefix(24).unwrap();
+        assert_eq!(net.format(0xc0a8_0100), "192.168.1.0/24");
+        assert_eq!(V6.format(0xfe80 << 112 | 1), "fe80::1");
+    }
+}
@@ -0,0 +1,302 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use super::{
+    int_type::{mask, IntType},
+    num::Number,
+    value::{Scalar, ValueErrorKind},
+};
+
+/// A named field of a layout, spanning the bits `lo..=hi`, with optional names for some of its values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    name: String,
+    hi: u32,
+    lo: u32,
+    variants: Vec<(String, u128)>,
+}
+
+impl Field {
+    /// Creates a field spanning the bits between `a` and `b`, in either order.
+    pub fn new(name: String, a: u32, b: u32) -> Self {
+        Self {
+            name,
+            hi: a.max(b),
+            lo: a.min(b),
+            variants: Vec::new(),
+        }
+    }
+
+    /// Names the field value `value`, erroring if it does not fit into the field.
+    pub fn add_variant(&mut self, name: String, value: u128) -> Result<(), ValueErrorKind> {
+        self.check_fits(value)?;
+        self.variants.push((name, value));
+        Ok(())
+    }
+
+    fn check_fits(&self, value: u128) -> Result<(), ValueErrorKind> {
+        match value >> 1 >> (self.bits() - 1) {
+            0 => Ok(()),
+            _ => Err(ValueErrorKind::TooWide { bits: self.bits() }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn variants(&self) -> &[(String, u128)] {
+        &self.variants
+    }
+
+    /// Returns the width of the field in bits.
+    pub fn bits(&self) -> u32 {
+        self.hi - self.lo + 1
+    }
+
+    /// Returns the value of the field in `bits`.
+    pub fn get(&self, bits: u128) -> u128 {
+        bits >> self.lo & mask(self.bits())
+    }
+
+    /// Returns the bits with the field set to `value`, erroring if the value does not fit into the field.
+    pub fn set(&self, bits: u128, value: u128) -> Result<u128, ValueErrorKind> {
+        self.check_fits(value)?;
+        Ok(bits & !(mask(self.bits()) << self.lo) | value << self.lo)
+    }
+
+    /// Returns the value named `name`.
+    pub fn variant(&self, name: &str) -> Option<u128> {
+        self.variants
+            .iter()
+            .find_map(|(n, value)| (n == name).then_some(*value))
+    }
+
+    /// Returns the name of `value`, if it has one.
+    pub fn variant_name(&self, value: u128) -> Option<&str> {
+        self.variants
+            .iter()
+            .find_map(|(name, v)| (*v == value).then_some(name.as_str()))
+    }
+
+    /// Formats the bits spanned by the field, e.g. `0` or `15..8`.
+    pub fn range(&self) -> String {
+        match self.hi == self.lo {
+            true => self.lo.to_string(),
+            false => format!("{}..{}", self.hi, self.lo),
+        }
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.range())?;
+        if !self.variants.is_empty() {
+            let variants = self
+                .variants
+                .iter()
+                .map(|(name, value)| format!("{name}: {value}"))
+                .collect::<Vec<_>>();
+            write!(f, " {{ {} }}", variants.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A named layout of the bits of a register, e.g. `layout CTRL { EN: 0, MODE: 2..1, DIV: 15..8 }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    name: String,
+    size: u32,
+    fields: Vec<Field>,
+}
+
+impl Layout {
+    pub fn new(name: String) -> Self {
+        Self::with_size(name, 0)
+    }
+
+    /// Creates a layout of a register that is at least `size` bits wide, regardless of its fields.
+    pub fn with_size(name: String, size: u32) -> Self {
+        Self {
+            name,
+            size,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Adds `field` to the layout, erroring if there already is a field of the same name.
+    pub fn add_field(&mut self, field: Field) -> Result<(), ValueErrorKind> {
+        if self.field(field.name()).is_some() {
+            return Err(ValueErrorKind::DuplicateField);
+        }
+        self.fields.push(field);
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Returns the lowest bit `field` shares with the fields of the layout, if it overlaps any of them.
+    pub fn overlap(&self, field: &Field) -> Option<u32> {
+        let bits = field.set(0, mask(field.bits())).unwrap_or_default();
+        let shared = self.fields.iter().fold(0, |shared, other| {
+            shared | bits & other.set(0, mask(other.bits())).unwrap_or_default()
+        });
+        (shared != 0).then(|| shared.trailing_zeros())
+    }
+
+    /// Returns the type of the values of the layout, i.e. the smallest of `u8`, `u16`, `u32`, `u64` and
+    /// `u128` holding all of its fields and its size.
+    pub fn int_type(&self) -> IntType {
+        let bits = self.fields.iter().map(|field| field.hi + 1).max();
+        let bits = bits.unwrap_or(0).max(self.size).min(128);
+        IntType::unsigned(bits.next_power_of_two().max(8))
+    }
+
+    /// Returns the bits of `bits` that are not part of any field.
+    pub fn unmapped(&self, bits: u128) -> u128 {
+        self.fields
+            .iter()
+            .fold(bits, |bits, field| bits & !(mask(field.bits()) << field.lo))
+    }
+}
+
+impl Display for Layout {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let fields = self.fields.iter().map(Field::to_string).collect::<Vec<_>>();
+        write!(f, "layout {} {{ {} }}", self.name, fields.join(", "))
+    }
+}
+
+/// A value decoded according to a layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bitfield {
+    layout: Layout,
+    value: Scalar,
+}
+
+impl Bitfield {
+    /// Decodes `value` according to `layout`, erroring if it is not an integer fitting into the type of the
+    /// layout.
+    pub fn try_new(layout: Layout, value: Scalar) -> Result<Self, ValueErrorKind> {
+        if value.unit().is_some() {
+            return Err(ValueErrorKind::BitwiseWithUnit);
+        }
+        value.try_int()?;
+
+        let ty = layout.int_type();
+        let bits = value
+            .bits()
+            .filter(|bits| bits >> 1 >> (ty.bits() - 1) == 0)
+            .ok_or(ValueErrorKind::TooWide { bits: ty.bits() })?;
+        let value = Scalar::typed(Number::Int(bits as i128), None, Some(ty.into()));
+        Ok(Self { layout, value })
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Returns the whole value as an integer of the type of the layout.
+    pub fn scalar(&self) -> &Scalar {
+        &self.value
+    }
+
+    pub fn into_scalar(self) -> Scalar {
+        self.value
+    }
+
+    pub fn bits(&self) -> u128 {
+        self.value.bits().unwrap_or_default()
+    }
+}
+
+impl Display for Bitfield {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let fields = self
+            .layout
+            .fields
+            .iter()
+            .map(|field| {
+                let value = field.get(self.bits());
+                match field.variant_name(value) {
+                    Some(name) => format!("{}: {}", field.name, name),
+                    None => format!("{}: {}", field.name, value),
+                }
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{} {{ {} }}", self.layout.name, fields.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ctrl() -> Layout {
+        let mut layout = Layout::new("CTRL".into());
+        let mut mode = Field::new("MODE".into(), 2, 1);
+        mode.add_variant("OFF".into(), 0).unwrap();
+        mode.add_variant("FAST".into(), 2).unwrap();
+        layout.add_field(Field::new("EN".into(), 0, 0)).unwrap();
+        layout.add_field(mode).unwrap();
+        layout.add_field(Field::new("DIV".into(), 8, 15)).unwrap();
+        layout
+    }
+
+    #[test]
+    fn test_field() {
+        let field = Field::new("DIV".into(), 8, 15);
+        assert_eq!(field.bits(), 8);
+        assert_eq!(field.range(), "15..8");
+        assert_eq!(field.get(0x1a05), 0x1a);
+        assert_eq!(field.set(0xffff, 0x12), Ok(0x12ff));
+        assert_eq!(
+            field.set(0, 0x100),
+            Err(ValueErrorKind::TooWide { bits: 8 })
+        );
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut layout = ctrl();
+        assert_eq!(layout.int_type(), IntType::unsigned(16));
+        assert_eq!(
+            Layout::with_size("R".into(), 32).int_type(),
+            IntType::unsigned(32)
+        );
+        assert_eq!(layout.unmapped(0xffff), 0xf8);
+        assert_eq!(layout.overlap(&Field::new("X".into(), 3, 7)), None);
+        assert_eq!(layout.overlap(&Field::new("X".into(), 2, 9)), Some(2));
+        assert_eq!(
+            layout.add_field(Field::new("EN".into(), 3, 3)),
+            Err(ValueErrorKind::DuplicateField)
+        );
+        assert_eq!(
+            layout.to_string(),
+            "layout CTRL { EN: 0, MODE: 2..1 { OFF: 0, FAST: 2 }, DIV: 15..8 }"
+        );
+    }
+
+    #[test]
+    fn test_bitfield() {
+        let value = Bitfield::try_new(ctrl(), Scalar::new(0x1a05i128, None)).unwrap();
+        assert_eq!(value.to_string(), "CTRL { EN: 1, MODE: FAST, DIV: 26 }");
+        assert_eq!(value.scalar().ty(), Some(IntType::unsigned(16).into()));
+
+        assert_eq!(
+            Bitfield::try_new(ctrl(), Scalar::new(0x10000i128, None)),
+            Err(ValueErrorKind::TooWide { bits: 16 })
+        );
+    }
+}
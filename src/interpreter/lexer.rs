@@ -1,10 +1,14 @@
 use miette::Diagnostic;
 use paste::paste;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use thiserror::Error;
 
 use super::{
+    bytes::unhex,
+    fixed_point::QFormat,
+    ip_type::{IpType, IpVersion},
     num::{from_slice_radix, ParseIntError},
-    token::{token, FullUnit, Token, Unit},
+    token::{token, FullUnit, Token, TokenKind, Unit},
     unit_prefix::UnitPrefix,
 };
 
@@ -14,6 +18,20 @@ pub enum LexErrorKind {
     UnexpectedCharacter,
     #[error("Invalid digit")]
     InvalidDigit(#[source] super::num::ParseIntError),
+    #[error("Invalid fixed point format")]
+    InvalidQFormat,
+    #[error("Unterminated string")]
+    UnterminatedString,
+    #[error("Invalid escape sequence")]
+    InvalidEscape,
+    #[error("Invalid hex string")]
+    InvalidHexString,
+    #[error("Invalid character literal")]
+    InvalidChar,
+    #[error("Invalid IP address")]
+    InvalidAddress,
+    #[error("Invalid prefix length")]
+    InvalidPrefix,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Error, Diagnostic)]
@@ -66,28 +84,292 @@ fn parse_hex_nr(s: &[u8]) -> Result<(u64, &[u8]), ParseIntError> {
     parse_nr::<16>(s, |c| !c.is_ascii_hexdigit())
 }
 
+/// Parses a decimal float literal like `1.5`, if `s` starts with one.
+#[inline]
+fn parse_float(s: &[u8]) -> Option<(f64, &[u8])> {
+    let int_len = s
+        .iter()
+        .position(|c| !c.is_ascii_digit())
+        .unwrap_or(s.len());
+    let frac_len = match &s[int_len..] {
+        [b'.', frac @ ..] => frac
+            .iter()
+            .position(|c| !c.is_ascii_digit())
+            .unwrap_or(frac.len()),
+        _ => return None,
+    };
+    if int_len == 0 || frac_len == 0 {
+        return None;
+    }
+
+    let (num, rest) = s.split_at(int_len + 1 + frac_len);
+    // The literal only consists of ASCII digits and a dot
+    let val = std::str::from_utf8(num).ok()?.parse().ok()?;
+    Some((val, rest))
+}
+
+#[inline]
+fn is_word_start(c: &u8) -> bool {
+    c.is_ascii_alphabetic() || *c == b'_'
+}
+
+#[inline]
+fn is_word_char(c: &u8) -> bool {
+    c.is_ascii_alphanumeric() || *c == b'_'
+}
+
+#[inline]
+fn split_word(s: &[u8]) -> (&[u8], &[u8]) {
+    let end = s.iter().position(|c| !is_word_char(c)).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Parses a string literal like `"abc"`, `b"abc\n"` or `x"dead beef"` into its bytes, which `s` has to
+/// start with. Strings without a prefix are UTF-8 encoded.
+///
+/// `loc` is the location of `s` in the input, used for errors.
+fn parse_byte_string(s: &[u8], loc: usize) -> Result<(Vec<u8>, &[u8]), LexError> {
+    let (is_hex, start, content) = match s {
+        [b'"', content @ ..] => (false, 1, content),
+        [c, b'"', content @ ..] => (*c == b'x', 2, content),
+        _ => unreachable!("Strings start with an optional prefix and a quote"),
+    };
+
+    let mut bytes = Vec::new();
+    let mut i = 0;
+    loop {
+        match &content[i..] {
+            [] => return Err(LexError::new(LexErrorKind::UnterminatedString, loc)),
+            [b'"', ..] => break,
+            [b'\\', escape @ ..] if !is_hex => {
+                let (byte, len) = match escape {
+                    [b'n', ..] => (b'\n', 1),
+                    [b'r', ..] => (b'\r', 1),
+                    [b't', ..] => (b'\t', 1),
+                    [b'0', ..] => (0, 1),
+                    [c @ (b'\\' | b'"'), ..] => (*c, 1),
+                    [b'x', hi, lo, ..] => match unhex(&[*hi, *lo]) {
+                        Some(byte) => (byte[0], 3),
+                        None => {
+                            return Err(LexError::new(LexErrorKind::InvalidEscape, loc + start + i))
+                        }
+                    },
+                    _ => return Err(LexError::new(LexErrorKind::InvalidEscape, loc + start + i)),
+                };
+                bytes.push(byte);
+                i += 1 + len;
+            }
+            [c, ..] => {
+                bytes.push(*c);
+                i += 1;
+            }
+        }
+    }
+
+    if is_hex {
+        bytes = unhex(&bytes).ok_or(LexError::new(LexErrorKind::InvalidHexString, loc + start))?;
+    }
+    Ok((bytes, &content[i + 1..]))
+}
+
+/// Parses a character literal like `'A'`, `'\n'` or `'\u{1F600}'` into its code point, which `s` has to
+/// start with.
+///
+/// `loc` is the location of `s` in the input, used for errors.
+fn parse_char(s: &[u8], loc: usize) -> Result<(u64, &[u8]), LexError> {
+    let err = LexError::new(LexErrorKind::InvalidChar, loc);
+    let (body, rest) = match &s[1..] {
+        [b'\\', b'\'', b'\'', rest @ ..] => (&s[1..3], rest),
+        content => {
+            let end = content
+                .iter()
+                .position(|c| *c == b'\'')
+                .ok_or(err.clone())?;
+            (&content[..end], &content[end + 1..])
+        }
+    };
+
+    let c = match body {
+        [b'\\', b'n'] => '\n',
+        [b'\\', b'r'] => '\r',
+        [b'\\', b't'] => '\t',
+        [b'\\', b'0'] => '\0',
+        [b'\\', c @ (b'\\' | b'\'' | b'"')] => *c as char,
+        [b'\\', b'u', b'{', hex @ .., b'}'] if (1..=6).contains(&hex.len()) => {
+            let code = hex
+                .iter()
+                .try_fold(0, |acc, c| Some(acc << 4 | (*c as char).to_digit(16)?));
+            code.and_then(char::from_u32).ok_or(err)?
+        }
+        [b'\\', ..] => return Err(err),
+        // The input is valid UTF-8, but the body may still consist of more than one character
+        _ => {
+            let mut chars = std::str::from_utf8(body).map_err(|_| err.clone())?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(err),
+            }
+        }
+    };
+    Ok((c.into(), rest))
+}
+
+/// Returns the length of the IP address `s` starts with, if it starts with something shaped like one, i.e.
+/// four dot separated numbers or hex digits containing at least two colons. Single colons are left to
+/// field separators like `EN:1`.
+fn address_len(s: &[u8]) -> Option<usize> {
+    let len = |allowed: fn(&u8) -> bool| s.iter().position(|c| !allowed(c)).unwrap_or(s.len());
+
+    let v6_len = len(|c| c.is_ascii_hexdigit() || matches!(c, b':' | b'.'));
+    if s[..v6_len].iter().filter(|c| **c == b':').count() >= 2 {
+        return Some(v6_len);
+    }
+    let v4_len = len(|c| c.is_ascii_digit() || *c == b'.');
+    (s[..v4_len].iter().filter(|c| **c == b'.').count() == 3).then_some(v4_len)
+}
+
+/// Parses an IP address like `10.0.0.1` or `fe80::1`, optionally followed by a prefix length like `/24`,
+/// which `s` has to start with and which is `len` bytes long without the prefix length.
+///
+/// `loc` is the location of `s` in the input, used for errors.
+fn parse_address(s: &[u8], len: usize, loc: usize) -> Result<(IpType, u128, &[u8]), LexError> {
+    let err = LexError::new(LexErrorKind::InvalidAddress, loc);
+    let (addr, rest) = s.split_at(len);
+    // Addresses only consist of ASCII characters
+    let addr = std::str::from_utf8(addr).map_err(|_| err.clone())?;
+    let (ty, bits) = if addr.contains(':') {
+        let addr = addr.parse::<Ipv6Addr>().map_err(|_| err)?;
+        (IpType::new(IpVersion::V6), addr.into())
+    } else {
+        let addr = addr.parse::<Ipv4Addr>().map_err(|_| err)?;
+        (IpType::new(IpVersion::V4), u32::from(addr).into())
+    };
+
+    match rest {
+        [b'/', prefix @ ..] if prefix.first().is_some_and(u8::is_ascii_digit) => {
+            let err = LexError::new(LexErrorKind::InvalidPrefix, loc + len + 1);
+            let (prefix, rest) = parse_dec_nr(prefix).map_err(|_| err.clone())?;
+            let ty = u32::try_from(prefix)
+                .ok()
+                .and_then(|prefix| ty.with_prefix(prefix))
+                .ok_or(err)?;
+            Ok((ty, bits, rest))
+        }
+        _ => Ok((ty, bits, rest)),
+    }
+}
+
+/// Parses a fixed point format like `q15`, `q1.15` or `uq8.8`, if `s` starts with one.
+///
+/// The format is `None` if `s` starts with something shaped like a format that is not a valid one.
+fn parse_q_format(s: &[u8]) -> Option<(Option<QFormat>, &[u8])> {
+    let (signed, s) = match s {
+        [b'u', b'q', rest @ ..] => (false, rest),
+        [b'q', rest @ ..] => (true, rest),
+        _ => return None,
+    };
+
+    let digits = |s: &[u8]| {
+        s.iter()
+            .position(|c| !c.is_ascii_digit())
+            .unwrap_or(s.len())
+    };
+    let (first, mut rest) = s.split_at(digits(s));
+    let mut second = None;
+    if let [b'.', frac @ ..] = rest {
+        let (frac, frac_rest) = frac.split_at(digits(frac));
+        if !frac.is_empty() {
+            second = Some(frac);
+            rest = frac_rest;
+        }
+    }
+    if first.is_empty() || rest.first().is_some_and(is_word_char) {
+        return None;
+    }
+
+    // The parts only consist of ASCII digits
+    let parse = |digits: &[u8]| std::str::from_utf8(digits).ok()?.parse::<u32>().ok();
+    let (int_bits, frac_bits) = match second {
+        Some(second) => (parse(first), parse(second)),
+        // `qN` has a sign bit and `uqN` no integer bits at all
+        None => (Some(signed as u32), parse(first)),
+    };
+    let q = int_bits
+        .zip(frac_bits)
+        .and_then(|(int_bits, frac_bits)| QFormat::new(signed, int_bits, frac_bits));
+    Some((q, rest))
+}
+
+fn parse_unit(word: &[u8]) -> Option<FullUnit> {
+    let (unit, prefix) = word.split_last()?;
+    let unit = match unit {
+        b'b' => Unit::Bit,
+        b'B' => Unit::Byte,
+        _ => return None,
+    };
+    let prefix = UnitPrefix::try_from(std::str::from_utf8(prefix).ok()?).ok()?;
+    Some(FullUnit(prefix, unit))
+}
+
+/// Returns the length of the cast target at the start of `input` that converts sizes to the unit with the
+/// best fitting decimal or binary prefix, i.e. `auto-si` or `auto-iec`.
+fn auto_unit_len(input: &[u8]) -> Option<usize> {
+    [b"auto-si".as_slice(), b"auto-iec"]
+        .into_iter()
+        .find(|target| {
+            input.starts_with(target) && !input.get(target.len()).is_some_and(is_word_char)
+        })
+        .map(<[u8]>::len)
+}
+
+/// Classifies a word as either a keyword, a unit or an identifier.
+fn word_kind(word: &[u8]) -> TokenKind {
+    match word {
+        b"as" => TokenKind::As,
+        b"layout" => TokenKind::Layout,
+        b"let" => TokenKind::Let,
+        _ => match parse_unit(word) {
+            Some(unit) => TokenKind::Unit(unit),
+            // Words only consist of ASCII characters
+            None => TokenKind::Identifier(String::from_utf8_lossy(word).into_owned()),
+        },
+    }
+}
+
 pub struct Lexer<'a> {
+    /// The whole input, which the locations of the tokens refer to.
+    source: &'a str,
     input: Option<&'a [u8]>,
     current: usize,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &'a str) -> Self {
-        let mut input = input.as_bytes();
+    pub fn new(source: &'a str) -> Self {
+        let mut input = source.as_bytes();
         let old_len = input.len();
         input = input.trim_ascii_start();
 
         Self {
+            source,
             input: Some(input),
             current: old_len - input.len(),
         }
     }
 
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Skips whitespace and `#` comments, which extend to the end of their line.
     #[inline]
     fn trim_whitespace(&mut self) {
         if let Some(mut input) = self.input {
             let old_len = input.len();
             input = input.trim_ascii_start();
+            while let [b'#', rest @ ..] = input {
+                let len = rest.iter().position(|c| *c == b'\n').unwrap_or(rest.len());
+                input = rest[len..].trim_ascii_start();
+            }
             self.input = Some(input);
             self.current += old_len - input.len();
         }
@@ -123,15 +405,6 @@ impl Iterator for Lexer<'_> {
             };
         }
 
-        macro_rules! unit {
-            ($prefix:expr, $unit:expr, $len:expr) => {
-                token!(Unit(FullUnit($prefix, $unit)), self.span($len))
-            };
-            ($unit:expr, $len:expr) => {
-                unit!(UnitPrefix::None, $unit, $len)
-            };
-        }
-
         macro_rules! parse_as {
             ($rad:ident, $input:ident, $offset:literal) => {{
                 paste! {
@@ -148,17 +421,13 @@ impl Iterator for Lexer<'_> {
             };
         }
 
-        macro_rules! parse_unit {
-            ($input:ident, $prefix:expr, $len:literal) => {{
-                match $input {
-                    [b'b', rest @ ..] => (unit!($prefix, Unit::Bit, $len + 1), rest),
-                    [b'B', rest @ ..] => (unit!($prefix, Unit::Byte, $len + 1), rest),
-                    _ => return Some(Err(LE::new(LEK::UnexpectedCharacter, self.current))),
-                }
-            }};
-        }
-
         let (token, rest) = match input {
+            // Multi character tokens
+            [b'<', b'<', rest @ ..] => (tok!(LessLess, 2), rest),
+            [b'>', b'>', b'>', rest @ ..] => (tok!(GreaterGreaterGreater, 3), rest),
+            [b'>', b'>', rest @ ..] => (tok!(GreaterGreater, 2), rest),
+            [b'.', b'.', rest @ ..] => (tok!(DotDot, 2), rest),
+            [b'.', rest @ ..] => (tok!(Dot, 1), rest),
             // Single character tokens
             [b'-', rest @ ..] => (tok!(Minus, 1), rest),
             [b'+', rest @ ..] => (tok!(Plus, 1), rest),
@@ -166,11 +435,31 @@ impl Iterator for Lexer<'_> {
             [b'/', rest @ ..] => (tok!(Slash, 1), rest),
             [b'(', rest @ ..] => (tok!(LeftParen, 1), rest),
             [b')', rest @ ..] => (tok!(RightParen, 1), rest),
-            [b'b', rest @ ..] => (unit!(Unit::Bit, 1), rest),
-            [b'B', rest @ ..] => (unit!(Unit::Byte, 1), rest),
-            // Keywords
-            [b'a', b's', rest @ ..] => (tok!(As, 2), rest),
+            [b'[', rest @ ..] => (tok!(LeftBracket, 1), rest),
+            [b']', rest @ ..] => (tok!(RightBracket, 1), rest),
+            [b'{', rest @ ..] => (tok!(LeftBrace, 1), rest),
+            [b'}', rest @ ..] => (tok!(RightBrace, 1), rest),
+            [b':', rest @ ..] if address_len(input).is_none() => (tok!(Colon, 1), rest),
+            [b'&', rest @ ..] => (tok!(Ampersand, 1), rest),
+            [b'|', rest @ ..] => (tok!(Pipe, 1), rest),
+            [b'^', rest @ ..] => (tok!(Caret, 1), rest),
+            [b'~', rest @ ..] => (tok!(Tilde, 1), rest),
+            [b',', rest @ ..] => (tok!(Comma, 1), rest),
+            [b'=', rest @ ..] => (tok!(Equal, 1), rest),
             // Literals
+            [c, ..] if (c.is_ascii_hexdigit() || *c == b':') && address_len(input).is_some() => {
+                let len = address_len(input).unwrap();
+                match parse_address(input, len, self.current) {
+                    Ok((ty, bits, rest)) => {
+                        (tok!(Address(ty, bits), input.len() - rest.len()), rest)
+                    }
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            [b'0'..=b'9', ..] if parse_float(input).is_some() => {
+                let (val, rest) = parse_float(input).unwrap();
+                (tok!(Float(val), input.len() - rest.len()), rest)
+            }
             [b'0', c, rest @ ..] => match c {
                 b'b' => parse_as!(bin, rest, 2),
                 b'o' => parse_as!(oct, rest, 2),
@@ -178,21 +467,48 @@ impl Iterator for Lexer<'_> {
                 _ => parse_as!(dec, input),
             },
             [b'0'..=b'9', ..] => parse_as!(dec, input),
-            [b'k' | b'K', b'i' | b'I', rest @ ..] => parse_unit!(rest, UnitPrefix::Kibi, 2),
-            [b'm' | b'M', b'i' | b'I', rest @ ..] => parse_unit!(rest, UnitPrefix::Mebi, 2),
-            [b'g' | b'G', b'i' | b'I', rest @ ..] => parse_unit!(rest, UnitPrefix::Gibi, 2),
-            [b't' | b'T', b'i' | b'I', rest @ ..] => parse_unit!(rest, UnitPrefix::Tebi, 2),
-            [b'p' | b'P', b'i' | b'I', rest @ ..] => parse_unit!(rest, UnitPrefix::Pebi, 2),
-            [b'e' | b'E', b'i' | b'I', rest @ ..] => parse_unit!(rest, UnitPrefix::Exbi, 2),
-            [b'k' | b'K', rest @ ..] => parse_unit!(rest, UnitPrefix::Kilo, 1),
-            [b'm' | b'M', rest @ ..] => parse_unit!(rest, UnitPrefix::Mega, 1),
-            [b'g' | b'G', rest @ ..] => parse_unit!(rest, UnitPrefix::Giga, 1),
-            [b't' | b'T', rest @ ..] => parse_unit!(rest, UnitPrefix::Tera, 1),
-            [b'p' | b'P', rest @ ..] => parse_unit!(rest, UnitPrefix::Peta, 1),
-            [b'e' | b'E', rest @ ..] => parse_unit!(rest, UnitPrefix::Exa, 1),
+            [b'\'', ..] => match parse_char(input, self.current) {
+                Ok((code, rest)) => (tok!(Integer(code), input.len() - rest.len()), rest),
+                Err(e) => return Some(Err(e)),
+            },
+            [b'"', ..] | [b'b' | b'x', b'"', ..] => match parse_byte_string(input, self.current) {
+                Ok((bytes, rest)) => (tok!(Bytes(bytes), input.len() - rest.len()), rest),
+                Err(e) => return Some(Err(e)),
+            },
+            [b'q' | b'u', ..] if parse_q_format(input).is_some() => {
+                let (q, rest) = parse_q_format(input).unwrap();
+                match q {
+                    Some(q) => (tok!(QFormat(q), input.len() - rest.len()), rest),
+                    None => return Some(Err(LE::new(LEK::InvalidQFormat, self.current))),
+                }
+            }
+            // Keywords, units and identifiers
+            [b'a', ..] if auto_unit_len(input).is_some() => {
+                let (word, rest) = input.split_at(auto_unit_len(input).unwrap());
+                let name = String::from_utf8_lossy(word).into_owned();
+                (
+                    Token::new(TokenKind::Identifier(name), self.span(word.len())),
+                    rest,
+                )
+            }
+            [c, ..] if is_word_start(c) => {
+                let (word, rest) = split_word(input);
+                (Token::new(word_kind(word), self.span(word.len())), rest)
+            }
             _ => return Some(Err(LE::new(LEK::UnexpectedCharacter, self.current))),
         };
 
+        // Numbers may only be directly followed by a unit, e.g. `42KiB`
+        if matches!(token.kind(), TokenKind::Integer(_) | TokenKind::Float(_))
+            && rest.first().is_some_and(is_word_char)
+        {
+            let (word, _) = split_word(rest);
+            if !matches!(word_kind(word), TokenKind::Unit(_)) {
+                let loc = self.current + token.len();
+                return Some(Err(LE::new(LEK::UnexpectedCharacter, loc)));
+            }
+        }
+
         self.current += token.len();
         self.input = Some(rest);
 
@@ -256,6 +572,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lexer_comment() {
+        let tokens = lex!("# mask\n42 # answer\n+ 1 #").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Integer(42), 7..9),
+                token!(Plus, 19..20),
+                token!(Integer(1), 21..22),
+                token!(Eof, 24..24),
+            ]
+        );
+    }
+
     #[test]
     fn test_lexer_unit_prefix() {
         let tokens = lex!("42Kib").unwrap();
@@ -367,7 +697,295 @@ mod tests {
         let res = lex!("0a").unwrap_err();
         assert_eq!(res, LE::new(LEK::UnexpectedCharacter, 1));
 
-        let res = lex!("ak").unwrap_err();
+        let res = lex!("$").unwrap_err();
         assert_eq!(res, LE::new(LEK::UnexpectedCharacter, 0));
+
+        let res = lex!("1 >= 2").unwrap_err();
+        assert_eq!(res, LE::new(LEK::UnexpectedCharacter, 2));
+    }
+
+    #[test]
+    fn test_parse_float() {
+        assert_eq!(parse_float(b"1.5 + 1"), Some((1.5, &b" + 1"[..])));
+        assert_eq!(parse_float(b"0.125"), Some((0.125, &b""[..])));
+        assert_eq!(parse_float(b"1."), None);
+        assert_eq!(parse_float(b"1"), None);
+        assert_eq!(parse_float(b".5"), None);
+    }
+
+    #[test]
+    fn test_lexer_float() {
+        let tokens = lex!("1.5KiB as f32bits").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Float(1.5), 0..3),
+                token!(Unit(FullUnit(UnitPrefix::Kibi, Unit::Byte)), 3..6),
+                token!(As, 7..9),
+                token!(Identifier("f32bits".into()), 10..17),
+                token!(Eof, 17..17),
+            ]
+        );
+
+        let res = lex!("1.5x").unwrap_err();
+        assert_eq!(res, LexError::new(LexErrorKind::UnexpectedCharacter, 3));
+    }
+
+    #[test]
+    fn test_lexer_identifier() {
+        let tokens = lex!("sext(x_1, 12) as u8").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Identifier("sext".into()), 0..4),
+                token!(LeftParen, 4..5),
+                token!(Identifier("x_1".into()), 5..8),
+                token!(Comma, 8..9),
+                token!(Integer(12), 10..12),
+                token!(RightParen, 12..13),
+                token!(As, 14..16),
+                token!(Identifier("u8".into()), 17..19),
+                token!(Eof, 19..19),
+            ]
+        );
+
+        let tokens = lex!("ak bswap16 kb").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Identifier("ak".into()), 0..2),
+                token!(Identifier("bswap16".into()), 3..10),
+                token!(Unit(FullUnit(UnitPrefix::Kilo, Unit::Bit)), 11..13),
+                token!(Eof, 13..13),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_auto_unit() {
+        let tokens = lex!("x as auto-iec - auto-sign").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Identifier("x".into()), 0..1),
+                token!(As, 2..4),
+                token!(Identifier("auto-iec".into()), 5..13),
+                token!(Minus, 14..15),
+                token!(Identifier("auto".into()), 16..20),
+                token!(Minus, 20..21),
+                token!(Identifier("sign".into()), 21..25),
+                token!(Eof, 25..25),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_q_format() {
+        let q = |signed, int_bits, frac_bits| QFormat::new(signed, int_bits, frac_bits).unwrap();
+
+        let tokens = lex!("x as q15 as q1.15 as uq8.8 as uq16").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Identifier("x".into()), 0..1),
+                token!(As, 2..4),
+                token!(QFormat(q(true, 1, 15)), 5..8),
+                token!(As, 9..11),
+                token!(QFormat(q(true, 1, 15)), 12..17),
+                token!(As, 18..20),
+                token!(QFormat(q(false, 8, 8)), 21..26),
+                token!(As, 27..29),
+                token!(QFormat(q(false, 0, 16)), 30..34),
+                token!(Eof, 34..34),
+            ]
+        );
+
+        let tokens = lex!("q qux q1x u8").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Identifier("q".into()), 0..1),
+                token!(Identifier("qux".into()), 2..5),
+                token!(Identifier("q1x".into()), 6..9),
+                token!(Identifier("u8".into()), 10..12),
+                token!(Eof, 12..12),
+            ]
+        );
+
+        let res = lex!("1 as q0.15").unwrap_err();
+        assert_eq!(res, LexError::new(LexErrorKind::InvalidQFormat, 5));
+
+        let res = lex!("1 as uq40.40").unwrap_err();
+        assert_eq!(res, LexError::new(LexErrorKind::InvalidQFormat, 5));
+    }
+
+    #[test]
+    fn test_lexer_bytes() {
+        let tokens = lex!(r#"[0x12, 0x34] + b"a\"\x00\n" + x"dead BEEF""#).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(LeftBracket, 0..1),
+                token!(Integer(0x12), 1..5),
+                token!(Comma, 5..6),
+                token!(Integer(0x34), 7..11),
+                token!(RightBracket, 11..12),
+                token!(Plus, 13..14),
+                token!(Bytes(b"a\"\0\n".to_vec()), 15..27),
+                token!(Plus, 28..29),
+                token!(Bytes(vec![0xde, 0xad, 0xbe, 0xef]), 30..42),
+                token!(Eof, 42..42),
+            ]
+        );
+
+        let tokens = lex!("b + x").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Unit(FullUnit(UnitPrefix::None, Unit::Bit)), 0..1),
+                token!(Plus, 2..3),
+                token!(Identifier("x".into()), 4..5),
+                token!(Eof, 5..5),
+            ]
+        );
+
+        use LexErrorKind as LEK;
+        let res = lex!(r#"1 + b"abc"#).unwrap_err();
+        assert_eq!(res, LexError::new(LEK::UnterminatedString, 4));
+
+        let res = lex!(r#"b"a\q""#).unwrap_err();
+        assert_eq!(res, LexError::new(LEK::InvalidEscape, 3));
+
+        let res = lex!(r#"x"abc""#).unwrap_err();
+        assert_eq!(res, LexError::new(LEK::InvalidHexString, 2));
+    }
+
+    #[test]
+    fn test_lexer_layout() {
+        let tokens = lex!("layout R { A: 7..0 }").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Layout, 0..6),
+                token!(Identifier("R".into()), 7..8),
+                token!(LeftBrace, 9..10),
+                token!(Identifier("A".into()), 11..12),
+                token!(Colon, 12..13),
+                token!(Integer(7), 14..15),
+                token!(DotDot, 15..17),
+                token!(Integer(0), 17..18),
+                token!(RightBrace, 19..20),
+                token!(Eof, 20..20),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_string() {
+        let tokens = lex!(r#""rw-r--r--" + "€\t""#).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Bytes(b"rw-r--r--".to_vec()), 0..11),
+                token!(Plus, 12..13),
+                token!(Bytes("€\t".as_bytes().to_vec()), 14..21),
+                token!(Eof, 21..21),
+            ]
+        );
+
+        let res = lex!(r#""a\q""#).unwrap_err();
+        assert_eq!(res, LexError::new(LexErrorKind::InvalidEscape, 2));
+    }
+
+    #[test]
+    fn test_lexer_char() {
+        let tokens = lex!(r"'A' + '\u{1F600}' + '€' + '\n' + '\''").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Integer(0x41), 0..3),
+                token!(Plus, 4..5),
+                token!(Integer(0x1f600), 6..17),
+                token!(Plus, 18..19),
+                token!(Integer(0x20ac), 20..25),
+                token!(Plus, 26..27),
+                token!(Integer(0x0a), 28..32),
+                token!(Plus, 33..34),
+                token!(Integer(0x27), 35..39),
+                token!(Eof, 39..39),
+            ]
+        );
+
+        for input in [
+            "'ab'",
+            "''",
+            "'a",
+            r"'\q'",
+            r"'\u{d800}'",
+            r"'\u{}'",
+            r"'\u{+41}'",
+        ] {
+            let res = lex!(input).unwrap_err();
+            assert_eq!(res, LexError::new(LexErrorKind::InvalidChar, 0), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_lexer_address() {
+        let v4 = IpType::new(IpVersion::V4);
+        let v6 = IpType::new(IpVersion::V6);
+        let tokens = lex!("10.0.0.0/8 & fe80::1 / 2 + ::").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Address(v4.with_prefix(8).unwrap(), 0x0a00_0000), 0..10),
+                token!(Ampersand, 11..12),
+                token!(Address(v6, 0xfe80 << 112 | 1), 13..20),
+                token!(Slash, 21..22),
+                token!(Integer(2), 23..24),
+                token!(Plus, 25..26),
+                token!(Address(v6, 0), 27..29),
+                token!(Eof, 29..29),
+            ]
+        );
+
+        let tokens = lex!("1.5 abc").unwrap();
+        assert_eq!(tokens[0], token!(Float(1.5), 0..3));
+
+        for input in ["256.0.0.1", "1.2.3.", "fe80:::1", "1::2::3"] {
+            let res = lex!(input).unwrap_err();
+            assert_eq!(
+                res,
+                LexError::new(LexErrorKind::InvalidAddress, 0),
+                "{input}"
+            );
+        }
+        let res = lex!("1.2.3.4/33").unwrap_err();
+        assert_eq!(res, LexError::new(LexErrorKind::InvalidPrefix, 8));
+    }
+
+    #[test]
+    fn test_lexer_bitwise_operators() {
+        let tokens = lex!("1<<2>>3>>>4&5|6^~7").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token!(Integer(1), 0..1),
+                token!(LessLess, 1..3),
+                token!(Integer(2), 3..4),
+                token!(GreaterGreater, 4..6),
+                token!(Integer(3), 6..7),
+                token!(GreaterGreaterGreater, 7..10),
+                token!(Integer(4), 10..11),
+                token!(Ampersand, 11..12),
+                token!(Integer(5), 12..13),
+                token!(Pipe, 13..14),
+                token!(Integer(6), 14..15),
+                token!(Caret, 15..16),
+                token!(Tilde, 16..17),
+                token!(Integer(7), 17..18),
+                token!(Eof, 18..18),
+            ]
+        );
     }
 }
@@ -1,20 +1,43 @@
+mod builtins;
+pub mod byte_order;
+pub mod bytes;
+pub mod char_type;
+pub mod diff;
+pub mod environment;
 pub mod expr;
+pub mod fixed_point;
+pub mod float_type;
+pub mod int_type;
+pub mod ip_type;
+pub mod layout;
 pub mod lexer;
-mod num;
+pub mod num;
 pub mod parser;
+pub mod perms;
+pub mod table;
 pub mod unit_prefix;
 pub mod value;
 
 #[macro_use]
-mod token;
+pub mod token;
+pub mod trace;
 
 use miette::Diagnostic;
 use std::ops::Range;
 use thiserror::Error;
 
-use expr::Expr;
+use byte_order::ByteOrder;
+use char_type::CharType;
+use environment::{Definition, Entry, Environment};
+use expr::{Expr, LayoutDecl, LetDecl, Stmt};
+use float_type::FloatType;
+use int_type::IntType;
+use ip_type::IpType;
+use layout::{Bitfield, Field, Layout};
+use num::Number;
+use table::Table;
 use token::Token;
-use value::Value;
+use value::{Scalar, Value};
 
 #[derive(Debug, Clone, PartialEq, Error, Diagnostic)]
 #[error(transparent)]
@@ -25,7 +48,7 @@ pub enum SyntaxErrorKind {
 }
 
 #[derive(Debug, Clone, PartialEq, Error, Diagnostic)]
-#[error("Syntax error")]
+#[error("{}", heading(kind))]
 pub struct SyntaxError {
     #[source]
     kind: SyntaxErrorKind,
@@ -33,10 +56,32 @@ pub struct SyntaxError {
     loc: Range<usize>,
 }
 
+/// Returns the heading of errors of `kind`, which tells errors of malformed statements from errors of
+/// evaluating them.
+fn heading(kind: &SyntaxErrorKind) -> &'static str {
+    match kind {
+        SyntaxErrorKind::Value(_) => "Evaluation error",
+        _ => "Syntax error",
+    }
+}
+
 impl SyntaxError {
     fn new(kind: SyntaxErrorKind, loc: Range<usize>) -> Self {
         Self { kind, loc }
     }
+
+    /// Moves the location of the error by `offset`, e.g. to locate an error of a statement in the script
+    /// containing it.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.loc = self.loc.start + offset..self.loc.end + offset;
+        self
+    }
+
+    /// Returns whether the error occurred while evaluating a well-formed statement, rather than while
+    /// lexing or parsing it.
+    pub fn is_evaluation_error(&self) -> bool {
+        matches!(self.kind, SyntaxErrorKind::Value(_))
+    }
 }
 
 impl From<SyntaxErrorKind> for SyntaxError {
@@ -73,22 +118,347 @@ impl ValueError {
     }
 }
 
-pub struct Interpreter {}
+#[derive(Clone)]
+pub struct Interpreter {
+    env: Environment,
+}
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            env: Environment::default(),
+        }
+    }
+
+    /// Interprets a statement, returning the value of expressions and the declared layout of layout
+    /// declarations.
+    pub fn interpret(&mut self, input: &str) -> Result<Value, SyntaxError> {
+        let stmt = Self::parse(input)?;
+        let value = self.execute(&stmt)?;
+        match (stmt, &value) {
+            (Stmt::Layout(_), Value::Layout(layout)) => self.env.define_layout(layout.clone()),
+            (Stmt::Let(decl), value) => {
+                let expr = input[decl.loc].to_string();
+                self.env
+                    .define_value(identifier(&decl.name), expr, value.clone());
+            }
+            _ => {}
+        }
+        Ok(value)
+    }
+
+    /// Interprets a statement like [`Interpreter::interpret`], but without any side effects, i.e. layouts
+    /// and values are not defined.
+    pub fn preview(&self, input: &str) -> Result<Value, SyntaxError> {
+        self.execute(&Self::parse(input)?)
+    }
+
+    /// Checks that `input` is a well-formed statement, without evaluating it.
+    pub fn check(input: &str) -> Result<(), SyntaxError> {
+        Self::parse(input)?;
+        Ok(())
+    }
+
+    /// Evaluates the statement `input` like [`Interpreter::preview`], tracing the value of each of its
+    /// sub-expressions and the implicit conversions done to compute them.
+    pub fn explain(&self, input: &str) -> Result<trace::Trace, SyntaxError> {
+        match Self::parse(input)? {
+            Stmt::Expr(expr) | Stmt::Let(LetDecl { expr, .. }) => {
+                let mut trace = trace::trace(&expr, &self.env)?;
+                trace.enclose_brackets(input);
+                Ok(trace)
+            }
+            stmt => Ok(trace::Trace {
+                loc: 0..input.len(),
+                value: self.execute(&stmt)?,
+                notes: vec![],
+                steps: vec![],
+            }),
+        }
     }
 
-    pub fn interpret(&self, input: &str) -> Result<Value, SyntaxError> {
+    /// Parses the statement `input`, without evaluating it.
+    pub fn parse(input: &str) -> Result<Stmt, SyntaxError> {
         let lexer = lexer::Lexer::new(input);
-        let mut parser = parser::Parser::new(lexer);
-        let expr = parser.parse()?;
-        evaluate(&expr)
+        Ok(parser::Parser::new(lexer).parse()?)
+    }
+
+    /// Returns the tokens of `input`.
+    pub fn tokens(input: &str) -> Result<Vec<Token>, SyntaxError> {
+        let tokens = lexer::Lexer::new(input).collect::<Result<Vec<_>, _>>();
+        tokens.map_err(|e| SyntaxErrorKind::Lex(e).into())
+    }
+
+    /// Evaluates the parsed expression `expr`, e.g. one that is parsed once and evaluated for many values
+    /// of a variable.
+    pub fn evaluate(&self, expr: &Expr) -> Result<Value, SyntaxError> {
+        evaluate(expr, &self.env)
+    }
+
+    /// Returns the value of `stmt`, without defining anything.
+    fn execute(&self, stmt: &Stmt) -> Result<Value, SyntaxError> {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Let(LetDecl { expr, .. }) => evaluate(expr, &self.env),
+            Stmt::Layout(decl) => Ok(Value::Layout(declare_layout(decl)?)),
+        }
+    }
+
+    /// Defines `layout`, replacing any previous layout of the same name.
+    pub fn define_layout(&mut self, layout: Layout) {
+        self.env.define_layout(layout);
+    }
+
+    /// Defines the named value `name` as the value of `expr`, replacing any previous value of the same
+    /// name.
+    pub fn define_value(&mut self, name: String, expr: String, value: Value) {
+        self.env.define_value(name, expr, value);
+    }
+
+    pub fn definition(&self, name: &str) -> Option<&Definition> {
+        self.env.value(name)
+    }
+
+    /// Returns the layouts, in order of their names.
+    pub fn layouts(&self) -> impl Iterator<Item = &Layout> {
+        self.env.layouts()
+    }
+
+    /// Returns the named values with their definitions, in the order they were defined.
+    pub fn definitions(&self) -> Vec<(&str, &Definition)> {
+        self.env.definitions()
+    }
+
+    /// Returns the definitions in the order they were made, which recreate the environment when
+    /// evaluated in order.
+    pub fn history(&self) -> &[Entry] {
+        self.env.history()
+    }
+
+    /// Returns the names of the functions, layouts and named values, e.g. for completion.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        functions().chain(self.env.names())
     }
+
+    /// Returns whether `name` is a built-in function or `table`.
+    pub fn is_function(name: &str) -> bool {
+        functions().any(|function| function == name)
+    }
+}
+
+/// Returns the names of the built-in functions and of `table`, which is part of the grammar.
+fn functions<'a>() -> impl Iterator<Item = &'a str> {
+    builtins::names().chain(["table"])
+}
+
+/// Returns the value of an integer token.
+fn int_token(token: &Token) -> u64 {
+    match token.kind() {
+        token::TokenKind::Integer(value) => value,
+        k => unreachable!("Invalid integer: {:?}", k),
+    }
+}
+
+/// Returns the name of an identifier token.
+fn identifier(token: &Token) -> String {
+    match token.kind() {
+        token::TokenKind::Identifier(name) => name,
+        k => unreachable!("Invalid identifier: {:?}", k),
+    }
+}
+
+/// Builds the layout declared by `decl`.
+fn declare_layout(decl: &LayoutDecl) -> Result<Layout, ValueError> {
+    use value::ValueErrorKind as VEK;
+
+    let bit_index = |token: &Token| match int_token(token) {
+        index @ 0..128 => Ok(index as u32),
+        _ => Err(ValueError::new(VEK::InvalidBitIndex, token.clone())),
+    };
+
+    let mut layout = Layout::new(identifier(&decl.name));
+    for field_decl in &decl.fields {
+        let first = bit_index(&field_decl.first)?;
+        let last = match &field_decl.last {
+            Some(last) => bit_index(last)?,
+            None => first,
+        };
+
+        let mut field = Field::new(identifier(&field_decl.name), first, last);
+        for (name, value) in &field_decl.variants {
+            field
+                .add_variant(identifier(name), int_token(value).into())
+                .map_err(|e| ValueError::new(e, value.clone()))?;
+        }
+        let overlap = layout.overlap(&field);
+        layout
+            .add_field(field)
+            .map_err(|e| ValueError::new(e, field_decl.name.clone()))?;
+        if let Some(bit) = overlap {
+            let kind = VEK::OverlappingField { bit };
+            return Err(ValueError::new(kind, field_decl.name.clone()));
+        }
+    }
+    Ok(layout)
+}
+
+/// Builds the table of the values of `body` with `var` bound to each integer from `start` up to `end`,
+/// which are of the type of the bounds.
+fn build_table(
+    var: &Token,
+    (start, dots, inclusive, end): (&Expr, &Token, bool, &Expr),
+    body: &Expr,
+    env: &Environment,
+) -> Result<Value, SyntaxError> {
+    use value::ValueErrorKind as VEK;
+
+    let bound = |expr: &Expr| -> Result<Scalar, SyntaxError> {
+        let value = evaluate(expr, env)?.into_scalar();
+        let value = value.and_then(|value| match value.unit() {
+            Some(_) => Err(VEK::BitwiseWithUnit),
+            None => value.try_int().map(|_| value),
+        });
+        value.map_err(|e| ValueError::new(e, dots.clone()).into())
+    };
+    let (start, end) = (bound(start)?, bound(end)?);
+    let ty = value::common_type(start.ty(), end.ty());
+    let first = start.try_int().unwrap_or_default();
+    let last = match end.try_int().unwrap_or_default() {
+        end if inclusive => end,
+        end => end.saturating_sub(1),
+    };
+    if last.saturating_sub(first) >= table::MAX_ROWS as i128 {
+        let error = VEK::TooManyRows {
+            max: table::MAX_ROWS,
+        };
+        return Err(ValueError::new(error, dots.clone()).into());
+    }
+
+    let name = identifier(var);
+    let mut table = Table::new(name.clone());
+    let mut env = env.clone();
+    for i in first..=last {
+        let index = Scalar::typed(Number::Int(i), None, ty);
+        env.define_value(name.clone(), i.to_string(), index.clone().into());
+        table.push(index, evaluate(body, &env)?);
+    }
+    Ok(Value::Table(table))
+}
+
+/// Builds a value of the layout named by `layout` from the values of its fields.
+///
+/// Field values may also be given by the name of a value of the field.
+fn build_bitfield(
+    layout: &Token,
+    fields: &[(Token, Expr)],
+    env: &Environment,
+) -> Result<Value, SyntaxError> {
+    use value::ValueErrorKind as VEK;
+
+    let layout_def = env
+        .layout(&identifier(layout))
+        .ok_or(ValueError::new(VEK::UnknownType, layout.clone()))?;
+
+    let mut bits = 0;
+    for (i, (name, expr)) in fields.iter().enumerate() {
+        let field = layout_def
+            .field(&identifier(name))
+            .ok_or(ValueError::new(VEK::UnknownField, name.clone()))?;
+        if fields[..i]
+            .iter()
+            .any(|(other, _)| other.kind() == name.kind())
+        {
+            return Err(ValueError::new(VEK::DuplicateField, name.clone()).into());
+        }
+
+        let variant = match expr {
+            Expr::Variable(variant) => field.variant(&identifier(variant)),
+            _ => None,
+        };
+        let value = match variant {
+            Some(value) => value,
+            None => {
+                let value = evaluate(expr, env)?.into_scalar();
+                let value = value.and_then(|value| match value.unit() {
+                    Some(_) => Err(VEK::BitwiseWithUnit),
+                    None => value.try_int(),
+                });
+                let value = value.map_err(|e| ValueError::new(e, name.clone()))?;
+                u128::try_from(value).map_err(|_| {
+                    ValueError::new(VEK::TooWide { bits: field.bits() }, name.clone())
+                })?
+            }
+        };
+        bits = field
+            .set(bits, value)
+            .map_err(|e| ValueError::new(e, name.clone()))?;
+    }
+
+    let value = Scalar::new(Number::Int(bits as i128), None);
+    Bitfield::try_new(layout_def.clone(), value)
+        .map(Value::Bitfield)
+        .map_err(|e| ValueError::new(e, layout.clone()).into())
+}
+
+/// Casts `value` to the type named `target`, which may also be a layout defined in `env`.
+fn cast(value: Value, target: &str, env: &Environment) -> Result<Value, value::ValueErrorKind> {
+    // Integer types with a byte order, e.g. `u16le`, convert between integers and byte arrays
+    let split = target.len().checked_sub(2).map(|i| target.split_at(i));
+    if let Some((Ok(ty), Ok(order))) =
+        split.map(|(ty, order)| (IntType::try_from(ty), ByteOrder::try_from(order)))
+    {
+        return value.try_int_bytes(ty, order);
+    }
+    if let Ok(ty) = CharType::try_from(target) {
+        return value.try_char(ty);
+    }
+    if target == "perms" {
+        return value.try_perms();
+    }
+    if let Some(layout) = env.layout(target) {
+        return value.try_decode(layout);
+    }
+
+    let value = value.into_scalar()?;
+    let value = if let Ok(ty) = IntType::try_from(target) {
+        value.cast(ty)
+    } else if let Ok(ty) = FloatType::try_from(target) {
+        value.try_reinterpret_float(ty)?
+    } else if let Some(Ok(ty)) = target.strip_suffix("bits").map(FloatType::try_from) {
+        value.float_bits(ty)
+    } else if let Ok(order) = ByteOrder::try_from(target) {
+        value.try_read_bytes(order)?
+    } else if let Ok(ty) = IpType::try_from(target) {
+        value.try_ip(ty)?
+    } else if target == "auto-si" || target == "auto-iec" {
+        value.convert_to_auto(target == "auto-iec")
+    } else {
+        return Err(value::ValueErrorKind::UnknownType);
+    };
+    Ok(value.into())
 }
 
-fn evaluate(expr: &Expr) -> Result<Value, SyntaxError> {
+/// Concatenates the elements of a byte array literal, which are either bytes or byte arrays.
+fn concat_bytes(elements: Vec<Value>) -> Result<Value, value::ValueErrorKind> {
+    let mut bytes = Vec::new();
+    for element in elements {
+        match element {
+            Value::Bytes(element) => bytes.extend(element),
+            element => {
+                let scalar = element
+                    .into_scalar()
+                    .ok()
+                    .filter(|scalar| scalar.unit().is_none())
+                    .ok_or(value::ValueErrorKind::NotAByte)?;
+                let byte =
+                    u8::try_from(scalar.try_int()?).map_err(|_| value::ValueErrorKind::NotAByte)?;
+                bytes.push(byte);
+            }
+        }
+    }
+    Ok(Value::Bytes(bytes))
+}
+
+fn evaluate(expr: &Expr, env: &Environment) -> Result<Value, SyntaxError> {
     use expr::OperatorExpr as OE;
     use token::TokenKind as TK;
 
@@ -99,48 +469,107 @@ fn evaluate(expr: &Expr) -> Result<Value, SyntaxError> {
                 operator,
                 right,
             } => {
-                let left = evaluate(left)?;
-                let right = evaluate(right)?;
-                match operator.kind() {
-                    TK::Plus => Ok(left + right),
-                    TK::Minus => Ok(left - right),
-                    TK::Star => left
-                        .try_mul(right)
-                        .map_err(|e| ValueError::new(e, operator.clone()).into()),
-                    TK::Slash => left
-                        .try_div(right)
-                        .map_err(|e| ValueError::new(e, operator.clone()).into()),
+                let left = evaluate(left, env)?;
+                let right = evaluate(right, env)?;
+                let result = match operator.kind() {
+                    TK::Plus => left.try_add(right),
+                    TK::Minus => left.zip_scalar(right, |l, r| Ok(l - r)),
+                    TK::Star => left.zip_scalar(right, |l, r| l.try_mul(r)),
+                    TK::Slash => left.zip_scalar(right, |l, r| l.try_div(r)),
+                    TK::Ampersand => left.zip_scalar(right, |l, r| l.try_bitand(r)),
+                    TK::Pipe => left.zip_scalar(right, |l, r| l.try_bitor(r)),
+                    TK::Caret => left.zip_scalar(right, |l, r| l.try_bitxor(r)),
+                    TK::LessLess => left.zip_scalar(right, |l, r| l.try_shl(r)),
+                    TK::GreaterGreater => left.zip_scalar(right, |l, r| l.try_shr(r, false)),
+                    TK::GreaterGreaterGreater => left.zip_scalar(right, |l, r| l.try_shr(r, true)),
                     k => unreachable!("Invalid binary operator: {:?}", k),
-                }
+                };
+                result.map_err(|e| ValueError::new(e, operator.clone()).into())
             }
-            OE::TypeCast { expr: left, unit } => {
-                let left = evaluate(left)?;
-                let unit = match unit.kind() {
-                    TK::Unit(unit) => unit,
-                    u => unreachable!("Invalid unit: {:?}", u),
+            OE::TypeCast { expr: left, target } => {
+                let left = evaluate(left, env)?;
+                let result = match target.kind() {
+                    TK::Unit(unit) => left.map_scalar(|v| Ok(v.convert_to(unit))),
+                    TK::Identifier(name) => cast(left, &name, env),
+                    TK::QFormat(ty) => left.map_scalar(|v| v.try_fixed(ty)),
+                    u => unreachable!("Invalid cast target: {:?}", u),
                 };
-                Ok(left.convert_to(unit))
+                result.map_err(|e| ValueError::new(e, target.clone()).into())
             }
             OE::Unary { operator, right } => {
-                let right = evaluate(right)?;
-                match operator.kind() {
-                    TK::Minus => Ok(-right),
+                let right = evaluate(right, env)?;
+                let result = match operator.kind() {
+                    TK::Minus => right.map_scalar(|v| Ok(-v)),
+                    TK::Tilde => right.map_scalar(|v| v.try_not()),
                     k => unreachable!("Invalid unary operator: {:?}", k),
-                }
+                };
+                result.map_err(|e| ValueError::new(e, operator.clone()).into())
             }
         },
-        Expr::Grouping(expr) => evaluate(expr),
+        Expr::Grouping(expr) => evaluate(expr, env),
         Expr::Literal { kind, unit } => match kind.kind() {
             TK::Integer(num) => {
-                let value = num as f64;
                 let unit = unit.as_ref().map(|u| match u.kind() {
                     TK::Unit(unit) => unit,
                     k => unreachable!("Invalid unit: {:?}", k),
                 });
-                Ok(Value::new(value, unit))
+                Ok(Scalar::new(num, unit).into())
+            }
+            TK::Float(num) => {
+                let unit = unit.as_ref().map(|u| match u.kind() {
+                    TK::Unit(unit) => unit,
+                    k => unreachable!("Invalid unit: {:?}", k),
+                });
+                Ok(Scalar::new(num, unit).into())
+            }
+            TK::Bytes(bytes) => Ok(Value::Bytes(bytes)),
+            TK::Address(ty, bits) => {
+                Ok(Scalar::typed(Number::Int(bits as i128), None, Some(ty.into())).into())
             }
             k => unreachable!("Invalid literal: {:?}", k),
         },
+        Expr::Variable(name) => {
+            let name_str = identifier(name);
+            env.value(&name_str)
+                .map(|definition| definition.value().clone())
+                .or_else(|| env.layout(&name_str).cloned().map(Value::Layout))
+                .ok_or(ValueError::new(value::ValueErrorKind::UnknownVariable, name.clone()).into())
+        }
+        Expr::Bitfield { layout, fields } => build_bitfield(layout, fields, env),
+        Expr::Decode { expr, layout } => {
+            let value = evaluate(expr, env)?;
+            env.layout(&identifier(layout))
+                .ok_or(value::ValueErrorKind::UnknownType)
+                .and_then(|layout| value.try_decode(layout))
+                .map_err(|e| ValueError::new(e, layout.clone()).into())
+        }
+        Expr::Table {
+            var,
+            start,
+            dots,
+            inclusive,
+            end,
+            body,
+            ..
+        } => build_table(var, (start, dots, *inclusive, end), body, env),
+        Expr::Call { callee, args } => {
+            let name = match callee.kind() {
+                TK::Identifier(name) => name,
+                k => unreachable!("Invalid callee: {:?}", k),
+            };
+            let args = args
+                .iter()
+                .map(|expr| evaluate(expr, env))
+                .collect::<Result<Vec<_>, _>>()?;
+            builtins::call(&name, &args).map_err(|e| ValueError::new(e, callee.clone()).into())
+        }
+        Expr::Array { bracket, elements } => {
+            let elements = elements
+                .iter()
+                .map(|expr| evaluate(expr, env))
+                .collect::<Result<Vec<_>, _>>()?;
+            concat_bytes(elements).map_err(|e| ValueError::new(e, bracket.clone()).into())
+        }
     }
 }
 
@@ -152,30 +581,50 @@ mod tests {
 
     #[test]
     fn test_interpreter() {
-        let interpreter = Interpreter::new();
-        let value = interpreter.interpret("1 + 2").unwrap();
+        let mut interpreter = Interpreter::new();
+        let value = interpreter
+            .interpret("1 + 2")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
         assert_eq!(value.value(), 3.0);
         assert_eq!(value.unit(), None);
 
-        let value = interpreter.interpret("1 + 2 B").unwrap();
+        let value = interpreter
+            .interpret("1 + 2 B")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
         assert_eq!(value.value(), 3.0);
         assert_eq!(value.unit(), Some(FullUnit::byte()));
 
-        let value = interpreter.interpret("1 + 2 KiB").unwrap();
+        let value = interpreter
+            .interpret("1 + 2 KiB")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
         assert_eq!(value.value(), 3.0);
         assert_eq!(
             value.unit(),
             Some(FullUnit::new(UnitPrefix::Kibi, Unit::Byte))
         );
 
-        let value = interpreter.interpret("1 + 2 KiB + 3 MiB").unwrap();
+        let value = interpreter
+            .interpret("1 + 2 KiB + 3 MiB")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
         assert_eq!(value.value(), 3.0 + 3.0 * 1024.0);
         assert_eq!(
             value.unit(),
             Some(FullUnit::new(UnitPrefix::Kibi, Unit::Byte))
         );
 
-        let value = interpreter.interpret("1 + 2 KiB + 3 MiB + 4 GiB").unwrap();
+        let value = interpreter
+            .interpret("1 + 2 KiB + 3 MiB + 4 GiB")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
         assert_eq!(value.value(), 3.0 + 3.0 * 1024.0 + 4.0 * 1024.0 * 1024.0);
         assert_eq!(
             value.unit(),
@@ -184,6 +633,8 @@ mod tests {
 
         let value = interpreter
             .interpret("1 + 2 KiB + 3 MiB + 4 GiB + 5 TiB")
+            .unwrap()
+            .into_scalar()
             .unwrap();
         assert_eq!(
             value.value(),
@@ -194,4 +645,551 @@ mod tests {
             Some(FullUnit::new(UnitPrefix::Kibi, Unit::Byte))
         );
     }
+
+    #[test]
+    fn test_interpreter_shifts() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter
+            .interpret("1 << 4 | 1")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(17));
+
+        let value = interpreter
+            .interpret("-16 >> 2")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(-4));
+
+        let value = interpreter
+            .interpret("-16 as i8 >> 2")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(-4));
+        assert_eq!(value.ty(), Some(IntType::signed(8).into()));
+
+        let value = interpreter
+            .interpret("-16 as u8 >> 2")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x3c));
+
+        let value = interpreter
+            .interpret("-16 as i8 >>> 2")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x3c));
+
+        let value = interpreter
+            .interpret("-1 >>> 60")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0xf));
+
+        let value = interpreter
+            .interpret("0xff as u8 << 4")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0xf0));
+
+        let value = interpreter
+            .interpret("1 KiB << 2")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(4));
+        assert_eq!(
+            value.unit(),
+            Some(FullUnit::new(UnitPrefix::Kibi, Unit::Byte))
+        );
+
+        let value = interpreter
+            .interpret("~0 as u16 & 0xff0")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0xff0));
+        assert_eq!(value.ty(), Some(IntType::unsigned(16).into()));
+    }
+
+    #[test]
+    fn test_interpreter_sign_extension() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter
+            .interpret("sext(0xfe0 >> 5, 7)")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(-1));
+
+        let value = interpreter
+            .interpret("sext(0x800, 12) as u32")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0xffff_f800));
+
+        let value = interpreter
+            .interpret("zext(-2, 4) + trunc(0x1ff, 8)")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some((0x0e + 0xff) & 0xff));
+        assert_eq!(value.ty(), Some(IntType::unsigned(8).into()));
+    }
+
+    #[test]
+    fn test_interpreter_floats() {
+        use float_type::FloatType;
+
+        let mut interpreter = Interpreter::new();
+        let value = interpreter
+            .interpret("0x3F800000 as f32")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.value(), 1.0);
+        assert_eq!(value.ty(), Some(FloatType::F32.into()));
+        assert_eq!(value.bits(), Some(0x3f80_0000));
+
+        // Untyped numbers are bit patterns if they are integers and rounded if they are floats
+        let value = interpreter
+            .interpret("(4 / 2) as f32")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.bits(), Some(2));
+        assert_eq!(value.value(), f64::from(f32::from_bits(2)));
+        for (expr, expected) in [("2.0 as f32", 2.0), ("(1.5 * 2) as f32", 3.0)] {
+            let value = interpreter.interpret(expr).unwrap().into_scalar().unwrap();
+            assert_eq!(value.value(), expected, "{expr}");
+            assert_eq!(value.ty(), Some(FloatType::F32.into()));
+        }
+        let value = interpreter
+            .interpret("(3 / 2) as f32")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.value(), 1.5);
+        assert_eq!(value.bits(), Some(0x3fc0_0000));
+
+        let value = interpreter
+            .interpret("1.5 as f32bits")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x3fc0_0000));
+        assert_eq!(value.ty(), Some(IntType::unsigned(32).into()));
+
+        let value = interpreter
+            .interpret("0.1 as f16 as f64bits")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.bits(), Some(0.0999755859375f64.to_bits().into()));
+
+        let value = interpreter
+            .interpret("0x3c00 as f16 + 1")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.value(), 2.0);
+        assert_eq!(value.ty(), Some(FloatType::F16.into()));
+
+        let value = interpreter
+            .interpret("1 as bf16bits")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x3f80));
+
+        let value = interpreter
+            .interpret("1.5 KiB as B")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.value(), 1536.0);
+    }
+
+    #[test]
+    fn test_interpreter_fixed_point() {
+        use fixed_point::QFormat;
+        use value::Loss;
+
+        let mut interpreter = Interpreter::new();
+        let value = interpreter
+            .interpret("0x4000 as q15")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.value(), 0.5);
+        assert_eq!(value.ty(), QFormat::new(true, 1, 15).map(Into::into));
+
+        let value = interpreter
+            .interpret("0.75 as q1.15")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x6000));
+        assert_eq!(value.ty(), Some(IntType::signed(16).into()));
+
+        let value = interpreter
+            .interpret("0x0180 as q8.8 * 2")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.value(), 3.0);
+
+        let value = interpreter
+            .interpret("0xffff as uq16")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.value(), 1.0 - 2f64.powi(-16));
+
+        let value = interpreter
+            .interpret("0x8000 as q15 as q31")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(i32::MIN.into()));
+
+        let value = interpreter
+            .interpret("1.0 as q15")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x7fff));
+        assert_eq!(value.loss(), Some(Loss::Saturated(1.0)));
+
+        let value = interpreter
+            .interpret("-0.3 as uq8.8")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0));
+        assert_eq!(value.ty(), Some(IntType::unsigned(16).into()));
+    }
+
+    #[test]
+    fn test_interpreter_byte_order() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter
+            .interpret("0x12345678 as le")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x7856_3412));
+
+        let value = interpreter
+            .interpret("0x12345678 as be")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x1234_5678));
+
+        let value = interpreter
+            .interpret("0x0000803f as u32 as le as f32")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.value(), 1.0);
+
+        let value = interpreter
+            .interpret("0x1234 as u32 as le")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0x3412_0000));
+
+        let value = interpreter
+            .interpret("bswap16(0xabcd) | 1")
+            .unwrap()
+            .into_scalar()
+            .unwrap();
+        assert_eq!(value.int(), Some(0xcdab));
+
+        let err = interpreter.interpret("1 as u12 as le").unwrap_err();
+        assert_eq!(err.loc, 12..14);
+    }
+
+    #[test]
+    fn test_interpreter_bytes() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter.interpret("[0x12, 0x34] as u16le").unwrap();
+        assert_eq!(value.scalar().unwrap().int(), Some(0x3412));
+        assert_eq!(value.ty(), Some(IntType::unsigned(16).into()));
+
+        let value = interpreter.interpret("[0xff, 0xfe] as i16be").unwrap();
+        assert_eq!(value.scalar().unwrap().int(), Some(-2));
+
+        let value = interpreter.interpret("0x1234 as u32le").unwrap();
+        assert_eq!(value, Value::Bytes(vec![0x34, 0x12, 0, 0]));
+
+        let value = interpreter
+            .interpret("[1, b\"ab\", 0x1234 as u16be] + x\"ff\"")
+            .unwrap();
+        assert_eq!(value, Value::Bytes(vec![1, b'a', b'b', 0x12, 0x34, 0xff]));
+
+        let value = interpreter.interpret("unhex(hex(b\"hi\"))").unwrap();
+        assert_eq!(value, Value::Bytes(b"hi".to_vec()));
+
+        let value = interpreter.interpret("unbase64(b\"3q2+7w==\")").unwrap();
+        assert_eq!(value, Value::Bytes(vec![0xde, 0xad, 0xbe, 0xef]));
+
+        let value = interpreter.interpret("[]").unwrap();
+        assert_eq!(format!("{}", value), "x\"\"");
+    }
+
+    #[test]
+    fn test_interpreter_chars() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter.interpret("'A' + 1").unwrap();
+        assert_eq!(value.scalar().unwrap().int(), Some(0x42));
+
+        let value = interpreter.interpret("'a' - 'A' + '0' as char").unwrap();
+        assert_eq!(format!("{}", value), "'P': char");
+
+        let value = interpreter.interpret("utf8('\\u{1F600}')").unwrap();
+        assert_eq!(value.scalar().unwrap().bits(), Some(0xf09f_9880));
+
+        let value = interpreter.interpret("utf8(x\"e282ac\") as char").unwrap();
+        assert_eq!(value.scalar().unwrap().int(), Some(0x20ac));
+
+        let value = interpreter.interpret("0x263a as utf16 as char").unwrap();
+        assert_eq!(format!("{}", value), "'☺': char");
+
+        let err = interpreter.interpret("0x110000 as char").unwrap_err();
+        assert_eq!(err.loc, 12..16);
+    }
+
+    #[test]
+    fn test_interpreter_addresses() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter.interpret("192.168.1.1 + 255").unwrap();
+        assert_eq!(format!("{}", value), "192.168.2.0: ipv4");
+
+        let value = interpreter.interpret("0xc0a80101 as ipv4 as u32").unwrap();
+        assert_eq!(value.scalar().unwrap().int(), Some(0xc0a8_0101));
+
+        let value = interpreter.interpret("network(192.168.1.77, 20)").unwrap();
+        assert_eq!(format!("{}", value), "192.168.0.0/20: ipv4");
+
+        let value = interpreter.interpret("192.168.1.77 & netmask(20)").unwrap();
+        assert_eq!(format!("{}", value), "192.168.0.0: ipv4");
+
+        let value = interpreter
+            .interpret("broadcast(network(10.1.2.3, 8))")
+            .unwrap();
+        assert_eq!(format!("{}", value), "10.255.255.255: ipv4");
+
+        let value = interpreter.interpret("netmask(2001:db8::/32)").unwrap();
+        assert_eq!(format!("{}", value), "ffff:ffff::: ipv6");
+
+        let value = interpreter.interpret("1 as ipv6").unwrap();
+        assert_eq!(format!("{}", value), "::1: ipv6");
+
+        let err = interpreter.interpret("0x100000000 as ipv4").unwrap_err();
+        assert_eq!(err.loc, 15..19);
+    }
+
+    #[test]
+    fn test_interpreter_perms() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter.interpret("0o4755 as perms").unwrap();
+        assert_eq!(format!("{}", value), "rwsr-xr-x: perms");
+
+        let value = interpreter.interpret("\"rw-r--r--\" as perms").unwrap();
+        assert_eq!(value.scalar().unwrap().int(), Some(0o644));
+
+        let value = interpreter
+            .interpret("\"drwxrwxrwx\" as perms & ~0o022 | 0o1000")
+            .unwrap();
+        assert_eq!(format!("{}", value), "rwxr-xr-t: perms");
+
+        let err = interpreter.interpret("0o10000 as perms").unwrap_err();
+        assert_eq!(err.loc, 11..16);
+
+        let err = interpreter.interpret("\"rwx\" as perms").unwrap_err();
+        assert_eq!(err.loc, 9..14);
+    }
+
+    #[test]
+    fn test_interpreter_preview() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter.preview("layout CTRL { EN: 0 }").unwrap();
+        assert!(matches!(value, Value::Layout(_)));
+        assert!(interpreter.preview("1 as CTRL").is_err());
+
+        interpreter.interpret("layout CTRL { EN: 0 }").unwrap();
+        assert!(interpreter.preview("1 as CTRL").is_ok());
+
+        interpreter.preview("let MASK = 1").unwrap();
+        assert!(interpreter.definition("A").is_none());
+    }
+
+    #[test]
+    fn test_interpreter_let() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter.interpret("let MASK = 0xf0 # mask").unwrap();
+        assert_eq!(format!("{}", value), "240");
+        interpreter.interpret("let LOW = MASK >> 4").unwrap();
+        interpreter.interpret("let MASK = 1").unwrap();
+
+        let definitions = interpreter
+            .definitions()
+            .into_iter()
+            .map(|(name, def)| (name, def.expr(), def.value().to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            definitions,
+            [("LOW", "MASK >> 4", "15".into()), ("MASK", "1", "1".into())]
+        );
+    }
+
+    #[test]
+    fn test_interpreter_check() {
+        assert_eq!(Interpreter::check("1 + foo"), Ok(()));
+        assert!(!Interpreter::check("1 +").unwrap_err().is_evaluation_error());
+
+        let err = Interpreter::new().interpret("1 + foo").unwrap_err();
+        assert!(err.is_evaluation_error());
+    }
+
+    #[test]
+    fn test_interpreter_layouts() {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter
+            .interpret("layout CTRL { EN: 0, MODE: 2..1 { OFF: 0, FAST: 2 }, DIV: 15..8 }")
+            .unwrap();
+        assert_eq!(
+            format!("{}", value),
+            "layout CTRL { EN: 0, MODE: 2..1 { OFF: 0, FAST: 2 }, DIV: 15..8 }"
+        );
+
+        let value = interpreter.interpret("0x1A05 as CTRL").unwrap();
+        assert_eq!(format!("{}", value), "CTRL { EN: 1, MODE: FAST, DIV: 26 }");
+
+        let value = interpreter
+            .interpret("CTRL { EN: 1, DIV: 0x1A, MODE: FAST }")
+            .unwrap();
+        assert_eq!(value.scalar().unwrap().int(), Some(0x1a05));
+
+        let value = interpreter.interpret("CTRL { MODE: 1 } | 1").unwrap();
+        assert_eq!(value.scalar().unwrap().int(), Some(3));
+
+        let err = interpreter.interpret("CTRL { SPEED: 1 }").unwrap_err();
+        assert_eq!(err.loc, 7..12);
+
+        let err = interpreter.interpret("CTRL { MODE: 4 }").unwrap_err();
+        assert_eq!(err.loc, 7..11);
+
+        let err = interpreter.interpret("0x10000 as CTRL").unwrap_err();
+        assert_eq!(err.loc, 11..15);
+
+        let err = interpreter.interpret("REG { EN: 1 }").unwrap_err();
+        assert_eq!(err.loc, 0..3);
+
+        let err = interpreter.interpret("CTRL { EN: 1, EN: 0 }").unwrap_err();
+        assert_eq!(err.loc, 14..16);
+        assert_eq!(
+            std::error::Error::source(&err).unwrap().to_string(),
+            "Duplicate field"
+        );
+
+        let err = interpreter
+            .interpret("layout R { LO: 3..0, HI: 7..3 }")
+            .unwrap_err();
+        assert_eq!(err.loc, 21..23);
+        assert_eq!(
+            std::error::Error::source(&err).unwrap().to_string(),
+            "Field overlaps another one at bit 3"
+        );
+        assert!(interpreter.interpret("R").is_err());
+
+        let err = interpreter.interpret("CTRL { EN: 2 }").unwrap_err();
+        assert_eq!(
+            std::error::Error::source(&err).unwrap().to_string(),
+            "Value does not fit into 1 bit"
+        );
+
+        let err = interpreter.interpret("FAST").unwrap_err();
+        assert_eq!(err.loc, 0..4);
+
+        let value = interpreter.interpret("0x1A05.CTRL").unwrap();
+        assert_eq!(format!("{}", value), "CTRL { EN: 1, MODE: FAST, DIV: 26 }");
+
+        let value = interpreter.interpret("decode(CTRL, 0x1A05)").unwrap();
+        assert_eq!(format!("{}", value), "CTRL { EN: 1, MODE: FAST, DIV: 26 }");
+
+        let err = interpreter.interpret("0x1A05.REG").unwrap_err();
+        assert_eq!(err.loc, 7..10);
+
+        let err = interpreter.interpret("decode(1, 2)").unwrap_err();
+        assert_eq!(err.loc, 0..6);
+    }
+
+    #[test]
+    fn test_interpreter_errors() {
+        use value::ValueErrorKind as VEK;
+
+        let mut interpreter = Interpreter::new();
+        let err = interpreter.interpret("1 << 200").unwrap_err();
+        assert_eq!(err.loc, 2..4);
+        assert_eq!(
+            err.kind,
+            ValueError::new(VEK::Overflow, token!(LessLess, 2..4)).into()
+        );
+
+        let err = interpreter.interpret("1 KiB & 1").unwrap_err();
+        assert_eq!(err.loc, 6..7);
+
+        let err = interpreter.interpret("1 >> -1").unwrap_err();
+        assert_eq!(err.loc, 2..4);
+
+        let err = interpreter.interpret("1 as f7").unwrap_err();
+        assert_eq!(err.loc, 5..7);
+
+        let err = interpreter.interpret("foo(1)").unwrap_err();
+        assert_eq!(err.loc, 0..3);
+
+        let err = interpreter.interpret("1 as u8 / 0").unwrap_err();
+        assert_eq!(err.loc, 8..9);
+
+        let err = interpreter.interpret("0x10000 as f16").unwrap_err();
+        assert_eq!(err.loc, 11..14);
+
+        let err = interpreter.interpret("1 as f32 << 1").unwrap_err();
+        assert_eq!(err.loc, 9..11);
+
+        let err = interpreter.interpret("0x10000 as q15").unwrap_err();
+        assert_eq!(err.loc, 11..14);
+
+        let err = interpreter.interpret("1 as q0.15").unwrap_err();
+        assert_eq!(err.loc, 5..6);
+
+        let err = interpreter.interpret("[1, 256]").unwrap_err();
+        assert_eq!(err.loc, 0..1);
+
+        let err = interpreter.interpret("[1] * 2").unwrap_err();
+        assert_eq!(err.loc, 4..5);
+
+        let err = interpreter.interpret("[1] as u16le").unwrap_err();
+        assert_eq!(err.loc, 7..12);
+
+        let err = interpreter.interpret("-b\"a\"").unwrap_err();
+        assert_eq!(err.loc, 0..1);
+
+        let err = interpreter.interpret("1 as u8 / 0").unwrap_err();
+        assert_eq!(err.to_string(), "Evaluation error");
+        let err = interpreter.interpret("1 +").unwrap_err();
+        assert_eq!(err.to_string(), "Syntax error");
+    }
 }
@@ -48,3 +48,119 @@ pub(super) fn from_slice_radix<const RADIX: u32>(mut digits: &[u8]) -> Result<u6
     }
     Ok(result)
 }
+
+/// A numeric value as computed by the interpreter.
+///
+/// Integers are kept exact for as long as possible, results that can't be represented as an integer
+/// (e.g. fractional unit conversions) fall back to floating point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+impl Number {
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(v) => v as f64,
+            Number::Float(v) => v,
+        }
+    }
+
+    /// Returns the integer value, if the number is an integer or a float without a fractional part.
+    pub fn as_int(self) -> Option<i128> {
+        match self {
+            Number::Int(v) => Some(v),
+            Number::Float(v) if v.fract() == 0.0 && v.abs() < i128::MAX as f64 => Some(v as i128),
+            Number::Float(_) => None,
+        }
+    }
+
+    /// Truncates a float towards zero, saturating at the bounds of `i128`.
+    pub fn trunc(self) -> i128 {
+        match self {
+            Number::Int(v) => v,
+            Number::Float(v) => v as i128,
+        }
+    }
+}
+
+impl From<i128> for Number {
+    fn from(value: i128) -> Self {
+        Number::Int(value)
+    }
+}
+
+impl From<u64> for Number {
+    fn from(value: u64) -> Self {
+        Number::Int(value.into())
+    }
+}
+
+impl From<f64> for Number {
+    fn from(value: f64) -> Self {
+        Number::Float(value)
+    }
+}
+
+macro_rules! impl_op_for_number {
+    ($trait:ident, $op:ident, $checked:ident) => {
+        impl std::ops::$trait for Number {
+            type Output = Self;
+
+            fn $op(self, rhs: Self) -> Self::Output {
+                match (self, rhs) {
+                    (Number::Int(l), Number::Int(r)) => l
+                        .$checked(r)
+                        .map(Number::Int)
+                        .unwrap_or_else(|| Number::Float((l as f64).$op(r as f64))),
+                    (l, r) => Number::Float(l.as_f64().$op(r.as_f64())),
+                }
+            }
+        }
+    };
+}
+
+impl_op_for_number!(Add, add, checked_add);
+impl_op_for_number!(Sub, sub, checked_sub);
+impl_op_for_number!(Mul, mul, checked_mul);
+
+impl std::ops::Div for Number {
+    type Output = Self;
+
+    /// Divides two numbers, staying an integer only if the division is exact.
+    fn div(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (Number::Int(l), Number::Int(r)) if r != 0 && l.checked_rem(r) == Some(0) => {
+                Number::Int(l / r)
+            }
+            (l, r) => Number::Float(l.as_f64() / r.as_f64()),
+        }
+    }
+}
+
+impl std::ops::Neg for Number {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Number::Int(v) => v
+                .checked_neg()
+                .map(Number::Int)
+                .unwrap_or(Number::Float(-(v as f64))),
+            Number::Float(v) => Number::Float(-v),
+        }
+    }
+}
+
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Int(v) => write!(f, "{}", v),
+            Number::Float(v) => match f.precision() {
+                Some(precision) => write!(f, "{:.*}", precision, v),
+                None => write!(f, "{}", v),
+            },
+        }
+    }
+}
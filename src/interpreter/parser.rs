@@ -2,30 +2,58 @@ use miette::Diagnostic;
 use thiserror::Error;
 
 use super::{
-    expr::{Expr, OperatorExpr as OE},
+    expr::{Expr, FieldDecl, LayoutDecl, LetDecl, OperatorExpr as OE, Stmt},
     lexer::{LexError, Lexer},
     token::{Token, TokenKind},
     SyntaxErrorKind,
 };
 
 // Grammar:
-// expression   -> term EOF ;
+// statement    -> ( layout | let | bitor ) EOF ;
+// let          -> "let" IDENTIFIER "=" bitor ;
+// layout       -> "layout" IDENTIFIER "{" ( field ( "," field )* ","? )? "}" ;
+// field        -> IDENTIFIER ":" INTEGER ( ".." INTEGER )? ( "{" ( variant ( "," variant )* ","? )? "}" )? ;
+// variant      -> IDENTIFIER ":" INTEGER ;
+// bitor        -> bitxor ( "|" bitxor )* ;
+// bitxor       -> bitand ( "^" bitand )* ;
+// bitand       -> shift ( "&" shift )* ;
+// shift        -> term ( ( "<<" | ">>" | ">>>" ) term )* ;
 // term         -> factor ( ( "-" | "+" ) factor )* ;
-// factor       -> unitcast ( ( "/" | "*" ) unitcast )* ;
-// unitcast     -> unary ( "as" UNIT )? ;
-// unary        -> "-" unary | primary ;
-// primary      -> NUMBER ( UNIT )? | "(" expression ")" ;
+// factor       -> typecast ( ( "/" | "*" ) typecast )* ;
+// typecast     -> unary ( "as" ( UNIT | IDENTIFIER | QFORMAT ) )* ;
+// unary        -> ( "-" | "~" ) unary | decode ;
+// decode       -> primary ( "." IDENTIFIER )* ;
+// primary      -> NUMBER ( UNIT )? | BYTES | ADDRESS | array | table | call | bitfield | IDENTIFIER
+//               | "(" bitor ")" ;
+// array        -> "[" ( bitor ( "," bitor )* )? "]" ;
+// table        -> "table" "(" IDENTIFIER "," range "," bitor ")" ;
+// range        -> bitor ".." "="? bitor ;
+// call         -> IDENTIFIER "(" ( bitor ( "," bitor )* )? ")" ;
+// bitfield     -> IDENTIFIER "{" ( IDENTIFIER ":" bitor ( "," IDENTIFIER ":" bitor )* ","? )? "}" ;
 //
-// NUMBER   -> BINARY | OCTAL | DECIMAL | HEX ;
+// NUMBER   -> INTEGER | FLOAT ;
+// INTEGER  -> BINARY | OCTAL | DECIMAL | HEX ;
 // BINARY   -> "0b" [01]+ ;
 // OCTAL    -> "0o" [0-7]+ ;
 // DECIMAL  -> [0-9]+ ;
 // HEX      -> "0x" [0-9a-fA-F]+ ;
+// FLOAT    -> [0-9]+ "." [0-9]+ ;
+//
+// BYTES    -> "b"? "\"" ( [^"\\] | ESCAPE )* "\"" | "x\"" [0-9a-fA-F \t\n]* "\"" ;
+// ESCAPE   -> "\\" ( "n" | "r" | "t" | "0" | "\\" | "\"" | "x" [0-9a-fA-F]{2} ) ;
+//
+// ADDRESS  -> ( IPV4 | IPV6 ) ( "/" [0-9]+ )? ;
+// IPV4     -> [0-9]+ "." [0-9]+ "." [0-9]+ "." [0-9]+ ;
+// IPV6     -> [0-9a-fA-F:.]* ":" [0-9a-fA-F:.]* ;
 //
 // UNIT     -> UNITPREFIX? "b" | "B" ;
 // UNITPREFIX -> DECUNITPREFIX | BINUNITPREFIX ;
 // BINUNITPREFIX -> DECUNITPREFIX "i" ;
 // DECUNITPREFIX -> "k" | "m" | "g" | "t" | "p" | "e" | "K" | "M" | "G" | "T" | "P" | "E" ;
+//
+// IDENTIFIER -> [a-zA-Z_] [a-zA-Z0-9_]* ;
+// Words that are also a UNIT, like `b` or `kB`, are IDENTIFIERs wherever no UNIT is expected.
+// QFORMAT  -> "u"? "q" [0-9]+ ( "." [0-9]+ )? ;
 
 #[derive(Debug, Clone, PartialEq, Error, Diagnostic)]
 pub enum ParseErrorKind {
@@ -35,8 +63,8 @@ pub enum ParseErrorKind {
     ExpectedExpression,
     #[error("Expected end of expression")]
     ExpectedEof,
-    #[error("Expected unit")]
-    ExpectedUnit,
+    #[error("Expected unit or type")]
+    ExpectedCastTarget,
 }
 
 #[derive(Debug, Clone, PartialEq, Error, Diagnostic)]
@@ -68,7 +96,10 @@ macro_rules! error {
 }
 
 pub struct Parser<'a> {
+    source: &'a str,
     iter: std::iter::Peekable<Lexer<'a>>,
+    /// The end of the last consumed token.
+    end: usize,
 }
 
 macro_rules! bump_if {
@@ -83,22 +114,137 @@ macro_rules! bump_if {
 impl<'a> Parser<'a> {
     pub fn new(lexer: Lexer<'a>) -> Self {
         Self {
+            source: lexer.source(),
             iter: lexer.peekable(),
+            end: 0,
         }
     }
 
-    pub fn parse(&mut self) -> Result<Expr, SyntaxErrorKind> {
-        let expr = self.expression()?;
+    pub fn parse(&mut self) -> Result<Stmt, SyntaxErrorKind> {
+        let stmt = match self.peek()?.map(|t| t.kind()) {
+            Some(TokenKind::Layout) => Stmt::Layout(self.layout()?),
+            Some(TokenKind::Let) => Stmt::Let(self.let_decl()?),
+            _ => Stmt::Expr(self.expression()?),
+        };
 
         if bump_if!(self, Eof).is_some() {
-            return Ok(expr);
+            return Ok(stmt);
         }
 
         Err(error!(ExpectedEof, self.bump()).into())
     }
 
+    fn layout(&mut self) -> Result<LayoutDecl, SyntaxErrorKind> {
+        self.bump();
+        let name = self.consume_identifier()?;
+        self.consume_l_brace()?;
+        let fields = self.braced_list(Self::field)?;
+
+        Ok(LayoutDecl { name, fields })
+    }
+
+    fn field(&mut self) -> Result<FieldDecl, SyntaxErrorKind> {
+        let name = self.consume_identifier()?;
+        self.consume_colon()?;
+        let first = self.consume_integer()?;
+        let last = match bump_if!(self, DotDot) {
+            Some(_) => Some(self.consume_integer()?),
+            None => None,
+        };
+
+        let mut variants = Vec::new();
+        if bump_if!(self, LeftBrace).is_some() {
+            variants = self.braced_list(|parser| {
+                let name = parser.consume_identifier()?;
+                parser.consume_colon()?;
+                Ok((name, parser.consume_integer()?))
+            })?;
+        }
+
+        Ok(FieldDecl {
+            name,
+            first,
+            last,
+            variants,
+        })
+    }
+
+    fn let_decl(&mut self) -> Result<LetDecl, SyntaxErrorKind> {
+        self.bump();
+        let name = self.consume_identifier()?;
+        bump_if!(self, Equal).ok_or(error!(UnexpectedToken("="), self.peek()?.unwrap()))?;
+
+        let start = self.peek()?.map_or(self.end, |token| token.loc().start);
+        let expr = self.expression()?;
+        Ok(LetDecl {
+            name,
+            expr,
+            loc: start..self.end,
+        })
+    }
+
     fn expression(&mut self) -> Result<Expr, SyntaxErrorKind> {
-        self.term()
+        self.bit_or()
+    }
+
+    fn bit_or(&mut self) -> Result<Expr, SyntaxErrorKind> {
+        let mut expr = self.bit_xor()?;
+
+        while let Some(operator) = bump_if!(self, Pipe) {
+            let right = Box::new(self.bit_xor()?);
+            expr = Expr::Operator(OE::ArithmeticOrLogical {
+                left: Box::new(expr),
+                operator,
+                right,
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, SyntaxErrorKind> {
+        let mut expr = self.bit_and()?;
+
+        while let Some(operator) = bump_if!(self, Caret) {
+            let right = Box::new(self.bit_and()?);
+            expr = Expr::Operator(OE::ArithmeticOrLogical {
+                left: Box::new(expr),
+                operator,
+                right,
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, SyntaxErrorKind> {
+        let mut expr = self.shift()?;
+
+        while let Some(operator) = bump_if!(self, Ampersand) {
+            let right = Box::new(self.shift()?);
+            expr = Expr::Operator(OE::ArithmeticOrLogical {
+                left: Box::new(expr),
+                operator,
+                right,
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, SyntaxErrorKind> {
+        let mut expr = self.term()?;
+
+        while let Some(operator) = bump_if!(self, LessLess, GreaterGreater, GreaterGreaterGreater) {
+            let right = Box::new(self.term()?);
+            expr = Expr::Operator(OE::ArithmeticOrLogical {
+                left: Box::new(expr),
+                operator,
+                right,
+            });
+        }
+
+        Ok(expr)
     }
 
     fn term(&mut self) -> Result<Expr, SyntaxErrorKind> {
@@ -134,12 +280,12 @@ impl<'a> Parser<'a> {
     fn type_cast(&mut self) -> Result<Expr, SyntaxErrorKind> {
         let mut expr = self.unary()?;
 
-        if bump_if!(self, As).is_some() {
-            let unit = self.consume_unit()?;
+        while bump_if!(self, As).is_some() {
+            let target = self.consume_cast_target()?;
 
             expr = Expr::Operator(OE::TypeCast {
                 expr: Box::new(expr),
-                unit,
+                target,
             });
         }
 
@@ -147,21 +293,53 @@ impl<'a> Parser<'a> {
     }
 
     fn unary(&mut self) -> Result<Expr, SyntaxErrorKind> {
-        if let Some(operator) = bump_if!(self, Minus) {
+        if let Some(operator) = bump_if!(self, Minus, Tilde) {
             let right = Box::new(self.unary()?);
             return Ok(Expr::Operator(OE::Unary { operator, right }));
         }
 
-        self.primary()
+        self.decode()
+    }
+
+    fn decode(&mut self) -> Result<Expr, SyntaxErrorKind> {
+        let mut expr = self.primary()?;
+        while bump_if!(self, Dot).is_some() {
+            let layout = self.consume_identifier()?;
+            expr = Expr::Decode {
+                expr: Box::new(expr),
+                layout,
+            };
+        }
+
+        Ok(expr)
     }
 
     fn primary(&mut self) -> Result<Expr, SyntaxErrorKind> {
         match self.peek()?.map(|t| t.kind()) {
-            Some(TokenKind::Integer(_)) => {
+            Some(TokenKind::Integer(_) | TokenKind::Float(_)) => {
                 let kind = self.bump();
                 let unit = bump_if!(self, Unit(_));
                 return Ok(Expr::Literal { kind, unit });
             }
+            Some(TokenKind::Bytes(_) | TokenKind::Address(..)) => {
+                let kind = self.bump();
+                return Ok(Expr::Literal { kind, unit: None });
+            }
+            Some(TokenKind::LeftBracket) => return self.array(),
+            // A unit can't start an expression, so unit-shaped words are names here
+            Some(TokenKind::Identifier(_) | TokenKind::Unit(_)) => {
+                let name = self.consume_identifier()?;
+                return match self.peek()?.map(|t| t.kind()) {
+                    Some(TokenKind::LeftParen)
+                        if name.kind() == TokenKind::Identifier("table".into()) =>
+                    {
+                        self.table(name)
+                    }
+                    Some(TokenKind::LeftParen) => self.call(name),
+                    Some(TokenKind::LeftBrace) => self.bitfield(name),
+                    _ => Ok(Expr::Variable(name)),
+                };
+            }
             Some(TokenKind::LeftParen) => {
                 self.bump();
                 let expression = Box::new(self.expression()?);
@@ -174,16 +352,136 @@ impl<'a> Parser<'a> {
         Err(error!(ExpectedExpression, self.bump()).into())
     }
 
+    fn call(&mut self, callee: Token) -> Result<Expr, SyntaxErrorKind> {
+        self.bump();
+
+        let mut args = Vec::new();
+        if bump_if!(self, RightParen).is_none() {
+            loop {
+                args.push(self.expression()?);
+                if bump_if!(self, Comma).is_none() {
+                    break;
+                }
+            }
+            self.consume_r_paren()?;
+        }
+
+        Ok(Expr::Call { callee, args })
+    }
+
+    fn table(&mut self, callee: Token) -> Result<Expr, SyntaxErrorKind> {
+        self.bump();
+        let var = self.consume_identifier()?;
+        self.consume_comma()?;
+
+        let start = Box::new(self.expression()?);
+        let dots =
+            bump_if!(self, DotDot).ok_or(error!(UnexpectedToken(".."), self.peek()?.unwrap()))?;
+        let inclusive = bump_if!(self, Equal).is_some();
+        let end = Box::new(self.expression()?);
+        self.consume_comma()?;
+
+        let body = Box::new(self.expression()?);
+        self.consume_r_paren()?;
+        Ok(Expr::Table {
+            callee,
+            var,
+            start,
+            dots,
+            inclusive,
+            end,
+            body,
+        })
+    }
+
+    fn array(&mut self) -> Result<Expr, SyntaxErrorKind> {
+        let bracket = self.bump();
+
+        let mut elements = Vec::new();
+        if bump_if!(self, RightBracket).is_none() {
+            loop {
+                elements.push(self.expression()?);
+                if bump_if!(self, Comma).is_none() {
+                    break;
+                }
+            }
+            bump_if!(self, RightBracket)
+                .ok_or(error!(UnexpectedToken("]"), self.peek()?.unwrap()))?;
+        }
+
+        Ok(Expr::Array { bracket, elements })
+    }
+
+    fn bitfield(&mut self, layout: Token) -> Result<Expr, SyntaxErrorKind> {
+        self.bump();
+        let fields = self.braced_list(|parser| {
+            let name = parser.consume_identifier()?;
+            parser.consume_colon()?;
+            Ok((name, parser.expression()?))
+        })?;
+
+        Ok(Expr::Bitfield { layout, fields })
+    }
+
+    /// Parses a comma separated list of `item`s up to and including the closing `}`, allowing a trailing
+    /// comma.
+    fn braced_list<T>(
+        &mut self,
+        item: impl Fn(&mut Self) -> Result<T, SyntaxErrorKind>,
+    ) -> Result<Vec<T>, SyntaxErrorKind> {
+        let mut items = Vec::new();
+        while bump_if!(self, RightBrace).is_none() {
+            items.push(item(self)?);
+            if bump_if!(self, Comma).is_none() {
+                bump_if!(self, RightBrace)
+                    .ok_or(error!(UnexpectedToken("}"), self.peek()?.unwrap()))?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
     fn bump(&mut self) -> Token {
-        self.iter.next().unwrap().unwrap().clone()
+        let token = self.iter.next().unwrap().unwrap().clone();
+        self.end = token.loc().end;
+        token
     }
 
     fn peek(&mut self) -> Result<Option<Token>, LexError> {
         self.iter.peek().map(ToOwned::to_owned).transpose()
     }
 
-    fn consume_unit(&mut self) -> Result<Token, SyntaxErrorKind> {
-        bump_if!(self, Unit(_)).ok_or(error!(ExpectedUnit, self.peek()?.unwrap()).into())
+    fn consume_cast_target(&mut self) -> Result<Token, SyntaxErrorKind> {
+        bump_if!(self, Unit(_), Identifier(_), QFormat(_))
+            .ok_or(error!(ExpectedCastTarget, self.peek()?.unwrap()).into())
+    }
+
+    /// Consumes an identifier, including words like `b` or `kB` that are lexed as units, since they are
+    /// valid names wherever the grammar expects one.
+    fn consume_identifier(&mut self) -> Result<Token, SyntaxErrorKind> {
+        if let Some(unit) = bump_if!(self, Unit(_)) {
+            let name = self.source[unit.loc()].to_string();
+            return Ok(Token::new(TokenKind::Identifier(name), unit.loc()));
+        }
+        bump_if!(self, Identifier(_))
+            .ok_or(error!(UnexpectedToken("identifier"), self.peek()?.unwrap()).into())
+    }
+
+    fn consume_integer(&mut self) -> Result<Token, SyntaxErrorKind> {
+        bump_if!(self, Integer(_))
+            .ok_or(error!(UnexpectedToken("integer"), self.peek()?.unwrap()).into())
+    }
+
+    fn consume_colon(&mut self) -> Result<Token, SyntaxErrorKind> {
+        bump_if!(self, Colon).ok_or(error!(UnexpectedToken(":"), self.peek()?.unwrap()).into())
+    }
+
+    fn consume_comma(&mut self) -> Result<Token, SyntaxErrorKind> {
+        bump_if!(self, Comma).ok_or(error!(UnexpectedToken(","), self.peek()?.unwrap()).into())
+    }
+
+    fn consume_l_brace(&mut self) -> Result<Token, SyntaxErrorKind> {
+        bump_if!(self, LeftBrace).ok_or(error!(UnexpectedToken("{"), self.peek()?.unwrap()).into())
     }
 
     fn consume_r_paren(&mut self) -> Result<Token, SyntaxErrorKind> {
@@ -202,7 +500,12 @@ mod tests {
 
     macro_rules! parse {
         ($input:expr) => {
-            Parser::new(Lexer::new($input)).parse()
+            Parser::new(Lexer::new($input))
+                .parse()
+                .map(|stmt| match stmt {
+                    Stmt::Expr(expr) => expr,
+                    stmt => panic!("Expected an expression, got {stmt}"),
+                })
         };
     }
 
@@ -340,7 +643,7 @@ mod tests {
                     kind: token!(Integer(1234), 0..4),
                     unit: None
                 }),
-                unit: token!(Unit(FullUnit(UnitPrefix::Kibi, Unit::Byte)), 8..11),
+                target: token!(Unit(FullUnit(UnitPrefix::Kibi, Unit::Byte)), 8..11),
             })
         );
     }
@@ -398,8 +701,148 @@ mod tests {
                         }),
                     }
                 )))),
-                unit: token!(Unit(FullUnit(UnitPrefix::Mega, Unit::Byte)), 29..31),
+                target: token!(Unit(FullUnit(UnitPrefix::Mega, Unit::Byte)), 29..31),
             })
         );
     }
+
+    #[test]
+    fn test_parser_shift_precedence() {
+        let expr = parse!("1 + 2 << 3 & 4").unwrap();
+        assert_eq!(format!("{}", expr), "(& (<< (+ 1 2) 3) 4)");
+
+        let expr = parse!("1 | 2 ^ 3 >>> ~4").unwrap();
+        assert_eq!(format!("{}", expr), "(| 1 (^ 2 (>>> 3 (~ 4))))");
+    }
+
+    #[test]
+    fn test_parser_call_expr() {
+        let expr = parse!("sext(1, 2 + 3) as u32").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Operator(OE::TypeCast {
+                expr: Box::new(Expr::Call {
+                    callee: token!(Identifier("sext".into()), 0..4),
+                    args: vec![
+                        Expr::Literal {
+                            kind: token!(Integer(1), 5..6),
+                            unit: None
+                        },
+                        Expr::Operator(OE::ArithmeticOrLogical {
+                            left: Box::new(Expr::Literal {
+                                kind: token!(Integer(2), 8..9),
+                                unit: None
+                            }),
+                            operator: token!(Plus, 10..11),
+                            right: Box::new(Expr::Literal {
+                                kind: token!(Integer(3), 12..13),
+                                unit: None
+                            }),
+                        }),
+                    ],
+                }),
+                target: token!(Identifier("u32".into()), 18..21),
+            })
+        );
+
+        let expr = parse!("f()").unwrap();
+        assert_eq!(format!("{}", expr), "(f)");
+
+        let err = parse!("f(1,)").unwrap_err();
+        assert_eq!(
+            err,
+            error!(ExpectedExpression, token!(RightParen, 4..5)).into()
+        );
+
+        let err = parse!("f 1").unwrap_err();
+        assert_eq!(err, error!(ExpectedEof, token!(Integer(1), 2..3)).into());
+    }
+
+    #[test]
+    fn test_parser_layout() {
+        let stmt = Parser::new(Lexer::new(
+            "layout CTRL { EN: 0, MODE: 2..1 { OFF: 0, FAST: 2 }, }",
+        ))
+        .parse()
+        .unwrap();
+        assert_eq!(
+            format!("{}", stmt),
+            "(layout CTRL (EN 0) (MODE 2..1 (OFF 0) (FAST 2)))"
+        );
+
+        let expr = parse!("CTRL { EN: 1, MODE: FAST } | 1").unwrap();
+        assert_eq!(
+            format!("{}", expr),
+            "(| (fields CTRL (EN 1) (MODE FAST)) 1)"
+        );
+
+        let expr = parse!("-0x83.RCC_CR as u32").unwrap();
+        assert_eq!(format!("{}", expr), "(as (- (. 131 RCC_CR)) u32)");
+
+        let err = Parser::new(Lexer::new("layout CTRL { EN 0 }"))
+            .parse()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            error!(UnexpectedToken(":"), token!(Integer(0), 17..18)).into()
+        );
+    }
+
+    #[test]
+    fn test_parser_let() {
+        let stmt = Parser::new(Lexer::new("let MASK = (1 << 4) - 1 # low bits"))
+            .parse()
+            .unwrap();
+        assert_eq!(format!("{}", stmt), "(let MASK (- (group (<< 1 4)) 1))");
+        let Stmt::Let(decl) = stmt else {
+            panic!("Expected a let statement");
+        };
+        assert_eq!(decl.loc, 11..23);
+
+        let stmt = Parser::new(Lexer::new("let b = 1 b + b")).parse().unwrap();
+        assert_eq!(format!("{}", stmt), "(let b (+ 1b b))");
+        let stmt = Parser::new(Lexer::new("layout R { b: 0, KB: 1 { B: 1 } }"))
+            .parse()
+            .unwrap();
+        assert_eq!(format!("{}", stmt), "(layout R (b 0) (KB 1 (B 1)))");
+        let expr = parse!("R { b: kB, KB: B }.R.b").unwrap();
+        assert_eq!(format!("{}", expr), "(. (. (fields R (b kB) (KB B)) R) b)");
+
+        let err = Parser::new(Lexer::new("let MASK 1")).parse().unwrap_err();
+        assert_eq!(
+            err,
+            error!(UnexpectedToken("="), token!(Integer(1), 9..10)).into()
+        );
+    }
+
+    #[test]
+    fn test_parser_array_expr() {
+        let expr = parse!("[0x12, 1 + 2] + b\"a\"").unwrap();
+        assert_eq!(format!("{}", expr), "(+ (bytes 18 (+ 1 2)) b\"a\")");
+
+        let expr = parse!("[] as u16le").unwrap();
+        assert_eq!(format!("{}", expr), "(as (bytes) u16le)");
+
+        let err = parse!("[1 2]").unwrap_err();
+        assert_eq!(
+            err,
+            error!(UnexpectedToken("]"), token!(Integer(2), 3..4)).into()
+        );
+    }
+
+    #[test]
+    fn test_parser_table_expr() {
+        let expr = parse!("table(i, 0..16, 1 << i)").unwrap();
+        assert_eq!(format!("{}", expr), "(table i (.. 0 16) (<< 1 i))");
+        assert_eq!(expr.loc(), 0..22);
+
+        let expr = parse!("table(n, 1..=N - 1, n * 1 KiB)").unwrap();
+        assert_eq!(format!("{}", expr), "(table n (..= 1 (- N 1)) (* n 1kiB))");
+
+        let err = parse!("table(i, 0, i)").unwrap_err();
+        assert_eq!(
+            err,
+            error!(UnexpectedToken(".."), token!(Comma, 10..11)).into()
+        );
+    }
 }
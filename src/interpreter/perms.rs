@@ -0,0 +1,155 @@
+/// The classes of users a mode has permissions for, from most to least significant bits.
+pub const CLASSES: [Class; 3] = [Class::User, Class::Group, Class::Other];
+
+/// The file types `ls -l` may prefix the permissions with.
+const FILE_TYPES: &[u8] = b"-dlcbps";
+
+/// A class of users with their own read, write and execute permissions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    User,
+    Group,
+    Other,
+}
+
+impl Class {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::User => "User",
+            Self::Group => "Group",
+            Self::Other => "Other",
+        }
+    }
+
+    /// Returns the position of the read, write and execute bits of the class in a mode.
+    const fn shift(self) -> u32 {
+        match self {
+            Self::User => 6,
+            Self::Group => 3,
+            Self::Other => 0,
+        }
+    }
+
+    /// Returns the special bit sharing its symbol with the execute bit of the class.
+    const fn special_bit(self) -> u32 {
+        match self {
+            Self::User => 0o4000,
+            Self::Group => 0o2000,
+            Self::Other => 0o1000,
+        }
+    }
+
+    /// Returns the name of the special bit of the class.
+    pub const fn special_name(self) -> &'static str {
+        match self {
+            Self::User => "setuid",
+            Self::Group => "setgid",
+            Self::Other => "sticky",
+        }
+    }
+
+    /// Returns the symbol of the special bit of the class, in lowercase.
+    const fn special_symbol(self) -> u8 {
+        match self {
+            Self::Other => b't',
+            _ => b's',
+        }
+    }
+
+    /// Returns the read, write and execute permissions of the class in `mode`.
+    pub const fn permissions(self, mode: u32) -> u32 {
+        mode >> self.shift() & 0o7
+    }
+
+    /// Returns whether the special bit of the class is set in `mode`.
+    pub const fn is_special(self, mode: u32) -> bool {
+        mode & self.special_bit() != 0
+    }
+
+    /// Returns the three symbols of the permissions of the class in `mode`, e.g. `r-x` or `rws`.
+    fn symbolic(self, mode: u32) -> [u8; 3] {
+        let perms = self.permissions(mode);
+        let flag = |bit, c| if perms & bit != 0 { c } else { b'-' };
+        let execute = match (self.is_special(mode), perms & 1 != 0) {
+            (true, true) => self.special_symbol(),
+            (true, false) => self.special_symbol().to_ascii_uppercase(),
+            (false, execute) => flag(execute as u32, b'x'),
+        };
+        [flag(0o4, b'r'), flag(0o2, b'w'), execute]
+    }
+
+    /// Parses the three symbols of the permissions of the class into their mode bits.
+    fn parse(self, symbols: &[u8]) -> Option<u32> {
+        let flag = |c, expected, bit| match c {
+            b'-' => Some(0),
+            c if c == expected => Some(bit),
+            _ => None,
+        };
+        let symbol = self.special_symbol();
+        let (special, execute) = match symbols[2] {
+            c if c == symbol => (true, 1),
+            c if c == symbol.to_ascii_uppercase() => (true, 0),
+            c => (false, flag(c, b'x', 1)?),
+        };
+        let perms = flag(symbols[0], b'r', 0o4)? | flag(symbols[1], b'w', 0o2)? | execute;
+        let special = if special { self.special_bit() } else { 0 };
+        Some(perms << self.shift() | special)
+    }
+}
+
+/// Formats `mode` like `ls -l` does, e.g. `rwsr-xr-x` for `0o4755`.
+pub fn symbolic(mode: u32) -> String {
+    let symbols = CLASSES.iter().flat_map(|class| class.symbolic(mode));
+    // The symbols only consist of ASCII characters
+    String::from_utf8(symbols.collect()).unwrap_or_default()
+}
+
+/// Parses a symbolic mode like `rw-r--r--` into its numeric value. The symbols may be prefixed with a file
+/// type, as printed by `ls -l`.
+pub fn parse(text: &[u8]) -> Option<u32> {
+    let text = match text {
+        [file_type, rest @ ..] if rest.len() == 9 && FILE_TYPES.contains(file_type) => rest,
+        text if text.len() == 9 => text,
+        _ => return None,
+    };
+
+    CLASSES
+        .iter()
+        .zip(text.chunks(3))
+        .try_fold(0, |mode, (class, symbols)| {
+            Some(mode | class.parse(symbols)?)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_symbolic() {
+        assert_eq!(symbolic(0o755), "rwxr-xr-x");
+        assert_eq!(symbolic(0o644), "rw-r--r--");
+        assert_eq!(symbolic(0o4755), "rwsr-xr-x");
+        assert_eq!(symbolic(0o2644), "rw-r-Sr--");
+        assert_eq!(symbolic(0o1777), "rwxrwxrwt");
+        assert_eq!(symbolic(0o1000), "--------T");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(b"rw-r--r--"), Some(0o644));
+        assert_eq!(parse(b"-rwsr-xr-x"), Some(0o4755));
+        assert_eq!(parse(b"drwxrwxrwt"), Some(0o1777));
+        assert_eq!(parse(b"rw-r-Sr--"), Some(0o2644));
+        assert_eq!(parse(b"rwxrwxrw"), None);
+        assert_eq!(parse(b"rwtrwxrwx"), None);
+        assert_eq!(parse(b"wr-r--r--"), None);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for mode in 0..=0o7777 {
+            assert_eq!(parse(symbolic(mode).as_bytes()), Some(mode));
+        }
+    }
+}
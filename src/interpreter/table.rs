@@ -0,0 +1,52 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use super::value::{Scalar, Value};
+
+/// The maximum number of rows of a table, to keep a mistyped range from running away.
+pub const MAX_ROWS: usize = 4096;
+
+/// The values of an expression for each integer of a range, e.g. of `table(i, 0..16, 1 << i)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// The name the integers are bound to.
+    var: String,
+    rows: Vec<(Scalar, Value)>,
+}
+
+impl Table {
+    pub fn new(var: String) -> Self {
+        Self {
+            var,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Adds the row of the integer `index` and the `value` of the expression for it.
+    pub fn push(&mut self, index: Scalar, value: Value) {
+        self.rows.push((index, value));
+    }
+
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    pub fn rows(&self) -> &[(Scalar, Value)] {
+        &self.rows
+    }
+}
+
+impl Display for Table {
+    /// Formats the values of the table as a list, e.g. `[1, 2, 4, 8]`.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let values = self
+            .rows
+            .iter()
+            .map(|(_, value)| match f.precision() {
+                Some(precision) => format!("{value:.precision$}"),
+                None => value.to_string(),
+            })
+            .collect::<Vec<_>>();
+        write!(f, "[{}]", values.join(", "))
+    }
+}
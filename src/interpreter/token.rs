@@ -3,7 +3,7 @@ use std::{
     ops::Range,
 };
 
-use super::unit_prefix::UnitPrefix;
+use super::{bytes, fixed_point::QFormat, ip_type::IpType, unit_prefix::UnitPrefix};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Unit {
@@ -73,7 +73,7 @@ impl Display for FullUnit {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TokenKind {
     // Single character tokens
     Minus,
@@ -82,13 +82,38 @@ pub enum TokenKind {
     Slash,
     LeftParen,
     RightParen,
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    Colon,
+    Dot,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    Comma,
+    Equal,
+
+    // Multi character tokens
+    LessLess,
+    GreaterGreater,
+    GreaterGreaterGreater,
+    DotDot,
 
     // Literals
     Unit(FullUnit),
     Integer(u64),
+    Float(f64),
+    Bytes(Vec<u8>),
+    Address(IpType, u128),
+    Identifier(String),
+    QFormat(QFormat),
 
     // Keywords
     As,
+    Layout,
+    Let,
 
     // End of file
     Eof,
@@ -103,15 +128,38 @@ impl Display for TokenKind {
             TokenKind::Slash => write!(f, "/"),
             TokenKind::LeftParen => write!(f, "("),
             TokenKind::RightParen => write!(f, ")"),
+            TokenKind::LeftBracket => write!(f, "["),
+            TokenKind::RightBracket => write!(f, "]"),
+            TokenKind::LeftBrace => write!(f, "{{"),
+            TokenKind::RightBrace => write!(f, "}}"),
+            TokenKind::Colon => write!(f, ":"),
+            TokenKind::Dot => write!(f, "."),
+            TokenKind::Ampersand => write!(f, "&"),
+            TokenKind::Pipe => write!(f, "|"),
+            TokenKind::Caret => write!(f, "^"),
+            TokenKind::Tilde => write!(f, "~"),
+            TokenKind::Comma => write!(f, ","),
+            TokenKind::Equal => write!(f, "="),
+            TokenKind::LessLess => write!(f, "<<"),
+            TokenKind::GreaterGreater => write!(f, ">>"),
+            TokenKind::GreaterGreaterGreater => write!(f, ">>>"),
+            TokenKind::DotDot => write!(f, ".."),
             TokenKind::Unit(unit) => write!(f, "{}", unit),
             TokenKind::Integer(num) => write!(f, "{}", num),
+            TokenKind::Float(num) => write!(f, "{}", num),
+            TokenKind::Bytes(bytes) => write!(f, "{}", bytes::literal(bytes)),
+            TokenKind::Address(ty, bits) => write!(f, "{}", ty.format(*bits)),
+            TokenKind::Identifier(name) => write!(f, "{}", name),
+            TokenKind::QFormat(q) => write!(f, "{}", q),
             TokenKind::As => write!(f, "as"),
+            TokenKind::Layout => write!(f, "layout"),
+            TokenKind::Let => write!(f, "let"),
             TokenKind::Eof => write!(f, "EOF"),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     kind: TokenKind,
     loc: Range<usize>,
@@ -123,7 +171,7 @@ impl Token {
     }
 
     pub fn kind(&self) -> TokenKind {
-        self.kind
+        self.kind.clone()
     }
 
     pub fn loc(&self) -> Range<usize> {
@@ -179,6 +227,14 @@ mod test {
         assert_eq!(format!("{}", TokenKind::Slash), "/");
         assert_eq!(format!("{}", TokenKind::LeftParen), "(");
         assert_eq!(format!("{}", TokenKind::RightParen), ")");
+        assert_eq!(format!("{}", TokenKind::Ampersand), "&");
+        assert_eq!(format!("{}", TokenKind::Pipe), "|");
+        assert_eq!(format!("{}", TokenKind::Caret), "^");
+        assert_eq!(format!("{}", TokenKind::Tilde), "~");
+        assert_eq!(format!("{}", TokenKind::Comma), ",");
+        assert_eq!(format!("{}", TokenKind::LessLess), "<<");
+        assert_eq!(format!("{}", TokenKind::GreaterGreater), ">>");
+        assert_eq!(format!("{}", TokenKind::GreaterGreaterGreater), ">>>");
         assert_eq!(
             format!(
                 "{}",
@@ -187,6 +243,8 @@ mod test {
             "kB"
         );
         assert_eq!(format!("{}", TokenKind::Integer(42)), "42");
+        assert_eq!(format!("{}", TokenKind::Float(1.5)), "1.5");
+        assert_eq!(format!("{}", TokenKind::Identifier("sext".into())), "sext");
         assert_eq!(format!("{}", TokenKind::As), "as");
         assert_eq!(format!("{}", TokenKind::Eof), "EOF");
     }
@@ -0,0 +1,213 @@
+use std::ops::Range;
+
+use super::{
+    environment::Environment,
+    evaluate,
+    expr::{Expr, OperatorExpr},
+    identifier,
+    token::{FullUnit, TokenKind},
+    value::{common_type, common_unit, Scalar, Value},
+    SyntaxError,
+};
+
+/// A step of the evaluation of an expression, i.e. the value of a sub-expression, notes on the implicit
+/// conversions done to compute it and the steps of its operands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    /// The location of the sub-expression in the input.
+    pub loc: Range<usize>,
+    pub value: Value,
+    pub notes: Vec<String>,
+    pub steps: Vec<Trace>,
+}
+
+/// Evaluates `expr` like [`evaluate`], recording the value of each sub-expression along with the
+/// conversions of units and types done by its operator.
+pub fn trace(expr: &Expr, env: &Environment) -> Result<Trace, SyntaxError> {
+    // Parentheses only group, they don't add a step
+    if let Expr::Grouping(expr) = expr {
+        return trace(expr, env);
+    }
+
+    let steps = operands(expr)
+        .into_iter()
+        .map(|operand| trace(operand, env))
+        .collect::<Result<Vec<_>, _>>()?;
+    let value = evaluate(expr, env)?;
+    let mut notes = notes(expr, &steps, env);
+    if let Some(loss) = value.loss() {
+        notes.push(format!("Warning: {loss}"));
+    }
+    Ok(Trace {
+        loc: expr.loc(),
+        value,
+        notes,
+        steps,
+    })
+}
+
+impl Trace {
+    /// Extends the locations of the steps to the brackets of `input` they are missing, e.g. the closing
+    /// parenthesis of a call, which are not part of any expression.
+    pub fn enclose_brackets(&mut self, input: &str) {
+        self.loc = enclose_brackets(input.as_bytes(), self.loc.clone());
+        for step in &mut self.steps {
+            step.enclose_brackets(input);
+        }
+    }
+}
+
+/// Returns `loc` extended to the brackets of `input` needed to balance the brackets within it.
+fn enclose_brackets(input: &[u8], loc: Range<usize>) -> Range<usize> {
+    let (mut unopened, mut unclosed) = (0, 0);
+    for c in &input[loc.clone()] {
+        match c {
+            b'(' | b'[' | b'{' => unclosed += 1,
+            b')' | b']' | b'}' if unclosed == 0 => unopened += 1,
+            b')' | b']' | b'}' => unclosed -= 1,
+            _ => {}
+        }
+    }
+
+    let Range { mut start, mut end } = loc;
+    while unopened > 0 && start > 0 {
+        start -= 1;
+        match input[start] {
+            b'(' | b'[' | b'{' => unopened -= 1,
+            b')' | b']' | b'}' => unopened += 1,
+            _ => {}
+        }
+    }
+    while unclosed > 0 && end < input.len() {
+        match input[end] {
+            b'(' | b'[' | b'{' => unclosed += 1,
+            b')' | b']' | b'}' => unclosed -= 1,
+            _ => {}
+        }
+        end += 1;
+    }
+    start..end
+}
+
+/// Returns the operands of `expr`, which are evaluated before it.
+fn operands(expr: &Expr) -> Vec<&Expr> {
+    use OperatorExpr as OE;
+
+    match expr {
+        Expr::Operator(OE::ArithmeticOrLogical { left, right, .. }) => vec![left, right],
+        Expr::Operator(OE::TypeCast { expr, .. } | OE::Unary { right: expr, .. }) => vec![expr],
+        Expr::Grouping(expr) | Expr::Decode { expr, .. } => vec![expr],
+        // The body is evaluated once per row, with a variable that is only bound within it
+        Expr::Table { start, end, .. } => vec![start, end],
+        Expr::Literal { .. } | Expr::Variable(_) => vec![],
+        Expr::Call { args, .. } => args.iter().collect(),
+        Expr::Array { elements, .. } => elements.iter().collect(),
+        Expr::Bitfield { fields, .. } => fields.iter().map(|(_, expr)| expr).collect(),
+    }
+}
+
+/// Returns the notes on the implicit conversions done to evaluate `expr` from the `steps` of its
+/// operands, or on the definition of a variable.
+fn notes(expr: &Expr, steps: &[Trace], env: &Environment) -> Vec<String> {
+    use OperatorExpr as OE;
+    use TokenKind as TK;
+
+    let scalars = steps
+        .iter()
+        .filter_map(|step| step.value.scalar().ok())
+        .collect::<Vec<_>>();
+    match (expr, scalars.as_slice()) {
+        (Expr::Operator(OE::ArithmeticOrLogical { operator, .. }), [left, right])
+            if matches!(operator.kind(), TK::Plus | TK::Minus) =>
+        {
+            let verb = match operator.kind() {
+                TK::Plus => "added",
+                _ => "subtracted",
+            };
+            let mut notes = Vec::new();
+            if let (Some(left_ty), Some(right_ty)) = (left.ty(), right.ty()) {
+                if left_ty != right_ty {
+                    let ty = common_type(left.ty(), right.ty()).unwrap();
+                    notes.push(format!("{left_ty} and {right_ty} are {verb} as {ty}"));
+                }
+            }
+            if let (Some(left_unit), Some(right_unit)) = (left.unit(), right.unit()) {
+                let unit = common_unit(left.unit(), right.unit()).unwrap();
+                if left_unit != right_unit {
+                    notes.push(format!(
+                        "{left_unit} and {right_unit} are {verb} in {unit}, the more precise unit"
+                    ));
+                }
+                notes.extend(
+                    [left, right]
+                        .iter()
+                        .filter_map(|scalar| conversion(scalar, unit)),
+                );
+            }
+            notes
+        }
+        (Expr::Operator(OE::TypeCast { target, .. }), [scalar]) => match target.kind() {
+            TK::Unit(unit) => conversion(scalar, unit).into_iter().collect(),
+            _ => vec![],
+        },
+        (Expr::Variable(name), _) => env
+            .value(&identifier(name))
+            .map(|definition| format!("Defined as {}", definition.expr()))
+            .into_iter()
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns the note on converting `scalar` to `unit`, if it has a different unit.
+fn conversion(scalar: &Scalar, unit: FullUnit) -> Option<String> {
+    let conversion = scalar.conversion(unit)?;
+    let converted = scalar.clone().convert_to(unit);
+    Some(format!(
+        "{scalar} is converted from {conversion}: {converted}"
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interpreter::Interpreter;
+
+    #[test]
+    fn test_trace() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret("let SIZE = 1 KiB").unwrap();
+        let trace = interpreter.explain("(SIZE + 1 kB) as B").unwrap();
+        assert_eq!(trace.loc, 0..18);
+        assert_eq!(trace.value.to_string(), "2024B");
+        assert_eq!(
+            trace.notes,
+            ["2.024kB is converted from kB to B (x 1000): 2024B"]
+        );
+
+        let sum = &trace.steps[0];
+        assert_eq!(sum.loc, 1..12);
+        assert_eq!(
+            sum.notes,
+            [
+                "kiB and kB are added in kB, the more precise unit",
+                "1kiB is converted from kiB to kB (x 1.024): 1.024kB",
+            ]
+        );
+        assert_eq!(sum.steps[0].notes, ["Defined as 1 KiB"]);
+        assert_eq!(sum.steps[1].loc, 8..12);
+        assert!(sum.steps[1].steps.is_empty());
+
+        let trace = interpreter.explain("zext(-(1), 16)").unwrap();
+        assert_eq!(trace.loc, 0..14);
+        assert_eq!(trace.steps[0].loc, 5..9);
+    }
+
+    #[test]
+    fn test_trace_types() {
+        let trace = Interpreter::new()
+            .explain("(1 as u8) - (2 as i16)")
+            .unwrap();
+        assert_eq!(trace.value.to_string(), "-1: i16");
+        assert_eq!(trace.notes, ["u8 and i16 are subtracted as i16"]);
+    }
+}
@@ -55,24 +55,6 @@ impl UnitPrefix {
             EXBI..=u64::MAX => Self::Exbi,
         }
     }
-
-    pub(crate) fn len(&self) -> usize {
-        match self {
-            Self::None => 0,
-            Self::Kilo => 1,
-            Self::Mega => 1,
-            Self::Giga => 1,
-            Self::Tera => 1,
-            Self::Peta => 1,
-            Self::Exa => 1,
-            Self::Kibi => 2,
-            Self::Mebi => 2,
-            Self::Gibi => 2,
-            Self::Tebi => 2,
-            Self::Pebi => 2,
-            Self::Exbi => 2,
-        }
-    }
 }
 
 impl TryFrom<&str> for UnitPrefix {
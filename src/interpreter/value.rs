@@ -5,7 +5,103 @@ use std::{
 };
 use thiserror::Error;
 
-use super::token::FullUnit;
+use super::{
+    byte_order::{swap_bytes, ByteOrder},
+    bytes,
+    char_type::CharType,
+    diff::Diff,
+    fixed_point::QFormat,
+    float_type::FloatType,
+    int_type::IntType,
+    ip_type::IpType,
+    layout::{Bitfield, Layout},
+    num::Number,
+    perms,
+    table::Table,
+    token::{FullUnit, Unit},
+    unit_prefix::UnitPrefix,
+};
+
+/// The width used for logical operations on values without a type.
+const DEFAULT_TYPE: IntType = IntType::unsigned(64);
+
+/// The type holding the code points of character typed values.
+const CHAR_STORAGE_TYPE: IntType = IntType::unsigned(32);
+
+/// The type holding file permission modes, i.e. the permission bits and the setuid, setgid and sticky bits.
+const PERMS_STORAGE_TYPE: IntType = IntType::unsigned(12);
+
+/// The type of a value.
+///
+/// Integer typed values are always integers wrapped into the range of their type, float and fixed point
+/// typed values are always rounded to the precision of their format. Character typed values hold a code
+/// point, which is only guaranteed to be a valid character right after a cast. IP address typed values are
+/// unsigned integers of the width of the address, file permission typed values are 12 bit unsigned
+/// integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Int(IntType),
+    Float(FloatType),
+    Fixed(QFormat),
+    Char(CharType),
+    Ip(IpType),
+    Perms,
+}
+
+impl Type {
+    /// Returns the integer type the values of this type are wrapped into, if they are integers.
+    pub fn int_type(self) -> Option<IntType> {
+        match self {
+            Type::Int(ty) => Some(ty),
+            Type::Ip(ty) => Some(ty.storage_type()),
+            Type::Perms => Some(PERMS_STORAGE_TYPE),
+            _ => None,
+        }
+    }
+}
+
+impl From<IntType> for Type {
+    fn from(ty: IntType) -> Self {
+        Type::Int(ty)
+    }
+}
+
+impl From<FloatType> for Type {
+    fn from(ty: FloatType) -> Self {
+        Type::Float(ty)
+    }
+}
+
+impl From<QFormat> for Type {
+    fn from(ty: QFormat) -> Self {
+        Type::Fixed(ty)
+    }
+}
+
+impl From<CharType> for Type {
+    fn from(ty: CharType) -> Self {
+        Type::Char(ty)
+    }
+}
+
+impl From<IpType> for Type {
+    fn from(ty: IpType) -> Self {
+        Type::Ip(ty)
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int(ty) => write!(f, "{}", ty),
+            Type::Float(ty) => write!(f, "{}", ty),
+            Type::Fixed(ty) => write!(f, "{}", ty),
+            Type::Char(ty) => write!(f, "{}", ty),
+            Type::Ip(ty) => write!(f, "{}", ty),
+            Type::Perms => write!(f, "perms"),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Error, Diagnostic)]
 pub enum ValueErrorKind {
@@ -13,26 +109,414 @@ pub enum ValueErrorKind {
     DivisionByUnit,
     #[error("Cannot multiply two values with units")]
     MultiplicationByUnit,
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Cannot apply a bitwise operator to a value with a unit")]
+    BitwiseWithUnit,
+    #[error("Expected an integer")]
+    NotAnInteger,
+    #[error("Cannot shift by a negative amount")]
+    NegativeShift,
+    #[error("Integer overflow")]
+    Overflow,
+    #[error("Bit width must be between 1 and 128")]
+    InvalidBitWidth,
+    #[error("Bit width must be a multiple of 8")]
+    NotByteAligned,
+    #[error("Value does not fit into {bits} bit{}", if *.bits == 1 { "" } else { "s" })]
+    TooWide { bits: u32 },
+    #[error("Unknown type")]
+    UnknownType,
+    #[error("Unknown function")]
+    UnknownFunction,
+    #[error("Expected {expected} arguments, found {found}")]
+    WrongArgumentCount { expected: usize, found: usize },
+    #[error("Expected a number")]
+    ExpectedNumber,
+    #[error("Expected a byte array")]
+    ExpectedBytes,
+    #[error("Byte array elements must be between 0 and 255")]
+    NotAByte,
+    #[error("Expected {expected} bytes, found {found}")]
+    WrongByteCount { expected: usize, found: usize },
+    #[error("Invalid {0}")]
+    InvalidEncoding(&'static str),
+    #[error("Not a valid Unicode code point")]
+    InvalidCodePoint,
+    #[error("Expected an IP address")]
+    ExpectedAddress,
+    #[error("Prefix length must be between 0 and {bits}")]
+    InvalidPrefix { bits: u32 },
+    #[error("Cannot mix IPv4 and IPv6 addresses")]
+    IpVersionMismatch,
+    #[error("IPv6 networks need a prefix length of at least 2 to count their hosts")]
+    TooManyHosts,
+    #[error("Unknown variable")]
+    UnknownVariable,
+    #[error("Unknown field")]
+    UnknownField,
+    #[error("Duplicate field")]
+    DuplicateField,
+    #[error("Field overlaps another one at bit {bit}")]
+    OverlappingField { bit: u32 },
+    #[error("Bit index must be below 128")]
+    InvalidBitIndex,
+    #[error("Expected a layout")]
+    ExpectedLayout,
+    #[error("Tables have at most {max} rows")]
+    TooManyRows { max: usize },
+}
+
+/// A lossy conversion that produced a value, along with the exact value before the conversion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Loss {
+    /// The exact value was rounded to the nearest representable one.
+    Rounded(f64),
+    /// The exact value was out of range and clamped to the nearest bound.
+    Saturated(f64),
 }
 
-pub struct Value {
-    value: f64,
+impl Display for Loss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Loss::Rounded(exact) => write!(f, "rounded from {}", exact),
+            Loss::Saturated(exact) => write!(f, "saturated from {}", exact),
+        }
+    }
+}
+
+/// The result of evaluating an expression, i.e. a number, an array of bytes, a bitfield layout, a number
+/// decoded according to such a layout, the comparison of the bits of two numbers or a table of values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Scalar(Scalar),
+    Bytes(Vec<u8>),
+    Layout(Layout),
+    Bitfield(Bitfield),
+    Diff(Diff),
+    Table(Table),
+}
+
+/// A number with an optional unit and type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scalar {
+    value: Number,
     unit: Option<FullUnit>,
+    ty: Option<Type>,
+    loss: Option<Loss>,
 }
 
-impl Value {
-    pub fn new(value: f64, unit: Option<FullUnit>) -> Self {
-        Self { value, unit }
+/// Returns the type of the result of a binary operation on values of type `left` and `right`.
+///
+/// Float types take precedence over fixed point types, which take precedence over character types, which
+/// take precedence over IP address types, which take precedence over file permission types, which take
+/// precedence over integer types. Mixing two types of the same kind results in the wider type.
+pub fn common_type(left: Option<Type>, right: Option<Type>) -> Option<Type> {
+    use Type as T;
+
+    match (left, right) {
+        (Some(T::Int(left)), Some(T::Int(right))) => Some(T::Int(left.max(right))),
+        (Some(T::Float(left)), Some(T::Float(right))) if right.bits() > left.bits() => {
+            Some(T::Float(right))
+        }
+        (Some(T::Float(ty)), _) | (_, Some(T::Float(ty))) => Some(T::Float(ty)),
+        (Some(T::Fixed(left)), Some(T::Fixed(right))) if right.bits() > left.bits() => {
+            Some(T::Fixed(right))
+        }
+        (Some(T::Fixed(ty)), _) | (_, Some(T::Fixed(ty))) => Some(T::Fixed(ty)),
+        (Some(T::Char(ty)), _) | (_, Some(T::Char(ty))) => Some(T::Char(ty)),
+        (Some(T::Ip(left)), Some(T::Ip(right))) if right.bits() > left.bits() => Some(T::Ip(right)),
+        (Some(T::Ip(ty)), _) | (_, Some(T::Ip(ty))) => Some(T::Ip(ty)),
+        (Some(T::Perms), _) | (_, Some(T::Perms)) => Some(T::Perms),
+        (left, right) => left.or(right),
+    }
+}
+
+/// Returns the unit of the result of adding or subtracting values with the units `left` and `right`,
+/// which is the more precise, i.e. smaller, one if both have a unit.
+pub fn common_unit(left: Option<FullUnit>, right: Option<FullUnit>) -> Option<FullUnit> {
+    match (left, right) {
+        (Some(left), Some(right)) => Some(std::cmp::min(left, right)),
+        (left, right) => left.or(right),
+    }
+}
+
+/// A conversion of a value from one unit to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conversion {
+    pub from: FullUnit,
+    pub to: FullUnit,
+}
+
+impl Conversion {
+    /// Returns the factor values are multiplied by in the conversion.
+    pub fn multiplier(&self) -> f64 {
+        u64::from(self.from) as f64 / u64::from(self.to) as f64
+    }
+}
+
+impl Display for Conversion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} to {} (x {})", self.from, self.to, self.multiplier())
+    }
+}
+
+impl Scalar {
+    pub fn new(value: impl Into<Number>, unit: Option<FullUnit>) -> Self {
+        Self::typed(value.into(), unit, None)
+    }
+
+    /// Creates a new value of type `ty`, wrapping or rounding `value` into the range of the type.
+    pub fn typed(value: Number, unit: Option<FullUnit>, ty: Option<Type>) -> Self {
+        let value = match ty {
+            Some(Type::Int(ty)) => Number::Int(ty.wrap(value.trunc())),
+            Some(Type::Float(ty)) => Number::Float(ty.round(value.as_f64())),
+            Some(Type::Fixed(ty)) => Number::Float(ty.round(value.as_f64())),
+            Some(Type::Char(_)) => Number::Int(CHAR_STORAGE_TYPE.wrap(value.trunc())),
+            Some(Type::Ip(ty)) => Number::Int(ty.storage_type().wrap(value.trunc())),
+            Some(Type::Perms) => Number::Int(PERMS_STORAGE_TYPE.wrap(value.trunc())),
+            None => value,
+        };
+        Self {
+            value,
+            unit,
+            ty,
+            loss: None,
+        }
     }
 
     pub fn value(&self) -> f64 {
-        self.value
+        match self.bits() {
+            Some(bits) if self.int_type().is_some_and(|ty| !ty.is_signed()) => bits as f64,
+            _ => self.value.as_f64(),
+        }
     }
 
     pub fn unit(&self) -> Option<FullUnit> {
         self.unit
     }
 
+    pub fn ty(&self) -> Option<Type> {
+        self.ty
+    }
+
+    /// Returns how the value was changed by the conversion that produced it, if it was lossy.
+    pub fn loss(&self) -> Option<Loss> {
+        self.loss
+    }
+
+    /// Returns the integer type the value is wrapped into, if it has one.
+    fn int_type(&self) -> Option<IntType> {
+        self.ty.and_then(Type::int_type)
+    }
+
+    /// Returns the integer value, if the value is integral and not of a float or fixed point type.
+    pub fn int(&self) -> Option<i128> {
+        match self.ty {
+            Some(Type::Float(_) | Type::Fixed(_)) => None,
+            _ => self.value.as_int(),
+        }
+    }
+
+    /// Returns the character with the value as its code point, if there is one.
+    pub fn char(&self) -> Option<char> {
+        u32::try_from(self.int()?).ok().and_then(char::from_u32)
+    }
+
+    /// Returns the bit pattern of the value in its type, or its 128 bit two's complement if the value has
+    /// no type.
+    pub fn bits(&self) -> Option<u128> {
+        match self.ty {
+            Some(Type::Int(ty)) => Some(ty.to_bits(self.int()?)),
+            Some(Type::Float(ty)) => Some(ty.encode(self.value.as_f64()).into()),
+            Some(Type::Fixed(ty)) => {
+                Some(ty.raw_type().to_bits(ty.encode(self.value.as_f64()).raw))
+            }
+            Some(Type::Char(ty)) => self.char().map(|c| ty.encode(c)),
+            Some(Type::Ip(ty)) => Some(ty.storage_type().to_bits(self.int()?)),
+            Some(Type::Perms) => Some(PERMS_STORAGE_TYPE.to_bits(self.int()?)),
+            None => self.int().map(|value| value as u128),
+        }
+    }
+
+    /// Returns the width of the value in bytes, i.e. the width of its type or the fewest bytes holding it
+    /// if it has no type.
+    pub fn byte_width(&self) -> Option<u32> {
+        let bits = match self.ty {
+            Some(Type::Int(ty)) => ty.bits(),
+            Some(Type::Float(ty)) => ty.bits(),
+            Some(Type::Fixed(ty)) => ty.bits(),
+            Some(Type::Char(ty)) => return self.char().map(|c| ty.byte_width(c)),
+            Some(Type::Ip(ty)) => ty.bits(),
+            Some(Type::Perms) => PERMS_STORAGE_TYPE.bits(),
+            None => match self.int()? {
+                value if value < 0 => (129 - value.leading_ones()).max(DEFAULT_TYPE.bits()),
+                value => 128 - value.leading_zeros(),
+            },
+        };
+        Some(bits.div_ceil(8).max(1))
+    }
+
+    /// Returns the bytes of the bit pattern of the value at its width, in the memory order of `order`.
+    pub fn bytes(&self, order: ByteOrder) -> Option<Vec<u8>> {
+        if let Some(Type::Char(ty)) = self.ty {
+            return self.char().map(|c| ty.to_bytes(c, order));
+        }
+        Some(order.to_bytes(self.bits()?, self.byte_width()?))
+    }
+
+    /// Reads the bytes of the value, as it is written with its most significant byte first, in the byte
+    /// order `order`.
+    ///
+    /// Reading them as big endian leaves the value as is, reading them as little endian swaps its bytes
+    /// at its current width.
+    pub fn try_read_bytes(&self, order: ByteOrder) -> Result<Self, ValueErrorKind> {
+        if self.unit.is_some() {
+            return Err(ValueErrorKind::BitwiseWithUnit);
+        }
+        self.try_int()?;
+        if matches!(self.ty, Some(Type::Int(ty)) if !ty.bits().is_multiple_of(8)) {
+            return Err(ValueErrorKind::NotByteAligned);
+        }
+
+        let bits = match (order, self.bits(), self.byte_width()) {
+            (ByteOrder::Big, _, _) => return Ok(self.clone()),
+            (ByteOrder::Little, Some(bits), Some(bytes)) => swap_bytes(bits, bytes),
+            _ => return Err(ValueErrorKind::NotAnInteger),
+        };
+        Ok(Self::typed(Number::Int(bits as i128), None, self.ty))
+    }
+
+    /// Returns the integer value, erroring if the value is not integral.
+    pub fn try_int(&self) -> Result<i128, ValueErrorKind> {
+        self.int().ok_or(ValueErrorKind::NotAnInteger)
+    }
+
+    pub fn cast(self, ty: IntType) -> Self {
+        Self::typed(self.value, self.unit, Some(ty.into()))
+    }
+
+    /// Converts the value to the float type `ty`.
+    ///
+    /// Integers are reinterpreted as the bit pattern of a float, floats are rounded to the precision of
+    /// the format. Untyped numbers go by their kind rather than their value: integer literals and results
+    /// of integer arithmetic like `4 / 2` are bit patterns, while `2.0` or `1.5 * 2` are rounded.
+    pub fn try_reinterpret_float(self, ty: FloatType) -> Result<Self, ValueErrorKind> {
+        let bits = match (self.ty, self.value) {
+            (Some(Type::Float(_)), _) | (None, Number::Float(_)) => {
+                return Ok(Self::typed(self.value, self.unit, Some(ty.into())))
+            }
+            _ => self.bits().ok_or(ValueErrorKind::NotAnInteger)?,
+        };
+
+        let bits = u64::try_from(bits)
+            .ok()
+            .filter(|bits| bits >> 1 >> (ty.bits() - 1) == 0)
+            .ok_or(ValueErrorKind::TooWide { bits: ty.bits() })?;
+
+        let value = Number::Float(ty.decode(bits));
+        Ok(Self::typed(value, self.unit, Some(ty.into())))
+    }
+
+    /// Returns the bit pattern of the value encoded as the float type `ty`.
+    pub fn float_bits(self, ty: FloatType) -> Self {
+        let bits = ty.encode(self.value());
+        let int_ty = IntType::unsigned(ty.bits());
+        Self::typed(Number::Int(bits.into()), None, Some(int_ty.into()))
+    }
+
+    /// Converts the value to the fixed point format `ty`.
+    ///
+    /// Integers are reinterpreted as the raw value of the format, other numbers are encoded into their raw
+    /// value, rounding to nearest and saturating at the bounds of the format.
+    pub fn try_fixed(self, ty: QFormat) -> Result<Self, ValueErrorKind> {
+        let too_wide = ValueErrorKind::TooWide { bits: ty.bits() };
+        let raw = match (self.ty, self.value) {
+            (Some(Type::Int(_)), _) => self
+                .bits()
+                .filter(|bits| bits >> ty.bits() == 0)
+                .ok_or(too_wide)? as i128,
+            // Untyped integers may also be negative raw values of signed formats
+            (None, Number::Int(raw)) => Some(raw)
+                .filter(|&raw| {
+                    IntType::signed(ty.bits()).wrap(raw) == raw
+                        || IntType::unsigned(ty.bits()).wrap(raw) == raw
+                })
+                .ok_or(too_wide)?,
+            _ => return Ok(self.fixed_raw(ty)),
+        };
+
+        let value = Number::Float(ty.decode(raw));
+        Ok(Self::typed(value, self.unit, Some(ty.into())))
+    }
+
+    /// Returns the raw value of the value encoded in the fixed point format `ty`.
+    fn fixed_raw(self, ty: QFormat) -> Self {
+        let exact = self.value();
+        let encoded = ty.encode(exact);
+        let loss = if encoded.saturated {
+            Some(Loss::Saturated(exact))
+        } else if encoded.rounded {
+            Some(Loss::Rounded(exact))
+        } else {
+            None
+        };
+
+        let value = Number::Int(encoded.raw);
+        Self {
+            loss,
+            ..Self::typed(value, self.unit, Some(ty.raw_type().into()))
+        }
+    }
+
+    /// Views the value as the code point of a character, shown as `ty`.
+    pub fn try_char(self, ty: CharType) -> Result<Self, ValueErrorKind> {
+        let c = self.char().ok_or(ValueErrorKind::InvalidCodePoint)?;
+        Ok(Self::typed(
+            Number::Int(u32::from(c).into()),
+            None,
+            Some(ty.into()),
+        ))
+    }
+
+    /// Views the integer value as an IP address of type `ty`, erroring if it does not fit into the address.
+    pub fn try_ip(self, ty: IpType) -> Result<Self, ValueErrorKind> {
+        if self.unit.is_some() {
+            return Err(ValueErrorKind::BitwiseWithUnit);
+        }
+        self.try_int()?;
+
+        let bits = self
+            .bits()
+            .filter(|bits| bits >> 1 >> (ty.bits() - 1) == 0)
+            .ok_or(ValueErrorKind::TooWide { bits: ty.bits() })?;
+        Ok(Self::typed(
+            Number::Int(bits as i128),
+            None,
+            Some(ty.into()),
+        ))
+    }
+
+    /// Views the integer value as a file permission mode, erroring if it has bits beyond the mode.
+    pub fn try_perms(self) -> Result<Self, ValueErrorKind> {
+        if self.unit.is_some() {
+            return Err(ValueErrorKind::BitwiseWithUnit);
+        }
+
+        let bits = PERMS_STORAGE_TYPE.bits();
+        let mode = Some(self.try_int()?)
+            .filter(|mode| PERMS_STORAGE_TYPE.wrap(*mode) == *mode)
+            .ok_or(ValueErrorKind::TooWide { bits })?;
+        Ok(Self::typed(Number::Int(mode), None, Some(Type::Perms)))
+    }
+
+    /// Returns the conversion of the value to `unit`, if it has a different unit.
+    pub fn conversion(&self, unit: FullUnit) -> Option<Conversion> {
+        self.unit
+            .filter(|from| *from != unit)
+            .map(|from| Conversion { from, to: unit })
+    }
+
     pub fn convert_to(self, unit: FullUnit) -> Self {
         if self.unit == Some(unit) {
             return self;
@@ -40,13 +524,34 @@ impl Value {
 
         let our_unit = match self.unit {
             Some(u) => u,
-            None => return Self::new(self.value, Some(unit)),
+            None => return Self::typed(self.value, Some(unit), self.ty),
         };
 
-        let multiplier = u64::from(our_unit) as f64 / u64::from(unit) as f64;
-        let value = self.value * multiplier;
+        let (ours, theirs) = (u64::from(our_unit), u64::from(unit));
+        let value = match self.value {
+            Number::Int(v) if ours % theirs == 0 => Number::Int(v) * Number::from(ours / theirs),
+            v => {
+                let conversion = Conversion {
+                    from: our_unit,
+                    to: unit,
+                };
+                Number::Float(v.as_f64() * conversion.multiplier())
+            }
+        };
 
-        Self::new(value, Some(unit))
+        Self::typed(value, Some(unit), self.ty)
+    }
+
+    /// Converts the size to bytes with the largest decimal or, if `binary`, binary prefix it is at least
+    /// one of, e.g. `1536B` to `1.5kiB`. Values without a unit are taken as bytes.
+    pub fn convert_to_auto(self, binary: bool) -> Self {
+        let bytes = self.convert_to(FullUnit::byte());
+        let num = bytes.value().abs() as u64;
+        let prefix = match binary {
+            true => UnitPrefix::bin_from_num(num),
+            false => UnitPrefix::dec_from_num(num),
+        };
+        bytes.convert_to(FullUnit::new(prefix, Unit::Byte))
     }
 
     /// Returns the result of multiplying `self` by `rhs`, but only if one or both of the two values are
@@ -57,86 +562,554 @@ impl Value {
         }
 
         let unit = self.unit.or(rhs.unit);
-        Ok(Self::new(self.value * rhs.value, unit))
+        let ty = common_type(self.ty, rhs.ty);
+        let value = match ty {
+            Some(Type::Int(_) | Type::Ip(_)) => {
+                Number::Int(self.value.trunc().wrapping_mul(rhs.value.trunc()))
+            }
+            _ => self.value * rhs.value,
+        };
+        Ok(Self::typed(value, unit, ty))
     }
 
     /// Returns the result of dividing `self` by `rhs`, but only if the `rhs` or both of the two values are
     /// unitless.
+    ///
+    /// Typed values use integer division, untyped values only stay integers if the division is exact.
     pub fn try_div(&self, rhs: Self) -> Result<Self, ValueErrorKind> {
         if rhs.unit.is_some() {
             return Err(ValueErrorKind::DivisionByUnit);
         }
 
-        Ok(Self::new(self.value / rhs.value, self.unit))
+        let ty = common_type(self.ty, rhs.ty);
+        let value = match ty.and_then(Type::int_type) {
+            Some(ty) if !ty.is_signed() => {
+                let (left, right) = (
+                    ty.to_bits(self.value.trunc()),
+                    ty.to_bits(rhs.value.trunc()),
+                );
+                let value = left
+                    .checked_div(right)
+                    .ok_or(ValueErrorKind::DivisionByZero)?;
+                Number::Int(value as i128)
+            }
+            Some(_) => {
+                let (left, right) = (self.value.trunc(), rhs.value.trunc());
+                if right == 0 {
+                    return Err(ValueErrorKind::DivisionByZero);
+                }
+                Number::Int(left.wrapping_div(right))
+            }
+            None => self.value / rhs.value,
+        };
+        Ok(Self::typed(value, self.unit, ty))
+    }
+
+    /// Returns the integer operands of a bitwise operation and the type of its result.
+    fn bitwise_operands(&self, rhs: &Self) -> Result<(i128, i128, Option<Type>), ValueErrorKind> {
+        if self.unit.is_some() || rhs.unit.is_some() {
+            return Err(ValueErrorKind::BitwiseWithUnit);
+        }
+
+        Ok((
+            self.try_int()?,
+            rhs.try_int()?,
+            common_type(self.ty, rhs.ty),
+        ))
+    }
+
+    pub fn try_bitand(&self, rhs: Self) -> Result<Self, ValueErrorKind> {
+        let (left, right, ty) = self.bitwise_operands(&rhs)?;
+        Ok(Self::typed(Number::Int(left & right), None, ty))
+    }
+
+    pub fn try_bitor(&self, rhs: Self) -> Result<Self, ValueErrorKind> {
+        let (left, right, ty) = self.bitwise_operands(&rhs)?;
+        Ok(Self::typed(Number::Int(left | right), None, ty))
+    }
+
+    pub fn try_bitxor(&self, rhs: Self) -> Result<Self, ValueErrorKind> {
+        let (left, right, ty) = self.bitwise_operands(&rhs)?;
+        Ok(Self::typed(Number::Int(left ^ right), None, ty))
+    }
+
+    pub fn try_not(&self) -> Result<Self, ValueErrorKind> {
+        if self.unit.is_some() {
+            return Err(ValueErrorKind::BitwiseWithUnit);
+        }
+
+        Ok(Self::typed(Number::Int(!self.try_int()?), None, self.ty))
+    }
+
+    /// Returns the shift amount given by `self`.
+    fn shift_amount(&self) -> Result<u32, ValueErrorKind> {
+        if self.unit.is_some() {
+            return Err(ValueErrorKind::BitwiseWithUnit);
+        }
+
+        match self.try_int()? {
+            amount if amount < 0 => Err(ValueErrorKind::NegativeShift),
+            amount => Ok(amount.min(u32::MAX.into()) as u32),
+        }
+    }
+
+    /// Shifts `self` left by `rhs` bits.
+    ///
+    /// Typed values discard the bits shifted out of their type, untyped values error on overflow.
+    pub fn try_shl(&self, rhs: Self) -> Result<Self, ValueErrorKind> {
+        let amount = rhs.shift_amount()?;
+        let value = self.try_int()?;
+
+        let value = match self.int_type() {
+            Some(ty) if amount >= ty.bits() => 0,
+            Some(ty) => ((ty.to_bits(value)) << amount) as i128,
+            _ if value == 0 => 0,
+            _ => value
+                .checked_shl(amount)
+                .filter(|shifted| shifted >> amount == value)
+                .ok_or(ValueErrorKind::Overflow)?,
+        };
+        Ok(Self::typed(Number::Int(value), self.unit, self.ty))
+    }
+
+    /// Shifts `self` right by `rhs` bits.
+    ///
+    /// The shift is arithmetic for signed and untyped values and logical for unsigned values, unless
+    /// `logical` is set. Logical shifts of negative untyped values operate on their 64 bit two's complement.
+    pub fn try_shr(&self, rhs: Self, logical: bool) -> Result<Self, ValueErrorKind> {
+        let amount = rhs.shift_amount()?;
+        let value = self.try_int()?;
+
+        let ty = match self.int_type() {
+            Some(ty) => ty,
+            _ if logical && value < 0 => DEFAULT_TYPE,
+            _ => IntType::signed(128),
+        };
+
+        let value = if ty.is_signed() && !logical {
+            value >> amount.min(127)
+        } else {
+            ty.to_bits(value).checked_shr(amount).unwrap_or(0) as i128
+        };
+        Ok(Self::typed(Number::Int(value), self.unit, self.ty))
+    }
+}
+
+impl From<Scalar> for Value {
+    fn from(scalar: Scalar) -> Self {
+        Value::Scalar(scalar)
+    }
+}
+
+impl Value {
+    /// Returns the number, the whole value of a bitfield or the XOR of a diff.
+    pub fn scalar(&self) -> Result<&Scalar, ValueErrorKind> {
+        match self {
+            Value::Scalar(scalar) => Ok(scalar),
+            Value::Bitfield(bitfield) => Ok(bitfield.scalar()),
+            Value::Diff(diff) => Ok(diff.xor()),
+            Value::Bytes(_) | Value::Layout(_) | Value::Table(_) => {
+                Err(ValueErrorKind::ExpectedNumber)
+            }
+        }
+    }
+
+    /// Converts the value into a number, taking the whole value of bitfields and the XOR of diffs.
+    pub fn into_scalar(self) -> Result<Scalar, ValueErrorKind> {
+        match self {
+            Value::Scalar(scalar) => Ok(scalar),
+            Value::Bitfield(bitfield) => Ok(bitfield.into_scalar()),
+            Value::Diff(diff) => Ok(diff.xor().clone()),
+            Value::Bytes(_) | Value::Layout(_) | Value::Table(_) => {
+                Err(ValueErrorKind::ExpectedNumber)
+            }
+        }
+    }
+
+    pub fn as_bytes(&self) -> Result<&[u8], ValueErrorKind> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(ValueErrorKind::ExpectedBytes),
+        }
+    }
+
+    pub fn ty(&self) -> Option<Type> {
+        self.scalar().ok().and_then(Scalar::ty)
+    }
+
+    pub fn loss(&self) -> Option<Loss> {
+        self.scalar().ok().and_then(Scalar::loss)
+    }
+
+    /// Applies `op` to the number, erroring if the value is a byte array.
+    pub fn map_scalar(
+        self,
+        op: impl FnOnce(Scalar) -> Result<Scalar, ValueErrorKind>,
+    ) -> Result<Self, ValueErrorKind> {
+        op(self.into_scalar()?).map(Self::from)
+    }
+
+    /// Applies `op` to the numbers of `self` and `rhs`, erroring if either of them is a byte array.
+    pub fn zip_scalar(
+        self,
+        rhs: Self,
+        op: impl FnOnce(Scalar, Scalar) -> Result<Scalar, ValueErrorKind>,
+    ) -> Result<Self, ValueErrorKind> {
+        op(self.into_scalar()?, rhs.into_scalar()?).map(Self::from)
+    }
+
+    /// Adds two numbers or concatenates two byte arrays.
+    pub fn try_add(self, rhs: Self) -> Result<Self, ValueErrorKind> {
+        match (self, rhs) {
+            (Value::Bytes(mut left), Value::Bytes(right)) => {
+                left.extend(right);
+                Ok(Value::Bytes(left))
+            }
+            (left, right) => left.zip_scalar(right, |left, right| Ok(left + right)),
+        }
+    }
+
+    /// Views the value as a character shown as `ty`, decoding byte arrays in the encoding of `ty`.
+    pub fn try_char(self, ty: CharType) -> Result<Self, ValueErrorKind> {
+        match self {
+            Value::Bytes(bytes) => {
+                let c = ty
+                    .decode(&bytes)
+                    .ok_or(ValueErrorKind::InvalidEncoding("character"))?;
+                Ok(Scalar::new(u32::from(c) as i128, None).try_char(ty)?.into())
+            }
+            value => Ok(value.into_scalar()?.try_char(ty)?.into()),
+        }
+    }
+
+    /// Views the value as a file permission mode, parsing symbolic modes like `rw-r--r--`.
+    pub fn try_perms(self) -> Result<Self, ValueErrorKind> {
+        match self {
+            Value::Bytes(text) => {
+                let mode = perms::parse(&text)
+                    .ok_or(ValueErrorKind::InvalidEncoding("permission string"))?;
+                Ok(Scalar::new(mode as i128, None).try_perms()?.into())
+            }
+            value => Ok(value.into_scalar()?.try_perms()?.into()),
+        }
+    }
+
+    /// Decodes the integer value according to `layout`.
+    pub fn try_decode(self, layout: &Layout) -> Result<Self, ValueErrorKind> {
+        Bitfield::try_new(layout.clone(), self.into_scalar()?).map(Value::Bitfield)
+    }
+
+    /// Converts between an integer of type `ty` and its bytes in the byte order `order`.
+    ///
+    /// Byte arrays have to consist of exactly as many bytes as the type, integers are wrapped into it.
+    pub fn try_int_bytes(self, ty: IntType, order: ByteOrder) -> Result<Self, ValueErrorKind> {
+        if !ty.bits().is_multiple_of(8) {
+            return Err(ValueErrorKind::NotByteAligned);
+        }
+        let len = ty.bits() as usize / 8;
+
+        let scalar = match self {
+            Value::Bytes(bytes) if bytes.len() != len => {
+                return Err(ValueErrorKind::WrongByteCount {
+                    expected: len,
+                    found: bytes.len(),
+                })
+            }
+            Value::Bytes(mut bytes) => {
+                if order == ByteOrder::Little {
+                    bytes.reverse();
+                }
+                let bits = bytes.iter().fold(0u128, |acc, b| acc << 8 | *b as u128);
+                return Ok(Scalar::typed(Number::Int(bits as i128), None, Some(ty.into())).into());
+            }
+            value => value.into_scalar()?,
+        };
+
+        if scalar.unit.is_some() {
+            return Err(ValueErrorKind::BitwiseWithUnit);
+        }
+        scalar.try_int()?;
+        let bits = scalar.cast(ty).bits().ok_or(ValueErrorKind::NotAnInteger)?;
+        Ok(Value::Bytes(order.to_bytes(bits, len as u32)))
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Scalar(scalar) => scalar.fmt(f),
+            Value::Bytes(bytes) => write!(f, "{}", bytes::literal(bytes)),
+            Value::Layout(layout) => write!(f, "{}", layout),
+            Value::Bitfield(bitfield) => write!(f, "{}", bitfield),
+            Value::Diff(diff) => write!(f, "{}", diff),
+            Value::Table(table) => table.fmt(f),
+        }
     }
 }
 
-macro_rules! impl_op_for_value {
-    ($trait:ident, $op:ident) => {
-        impl $trait for Value {
+macro_rules! impl_op_for_scalar {
+    ($trait:ident, $op:ident, $wrapping:ident) => {
+        impl $trait for Scalar {
             type Output = Self;
 
             fn $op(self, rhs: Self) -> Self::Output {
-                if self.unit == rhs.unit {
-                    return Self::new(self.value.$op(rhs.value), self.unit);
-                }
+                let ty = common_type(self.ty, rhs.ty);
 
-                let (left, right) = if let (Some(left), Some(right)) = (self.unit, rhs.unit) {
-                    (left, right)
-                } else {
-                    let unit = self.unit.or(rhs.unit);
-                    return Self::new(self.value.$op(rhs.value), unit);
+                // Both operands are converted to the more precise unit
+                let unit = common_unit(self.unit, rhs.unit);
+                let (left, right) = match unit {
+                    Some(unit) => (self.convert_to(unit).value, rhs.convert_to(unit).value),
+                    None => (self.value, rhs.value),
                 };
 
-                let precise = std::cmp::min(left, right);
-                let value = self
-                    .convert_to(precise)
-                    .value
-                    .$op(rhs.convert_to(precise).value);
+                let value = match ty {
+                    Some(Type::Int(_) | Type::Ip(_)) => {
+                        Number::Int(left.trunc().$wrapping(right.trunc()))
+                    }
+                    _ => left.$op(right),
+                };
 
-                Self::new(value, Some(precise))
+                Self::typed(value, unit, ty)
             }
         }
     };
 }
 
-impl_op_for_value!(Sub, sub);
-impl_op_for_value!(Add, add);
+impl_op_for_scalar!(Sub, sub, wrapping_sub);
+impl_op_for_scalar!(Add, add, wrapping_add);
 
-impl Neg for Value {
+impl Neg for Scalar {
     type Output = Self;
 
     fn neg(self) -> Self::Output {
-        Self::new(-self.value, self.unit)
+        let value = match self.ty {
+            Some(Type::Int(_) | Type::Ip(_)) => Number::Int(self.value.trunc().wrapping_neg()),
+            _ => -self.value,
+        };
+        Self::typed(value, self.unit, self.ty)
     }
 }
 
-impl Display for Value {
+impl Display for Scalar {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.unit {
-            Some(unit) => write!(f, "{}{}", self.value, unit),
-            None => write!(f, "{}", self.value),
+        match (self.bits(), self.ty) {
+            (Some(bits), Some(Type::Int(ty))) if !ty.is_signed() => write!(f, "{}", bits)?,
+            (Some(_), Some(Type::Char(_))) => {
+                write!(f, "'{}'", self.char().unwrap().escape_debug())?
+            }
+            (Some(bits), Some(Type::Ip(ty))) => write!(f, "{}", ty.format(bits))?,
+            (Some(bits), Some(Type::Perms)) => write!(f, "{}", perms::symbolic(bits as u32))?,
+            _ => match f.precision() {
+                Some(precision) => write!(f, "{:.*}", precision, self.value)?,
+                None => write!(f, "{}", self.value)?,
+            },
+        }
+        if let Some(unit) = self.unit {
+            write!(f, "{}", unit)?;
+        }
+        if let Some(ty) = self.ty {
+            write!(f, ": {}", ty)?;
         }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::interpreter::{token::Unit, unit_prefix::UnitPrefix};
 
     #[test]
     fn test_value_display() {
-        let value = Value::new(42.0, Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte)));
+        let value = Scalar::new(42.0, Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte)));
         assert_eq!(format!("{}", value), "42kB");
 
-        let value = Value::new(42.0, None);
+        let value = Scalar::new(42.0, None);
         assert_eq!(format!("{}", value), "42");
+
+        let value = Scalar::new(-1i128, None).cast(IntType::unsigned(8));
+        assert_eq!(format!("{}", value), "255: u8");
+
+        let value = Scalar::new(-1i128, None).cast(IntType::unsigned(128));
+        assert_eq!(format!("{}", value), format!("{}: u128", u128::MAX));
+
+        let value = Scalar::new(2.0 / 3.0, Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte)));
+        assert_eq!(format!("{:.2}", value), "0.67kB");
+        assert_eq!(format!("{:.2}", Scalar::new(42i128, None)), "42");
+    }
+
+    #[test]
+    fn test_value_try_fixed() {
+        let q15 = QFormat::new(true, 1, 15).unwrap();
+
+        let value = Scalar::new(0x4000i128, None).try_fixed(q15).unwrap();
+        assert_eq!(value.value(), 0.5);
+        assert_eq!(value.bits(), Some(0x4000));
+        assert_eq!(format!("{}", value), "0.5: q1.15");
+
+        let value = Scalar::new(-0x4000i128, None).try_fixed(q15).unwrap();
+        assert_eq!(value.value(), -0.5);
+        assert_eq!(value.bits(), Some(0xc000));
+
+        let value = Scalar::new(0xc000i128, None).try_fixed(q15).unwrap();
+        assert_eq!(value.value(), -0.5);
+
+        assert_eq!(
+            Scalar::new(0x10000i128, None).try_fixed(q15),
+            Err(ValueErrorKind::TooWide { bits: 16 })
+        );
+        assert_eq!(
+            Scalar::new(-1i128, None)
+                .cast(IntType::unsigned(128))
+                .try_fixed(q15),
+            Err(ValueErrorKind::TooWide { bits: 16 })
+        );
+
+        let value = Scalar::new(0.75, None).try_fixed(q15).unwrap();
+        assert_eq!(value.int(), Some(0x6000));
+        assert_eq!(value.loss(), None);
+        assert_eq!(format!("{}", value), "24576: i16");
+
+        let value = Scalar::new(0.1, None).try_fixed(q15).unwrap();
+        assert_eq!(value.int(), Some(3277));
+        assert_eq!(value.loss(), Some(Loss::Rounded(0.1)));
+        assert_eq!(format!("{}", value.loss().unwrap()), "rounded from 0.1");
+
+        let value = Scalar::new(-1.5, None).try_fixed(q15).unwrap();
+        assert_eq!(value.int(), Some(-0x8000));
+        assert_eq!(value.loss(), Some(Loss::Saturated(-1.5)));
+    }
+
+    #[test]
+    fn test_value_fixed_arithmetic() {
+        let q15 = QFormat::new(true, 1, 15).unwrap();
+        let half = Scalar::new(0x4000i128, None).try_fixed(q15).unwrap();
+
+        let value = half.clone() + half.clone();
+        assert_eq!(value.value(), 1.0 - 2f64.powi(-15));
+        assert_eq!(value.ty(), Some(q15.into()));
+
+        let value = half.try_mul(Scalar::new(3i128, None).cast(IntType::unsigned(8)));
+        assert_eq!(value.unwrap().ty(), Some(q15.into()));
+    }
+
+    #[test]
+    fn test_value_bytes() {
+        let value = Scalar::new(0x12_3456i128, None);
+        assert_eq!(value.byte_width(), Some(3));
+        assert_eq!(value.bytes(ByteOrder::Little), Some(vec![0x56, 0x34, 0x12]));
+
+        let value = Scalar::new(0i128, None);
+        assert_eq!(value.bytes(ByteOrder::Big), Some(vec![0]));
+
+        let value = Scalar::new(-2i128, None);
+        assert_eq!(value.byte_width(), Some(8));
+        assert_eq!(value.bytes(ByteOrder::Big).unwrap()[7], 0xfe);
+
+        let value = Scalar::new(0x1234i128, None).cast(IntType::unsigned(32));
+        assert_eq!(value.bytes(ByteOrder::Little), Some(vec![0x34, 0x12, 0, 0]));
+
+        let value = Scalar::new(1.0, None)
+            .try_reinterpret_float(FloatType::F16)
+            .unwrap();
+        assert_eq!(value.bytes(ByteOrder::Big), Some(vec![0x3c, 0x00]));
+    }
+
+    #[test]
+    fn test_value_try_read_bytes() {
+        let value = Scalar::new(0x1234_5678i128, None);
+        let le = value.try_read_bytes(ByteOrder::Little).unwrap();
+        assert_eq!(le.int(), Some(0x7856_3412));
+        assert_eq!(value.try_read_bytes(ByteOrder::Big), Ok(value));
+
+        let value = Scalar::new(0x12i128, None).cast(IntType::unsigned(16));
+        let le = value.try_read_bytes(ByteOrder::Little).unwrap();
+        assert_eq!(le.int(), Some(0x1200));
+        assert_eq!(le.ty(), Some(IntType::unsigned(16).into()));
+
+        let value = Scalar::new(0x12i128, None).cast(IntType::unsigned(12));
+        assert_eq!(
+            value.try_read_bytes(ByteOrder::Little),
+            Err(ValueErrorKind::NotByteAligned)
+        );
+    }
+
+    #[test]
+    fn test_value_try_add_bytes() {
+        let value = Value::Bytes(vec![0x12]).try_add(Value::Bytes(b"ab".to_vec()));
+        assert_eq!(value, Ok(Value::Bytes(vec![0x12, b'a', b'b'])));
+
+        let value = Value::Bytes(vec![0x12]).try_add(Scalar::new(1i128, None).into());
+        assert_eq!(value, Err(ValueErrorKind::ExpectedNumber));
+    }
+
+    #[test]
+    fn test_value_try_int_bytes() {
+        let value = Value::Bytes(vec![0x12, 0x34]);
+        let le = value
+            .clone()
+            .try_int_bytes(IntType::unsigned(16), ByteOrder::Little);
+        assert_eq!(le.unwrap().scalar().unwrap().int(), Some(0x3412));
+        let be = value
+            .clone()
+            .try_int_bytes(IntType::signed(16), ByteOrder::Big);
+        assert_eq!(be.unwrap().scalar().unwrap().int(), Some(0x1234));
+        assert_eq!(
+            value.try_int_bytes(IntType::unsigned(32), ByteOrder::Big),
+            Err(ValueErrorKind::WrongByteCount {
+                expected: 4,
+                found: 2
+            })
+        );
+
+        let value = Value::from(Scalar::new(-2i128, None));
+        let bytes = value.try_int_bytes(IntType::signed(32), ByteOrder::Little);
+        assert_eq!(bytes, Ok(Value::Bytes(vec![0xfe, 0xff, 0xff, 0xff])));
+
+        let value = Value::from(Scalar::new(1i128, None));
+        assert_eq!(
+            value.try_int_bytes(IntType::unsigned(12), ByteOrder::Big),
+            Err(ValueErrorKind::NotByteAligned)
+        );
+    }
+
+    #[test]
+    fn test_value_display_bytes() {
+        assert_eq!(format!("{}", Value::Bytes(b"hi".to_vec())), "b\"hi\"");
+        assert_eq!(format!("{}", Value::Bytes(vec![0, 0xff])), "x\"00ff\"");
+    }
+
+    #[test]
+    fn test_value_try_char() {
+        let value = Scalar::new(0x20aci128, None)
+            .try_char(CharType::Char)
+            .unwrap();
+        assert_eq!(format!("{}", value), "'€': char");
+        assert_eq!(value.bits(), Some(0x20ac));
+        assert_eq!(value.bytes(ByteOrder::Little), Some(vec![0xac, 0x20, 0, 0]));
+
+        let value = value.try_char(CharType::Utf8).unwrap();
+        assert_eq!(format!("{}", value), "'€': utf8");
+        assert_eq!(value.bytes(ByteOrder::Big), Some(vec![0xe2, 0x82, 0xac]));
+
+        let value = Scalar::new(10i128, None).try_char(CharType::Char).unwrap();
+        assert_eq!(format!("{}", value), "'\\n': char");
+
+        assert_eq!(
+            Scalar::new(0xd800i128, None).try_char(CharType::Char),
+            Err(ValueErrorKind::InvalidCodePoint)
+        );
+
+        let value = Value::Bytes(vec![0xd8, 0x3d, 0xde, 0x00]).try_char(CharType::Utf16);
+        assert_eq!(value.unwrap().scalar().unwrap().char(), Some('😀'));
+        assert_eq!(
+            Value::Bytes(vec![0xff]).try_char(CharType::Utf8),
+            Err(ValueErrorKind::InvalidEncoding("character"))
+        );
     }
 
     #[test]
     fn test_value_convert_to() {
-        let value = Value::new(42.0, Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte)));
+        let value = Scalar::new(42.0, Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte)));
         let new_value = value.convert_to(FullUnit::new(UnitPrefix::Mega, Unit::Byte));
         assert_eq!(new_value.value(), 0.042);
         assert_eq!(
@@ -144,7 +1117,7 @@ mod test {
             Some(FullUnit::new(UnitPrefix::Mega, Unit::Byte))
         );
 
-        let value = Value::new(42.0, None);
+        let value = Scalar::new(42.0, None);
         let new_value = value.convert_to(FullUnit::new(UnitPrefix::Mega, Unit::Byte));
         assert_eq!(new_value.value(), 42.0);
         assert_eq!(
@@ -153,19 +1126,33 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_value_convert_to_auto() {
+        let value = Scalar::new(1536i128, None);
+        assert_eq!(value.clone().convert_to_auto(true).to_string(), "1.5kiB");
+        assert_eq!(value.convert_to_auto(false).to_string(), "1.536kB");
+
+        let value = Scalar::new(3i128, Some(FullUnit::new(UnitPrefix::Mebi, Unit::Bit)));
+        assert_eq!(value.convert_to_auto(true).to_string(), "384kiB");
+        assert_eq!(
+            Scalar::new(0i128, None).convert_to_auto(true).to_string(),
+            "0B"
+        );
+    }
+
     #[test]
     fn test_value_try_mul() {
-        let value = Value::new(42.0, Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte)));
-        let new_value = value.try_mul(Value::new(2.0, None)).unwrap();
+        let value = Scalar::new(42.0, Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte)));
+        let new_value = value.try_mul(Scalar::new(2.0, None)).unwrap();
         assert_eq!(new_value.value(), 84.0);
         assert_eq!(
             new_value.unit(),
             Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte))
         );
 
-        let value = Value::new(42.0, None);
+        let value = Scalar::new(42.0, None);
         let new_value = value
-            .try_mul(Value::new(
+            .try_mul(Scalar::new(
                 2.0,
                 Some(FullUnit::new(UnitPrefix::Mega, Unit::Byte)),
             ))
@@ -179,8 +1166,8 @@ mod test {
 
     #[test]
     fn test_value_try_div() {
-        let value = Value::new(42.0, Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte)));
-        let new_value = value.try_div(Value::new(2.0, None)).unwrap();
+        let value = Scalar::new(42.0, Some(FullUnit::new(UnitPrefix::Kilo, Unit::Byte)));
+        let new_value = value.try_div(Scalar::new(2.0, None)).unwrap();
         assert_eq!(new_value.value(), 21.0);
         assert_eq!(
             new_value.unit(),
@@ -1,58 +1,496 @@
-use clap::Parser;
+use std::{
+    io::{BufRead, IsTerminal, Read, Write},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{ArgGroup, CommandFactory, Parser, ValueEnum};
 use colored::Colorize;
-use miette::{IntoDiagnostic, Result};
+use miette::{IntoDiagnostic, NamedSource, Result, SourceCode, WrapErr};
 
+mod commands;
+mod config;
+mod emit;
 mod format;
+mod header;
+mod helper;
 mod interpreter;
+mod output;
+mod regs;
+mod script;
+mod settings;
+mod stream;
 
-use format::as_bin;
-use interpreter::Interpreter;
-use rustyline::error::ReadlineError;
+use emit::Lang;
+use format::{as_bin, BinFormat, Highlight};
+use helper::ReplHelper;
+use interpreter::{
+    byte_order::ByteOrder,
+    diff::Diff,
+    expr::Stmt,
+    fixed_point::QFormat,
+    float_type::{FloatClass, FloatType},
+    int_type::{mask, IntType},
+    ip_type::{IpType, IpVersion},
+    layout::{Bitfield, Layout},
+    perms,
+    table::Table,
+    token::FullUnit,
+    value::{Scalar, Type, Value},
+    Interpreter, SyntaxError,
+};
+use output::Format;
+use rustyline::{error::ReadlineError, history::DefaultHistory, Config, Editor};
+use settings::{Base, Convention, Settings};
+use stream::Selector;
 
-fn print_stats(num: u64) {
-    let dec = "Decimal".green();
-    println!("{dec}:\t{num}");
+/// Prints the stats of the bit pattern or size `num`, whose decimal rendering is `dec`, e.g. of the
+/// signed value for signed types.
+fn print_stats(num: u128, dec: &str, settings: &Settings) {
+    if settings.base.shows(Base::Dec) {
+        let dec_str = "Decimal".green();
+        println!("{dec_str}:\t{dec}");
+    }
+
+    if settings.base.shows(Base::Hex) {
+        let hex = "Hex".green();
+        println!("{hex}:\t\t0x{num:X}");
+    }
+
+    if settings.base.shows(Base::Oct) {
+        let oct = "Octal".green();
+        println!("{oct}:\t\t0o{num:o}");
+    }
 
+    if settings.base.shows(Base::Bin) {
+        let bin_str = "Binary".green();
+        let bin_format = settings.bin_format();
+        if settings.ruler {
+            println!("\t\t{}", bin_format.ruler(num));
+        }
+        println!("{bin_str}:\t\t{}", as_bin(num, &bin_format));
+    }
+
+    // Sizes beyond 16 EiB have no prefix to show them with
+    let Ok(num) = u64::try_from(num) else {
+        return;
+    };
+    if settings.units.shows(Convention::Si) {
+        let dec_size_str = "Decimal Size".green();
+        let dec_size = format::as_dec_size(num, settings.precision);
+        println!("{dec_size_str}:\t{dec_size}");
+    }
+
+    if settings.units.shows(Convention::Iec) {
+        let bin_size_str = "Binary Size".green();
+        let bin_size = format::as_bin_size(num, settings.precision);
+        println!("{bin_size_str}:\t{bin_size}");
+    }
+}
+
+fn print_float_stats(ty: FloatType, bits: u64) {
     let hex = "Hex".green();
-    println!("{hex}:\t\t0x{num:X}");
+    println!("{hex}:\t\t0x{bits:00$X}", ty.bits() as usize / 4);
 
-    let oct = "Octal".green();
-    println!("{oct}:\t\t0o{num:o}");
+    let bin_str = "Binary".green();
+    let bin = format::as_float_bin(ty, bits);
+    println!("{bin_str}:\t\t{bin}");
+
+    let fields = ty.fields(bits);
+    let sign_str = "Sign".green();
+    let sign = if fields.sign { '-' } else { '+' };
+    println!("{sign_str}:\t\t{sign}");
+
+    let exp_str = "Exponent".green();
+    let exp = match fields.class {
+        FloatClass::Normal => fields.exponent as i64 - ty.bias(),
+        _ => 1 - ty.bias(),
+    };
+    match fields.class {
+        FloatClass::Normal | FloatClass::Subnormal => {
+            println!("{exp_str}:\t{} (2^{exp})", fields.exponent)
+        }
+        _ => println!("{exp_str}:\t{}", fields.exponent),
+    }
+
+    let mantissa = "Mantissa".green();
+    println!("{mantissa}:\t0x{:X}", fields.mantissa);
+
+    let class = "Class".green();
+    println!("{class}:\t\t{}", fields.class);
+}
+
+fn print_fixed_stats(ty: QFormat, raw: u64) {
+    let raw_str = "Raw".green();
+    println!("{raw_str}:\t\t{}", ty.raw_type().wrap(raw.into()));
+
+    let hex = "Hex".green();
+    println!("{hex}:\t\t0x{raw:00$X}", ty.bits().div_ceil(4) as usize);
 
     let bin_str = "Binary".green();
-    let bin = as_bin(num);
+    let bin = format::as_fixed_bin(ty, raw);
     println!("{bin_str}:\t\t{bin}");
 
-    let dec_size_str = "Decimal Size".green();
-    let dec_size = format::as_dec_size(num);
-    println!("{dec_size_str}:\t{dec_size}");
+    let resolution = "Resolution".green();
+    println!("{resolution}:\t{} (2^-{})", ty.resolution(), ty.frac_bits());
+
+    let range = "Range".green();
+    println!("{range}:\t\t{} to {}", ty.min(), ty.max());
+}
+
+fn print_ip_stats(ty: IpType, bits: u128) {
+    let hex = "Hex".green();
+    println!("{hex}:\t\t0x{bits:00$X}", ty.bits() as usize / 4);
+
+    if ty.version() == IpVersion::V4 {
+        let bin_str = "Binary".green();
+        println!("{bin_str}:\t\t{}", format::as_ipv4_bin(bits as u32));
+    }
+
+    if ty.prefix().is_none() {
+        return;
+    }
+    let netmask = "Netmask".green();
+    println!("{netmask}:\t{}", ty.address().format(ty.netmask()));
+
+    let network = "Network".green();
+    println!("{network}:\t{}", ty.format(bits & ty.netmask()));
+
+    if ty.version() == IpVersion::V4 {
+        let broadcast = "Broadcast".green();
+        println!(
+            "{broadcast}:\t{}",
+            ty.address().format(bits | ty.hostmask())
+        );
+    }
+
+    let hosts_str = "Hosts".green();
+    match ty.hosts() {
+        Some(hosts) => println!("{hosts_str}:\t\t{hosts}"),
+        None => println!("{hosts_str}:\t\t2^128"),
+    }
+}
+
+fn print_perms_stats(mode: u32) {
+    let oct = "Octal".green();
+    println!("{oct}:\t\t0o{mode:04o}");
 
-    let bin_size_str = "Binary Size".green();
-    let bin_size = format::as_bin_size(num);
-    println!("{bin_size_str}:\t{bin_size}");
+    println!("{:8}{:7}{:7}{:9}Special", "", "Read", "Write", "Execute");
+    let flag = |set| if set { "yes" } else { "no" };
+    for class in perms::CLASSES {
+        let bits = class.permissions(mode);
+        let special = if class.is_special(mode) {
+            class.special_name()
+        } else {
+            ""
+        };
+        let row = format!(
+            "{:8}{:7}{:7}{:9}{}",
+            class.name().green(),
+            flag(bits & 0o4 != 0),
+            flag(bits & 0o2 != 0),
+            flag(bits & 0o1 != 0),
+            special
+        );
+        println!("{}", row.trim_end());
+    }
+}
+
+fn print_bytes(value: &Scalar) {
+    for order in [ByteOrder::Little, ByteOrder::Big] {
+        if let Some(bytes) = value.bytes(order) {
+            let bytes_str = format!("Bytes ({order})").green();
+            println!("{bytes_str}:\t{}", format::as_hex_bytes(&bytes));
+        }
+    }
+}
+
+fn print_scalar_stats(value: &Scalar, settings: &Settings) {
+    match (value.ty(), value.bits()) {
+        (Some(Type::Float(ty)), Some(bits)) => print_float_stats(ty, bits as u64),
+        (Some(Type::Fixed(ty)), Some(bits)) => print_fixed_stats(ty, bits as u64),
+        (Some(Type::Char(_)), Some(_)) => print_char_stats(value.char().unwrap()),
+        (Some(Type::Ip(ty)), Some(bits)) => print_ip_stats(ty, bits),
+        (Some(Type::Perms), Some(bits)) => print_perms_stats(bits as u32),
+        _ => {
+            if let Some(num) = stats_num(value, settings.word) {
+                print_stats(num, &stats_decimal(value, num, settings.word), settings);
+            }
+        }
+    }
+    if value.unit().is_none() {
+        print_bytes(value);
+    }
+}
+
+fn print_byte_array_stats(bytes: &[u8]) {
+    let len = "Length".green();
+    println!("{len}:\t\t{} B", bytes.len());
+
+    for line in format::as_hexdump(bytes) {
+        println!("{line}");
+    }
+}
+
+fn print_char_stats(c: char) {
+    let code_point = "Code point".green();
+    println!("{code_point}:\tU+{:04X}", c as u32);
+
+    let dec = "Decimal".green();
+    println!("{dec}:\t{}", c as u32);
+
+    let (bin, marks) = format::as_utf8_bin(c);
+    let utf8 = "UTF-8".green();
+    println!("{utf8}:\t\t{bin}");
+    if !marks.is_empty() {
+        println!("\t\t{}", marks.yellow());
+    }
+
+    let (bin, marks) = format::as_utf16_bin(c);
+    let utf16 = "UTF-16".green();
+    println!("{utf16}:\t\t{bin}");
+    if !marks.is_empty() {
+        println!("\t\t{}", marks.yellow());
+    }
+}
+
+fn print_layout(layout: &Layout) {
+    println!("{} {}", "Layout".green(), layout.name());
+    println!("{}:\t\t{}", "Type".green(), layout.int_type());
+
+    let header = ["Field", "Bits", "Values"].map(String::from).to_vec();
+    let rows = layout.fields().iter().map(|field| {
+        let variants = field
+            .variants()
+            .iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect::<Vec<_>>();
+        vec![field.name().to_string(), field.range(), variants.join(", ")]
+    });
+    print_table(std::iter::once(header).chain(rows).collect());
+}
+
+/// Prints `rows` as a table, highlighting the header in the first row.
+fn print_table(rows: Vec<Vec<String>>) {
+    let lines = format::as_table(&rows);
+    if let Some((header, lines)) = lines.split_first() {
+        println!("{}", header.green());
+        for line in lines {
+            println!("{line}");
+        }
+    }
+}
+
+fn print_bitfield(bitfield: &Bitfield, settings: &Settings) {
+    let bits = bitfield.bits();
+    let header = ["Field", "Bits", "Value", "Hex", "Binary"]
+        .map(String::from)
+        .to_vec();
+    let rows = bitfield.layout().fields().iter().map(|field| {
+        let value = field.get(bits);
+        let value_str = match field.variant_name(value) {
+            Some(name) => format!("{name} ({value})"),
+            None => value.to_string(),
+        };
+        let width = field.bits() as usize;
+        vec![
+            field.name().to_string(),
+            field.range(),
+            value_str,
+            format!("0x{value:X}"),
+            format!("{value:0width$b}"),
+        ]
+    });
+    print_table(std::iter::once(header).chain(rows).collect());
+
+    let unmapped = bitfield.layout().unmapped(bits);
+    if unmapped != 0 {
+        let warning = "Warning".yellow();
+        println!("{warning}: bits outside of any field are set: 0x{unmapped:X}");
+    }
+    print_scalar_stats(bitfield.scalar(), settings);
+}
+
+/// Prints the rows of `table` with the value of each row in the bases of the settings.
+fn print_value_table(table: &Table, settings: &Settings) {
+    // The bit patterns of unitless integers, like in the stats
+    let nums = table
+        .rows()
+        .iter()
+        .map(|(_, value)| {
+            let scalar = value.scalar().ok().filter(|scalar| scalar.unit().is_none());
+            scalar.and_then(|scalar| stats_num(scalar, settings.word))
+        })
+        .collect::<Vec<_>>();
+    let bits = nums.iter().flatten().map(|num| 128 - num.leading_zeros());
+    let bin_format = BinFormat {
+        width: Some(bits.max().unwrap_or(0).max(settings.width.unwrap_or(0))),
+        highlight: Highlight::None,
+        ..settings.bin_format()
+    };
+
+    let columns = [
+        (Base::Hex, "Hex"),
+        (Base::Oct, "Octal"),
+        (Base::Bin, "Binary"),
+    ];
+    let columns = columns
+        .into_iter()
+        .filter(|(base, _)| settings.base.shows(*base))
+        .collect::<Vec<_>>();
+    let mut header = vec![table.var().to_string(), "Value".to_string()];
+    header.extend(columns.iter().map(|(_, name)| name.to_string()));
+
+    let rows = table.rows().iter().zip(&nums).map(|((index, value), num)| {
+        let value = match settings.precision {
+            Some(precision) => format!("{value:.precision$}"),
+            None => value.to_string(),
+        };
+        let mut row = vec![index.to_string(), value];
+        row.extend(columns.iter().map(|(base, _)| match (base, num) {
+            (_, None) => String::new(),
+            (Base::Hex, Some(num)) => format!("0x{num:X}"),
+            (Base::Oct, Some(num)) => format!("0o{num:o}"),
+            (_, Some(num)) => as_bin(*num, &bin_format),
+        }));
+        row
+    });
+    print_table(std::iter::once(header).chain(rows).collect());
+}
+
+fn print_diff(diff: &Diff, settings: &Settings) {
+    let bin_format = BinFormat {
+        width: Some(diff.bits().max(settings.width.unwrap_or(0))),
+        ..settings.bin_format()
+    };
+    let hex_width = diff.bits().div_ceil(4) as usize;
+    let changed = diff.changed_bits();
+    if settings.ruler {
+        let indent = " ".repeat(hex_width + 4);
+        println!("\t\t{indent}{}", bin_format.ruler(changed));
+    }
+    for (name, bits) in [("Left", diff.left_bits()), ("Right", diff.right_bits())] {
+        let bin = bin_format.format_marked(bits, changed);
+        println!("{}:\t\t0x{bits:0hex_width$X}  {bin}", name.green());
+    }
+
+    let indices = diff
+        .changed()
+        .into_iter()
+        .map(|index| match diff.field(index) {
+            Some(field) => format!("{index} ({})", field.name()),
+            None => index.to_string(),
+        });
+    let indices = indices.collect::<Vec<_>>();
+    if !indices.is_empty() {
+        println!("{}:\t{}", "Changed".green(), indices.join(", "));
+    }
+
+    let fields = diff.changed_fields().into_iter().map(|field| {
+        let value = |bits| {
+            let value = field.get(bits);
+            field
+                .variant_name(value)
+                .map_or(value.to_string(), str::to_string)
+        };
+        let (left, right) = (value(diff.left_bits()), value(diff.right_bits()));
+        format!("{}: {left} -> {right}", field.name())
+    });
+    let fields = fields.collect::<Vec<_>>();
+    if !fields.is_empty() {
+        println!("{}:\t\t{}", "Fields".green(), fields.join(", "));
+    }
+
+    println!("{}:\t{}", "Popcount".green(), diff.count());
+}
+
+/// Returns the number to print the stats of, i.e. the bit pattern of unitless integers or the size in
+/// bytes of values with a unit. Untyped integers are truncated to the `word` size if there is one, and
+/// to their byte width otherwise, like in their bytes.
+fn stats_num(value: &Scalar, word: Option<u32>) -> Option<u128> {
+    if value.unit().is_none() {
+        let bits = match (value.ty(), word) {
+            (None, Some(word)) => mask(word),
+            (None, None) => mask(value.byte_width()? * 8),
+            _ => u128::MAX,
+        };
+        return value.bits().map(|num| num & bits);
+    }
+
+    let bytes = value.clone().convert_to(FullUnit::byte()).value();
+    (bytes.fract() == 0.0 && (0.0..=u128::MAX as f64).contains(&bytes)).then_some(bytes as u128)
+}
+
+/// Returns the decimal rendering of `value` in its stats of `num`, i.e. the signed value of signed types
+/// and of untyped integers not truncated to a `word` size, and `num` otherwise.
+fn stats_decimal(value: &Scalar, num: u128, word: Option<u32>) -> String {
+    let signed = match value.ty() {
+        _ if value.unit().is_some() => false,
+        Some(ty) => ty.int_type().is_some_and(IntType::is_signed),
+        None => word.is_none(),
+    };
+    match value.int() {
+        Some(int) if signed => int.to_string(),
+        _ => num.to_string(),
+    }
+}
+
+/// The maximum number of lines kept in the REPL history.
+const HISTORY_SIZE: usize = 1000;
+
+/// Returns the path of the REPL history file in the XDG data directory, i.e. `$XDG_DATA_HOME` or
+/// `~/.local/share`.
+fn history_path() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join("bitweiser").join("history"))
 }
 
 struct Repl {
     interpreter: Interpreter,
+    /// The last successfully evaluated expression and its value.
+    last: Option<(String, Value)>,
+    settings: Settings,
 }
 
 impl Repl {
-    fn new() -> Self {
+    fn new(settings: Settings) -> Self {
         Self {
             interpreter: Interpreter::new(),
+            last: None,
+            settings,
         }
     }
 
-    fn run(&self) -> Result<()> {
-        let mut rl = rustyline::DefaultEditor::new().into_diagnostic()?;
+    fn run(&mut self) -> Result<()> {
+        let config = Config::builder()
+            .max_history_size(HISTORY_SIZE)
+            .and_then(|builder| builder.history_ignore_dups(true))
+            .into_diagnostic()?
+            .build();
+        let mut rl = Editor::<ReplHelper, DefaultHistory>::with_config(config).into_diagnostic()?;
+        rl.set_helper(Some(ReplHelper::new()));
+        let history = history_path();
+        if let Some(path) = &history {
+            // There is no history yet on the first run
+            let _ = rl.load_history(path);
+        }
         println!("Welcome to the REPL! Type :h or :help for help.");
         loop {
+            if let Some(helper) = rl.helper_mut() {
+                helper.set_interpreter(&self.interpreter);
+            }
             let readline = rl.readline(">> ");
 
             match readline {
                 Ok(line) => {
                     rl.add_history_entry(line.as_str()).into_diagnostic()?;
-                    self.eval_line(&line);
+                    if self.eval_line(&line).is_break() {
+                        break;
+                    }
                 }
                 Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
                     break;
@@ -64,48 +502,564 @@ impl Repl {
             }
         }
 
+        if let Some(path) = &history {
+            let saved = path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .map_err(ReadlineError::from)
+                .and_then(|_| rl.save_history(path));
+            if let Err(e) = saved {
+                eprintln!("Failed to save the history to {}: {e}", path.display());
+            }
+        }
         Ok(())
     }
 
-    fn eval_line(&self, line: &str) {
-        match line {
-            ":q" | ":quit" => std::process::exit(0),
-            ":h" | ":help" => {
-                println!("Commands:");
-                println!("  :q | :quit - Quit the REPL");
-                println!("  :h | :help - Display this help message");
+    /// Evaluates a line of the REPL, which is a command if it starts with `:`, breaking if it asks to quit.
+    fn eval_line(&mut self, line: &str) -> ControlFlow<()> {
+        if line.starts_with(':') {
+            return commands::run(self, line);
+        }
+        if let Err(e) = self.eval_expr(line) {
+            self.report_error(line, e, line.to_string());
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Removes all variables, layouts and the last result.
+    fn clear(&mut self) {
+        self.interpreter = Interpreter::new();
+        self.last = None;
+    }
+
+    /// Formats `value` with the precision of the settings.
+    fn display(&self, value: &Value) -> String {
+        match self.settings.precision {
+            Some(precision) => format!("{value:.precision$}"),
+            None => value.to_string(),
+        }
+    }
+
+    /// Loads the register layouts described by the file at `path`, returning their number.
+    fn load_regs(&mut self, path: &Path) -> Result<usize> {
+        let layouts = regs::load(path)
+            .wrap_err_with(|| format!("Failed to load registers from {}", path.display()))?;
+        let count = layouts.len();
+        for layout in layouts {
+            self.interpreter.define_layout(layout);
+        }
+        Ok(count)
+    }
+
+    /// Imports the constants of the C header at `path`, warning about skipped ones and returning the
+    /// number of imported ones.
+    fn include(&mut self, path: &Path) -> Result<usize> {
+        let (count, skipped) = header::include(path, &mut self.interpreter)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to include {}", path.display()))?;
+        for warning in skipped {
+            eprintln!("{:?}", miette::Report::new(warning));
+        }
+        Ok(count)
+    }
+
+    /// Writes the layouts and variables of the session to `path` as a script that recreates them,
+    /// returning the number of definitions.
+    ///
+    /// The definitions are written in the order they were made, including redefined values that later
+    /// definitions depend on, so replaying the script reproduces the session exactly.
+    fn save(&self, path: &Path) -> Result<usize> {
+        std::fs::write(path, script::session(&self.interpreter))
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to save the session to {}", path.display()))?;
+        Ok(self.interpreter.history().len())
+    }
+
+    /// Replays the statements of the script at `path`, like one written by [`Repl::save`], without
+    /// printing their results. Stops at the first failing statement and returns the number of
+    /// statements evaluated.
+    fn load(&mut self, path: &Path) -> Result<usize> {
+        let text = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        self.replay(&path.display().to_string(), &text)
+    }
+
+    /// Evaluates the statements of the script `text`, named `name` in diagnostics, without printing their
+    /// results. Stops at the first failing statement and returns the number of statements evaluated.
+    fn replay(&mut self, name: &str, text: &str) -> Result<usize> {
+        let statements = script::statements(text);
+        for range in &statements {
+            if let Err(e) = self.interpreter.interpret(&text[range.clone()]) {
+                let src = NamedSource::new(name, text.to_string());
+                return Err(miette::Report::new(e.offset(range.start)).with_source_code(src));
+            }
+        }
+        Ok(statements.len())
+    }
+
+    /// Parses the arguments of `:emit`, i.e. the language and optional variable names, and emits the
+    /// corresponding constants.
+    fn emit_command(&self, args: &str) -> Result<String> {
+        let mut args = args.split_whitespace();
+        let lang = args.next().unwrap_or_default();
+        let lang = Lang::from_str(lang, true).map_err(|_| {
+            miette::miette!("Unknown language '{lang}', expected one of c, rust, python or verilog")
+        })?;
+        self.emit(lang, &args.collect::<Vec<_>>())
+    }
+
+    /// Emits the variables `names` as constants in `lang`, or the last result if no names are given.
+    fn emit(&self, lang: Lang, names: &[&str]) -> Result<String> {
+        let mut constants = Vec::new();
+        if names.is_empty() {
+            let (expr, value) = self
+                .last
+                .as_ref()
+                .ok_or_else(|| miette::miette!("There is no result to emit yet"))?;
+            // Results of a single variable are named after it and documented with its definition
+            let (name, expr) = match self.interpreter.definition(expr.trim()) {
+                Some(definition) => (expr.trim(), definition.expr()),
+                None => ("RESULT", expr.as_str()),
+            };
+            constants.push(emit::emit(lang, name, expr, value)?);
+        }
+        for name in names {
+            let definition = self
+                .interpreter
+                .definition(name)
+                .ok_or_else(|| miette::miette!("Unknown variable '{name}'"))?;
+            constants.push(emit::emit(
+                lang,
+                name,
+                definition.expr(),
+                definition.value(),
+            )?);
+        }
+        Ok(constants.join("\n\n"))
+    }
+
+    /// Runs the statements of the script `text`, named `name` in diagnostics, writing the result of each
+    /// to `out` or only checking that they are well-formed if `check` is set.
+    ///
+    /// Stops at the first failing statement unless `keep_going` is set, in which case all errors are
+    /// reported. Returns the status of the first failing statement.
+    fn run_script(
+        &mut self,
+        out: &mut impl Write,
+        name: &str,
+        text: &str,
+        keep_going: bool,
+        check: bool,
+    ) -> Status {
+        let src = NamedSource::new(name, text.to_string());
+        let mut status = Status::Success;
+        let mut failed = 0;
+        for range in script::statements(text) {
+            let statement = &text[range.clone()];
+            let result = match check {
+                true => Interpreter::check(statement).map(|_| None),
+                false => self.evaluate(statement).map(Some),
+            };
+            let written = match result {
+                Ok(None) => Ok(()),
+                Ok(Some(value)) if self.settings.format != Format::Human => {
+                    let value = output::format_value(self.settings.format, statement, &value);
+                    writeln!(out, "{value}")
+                }
+                Ok(Some(Value::Layout(layout))) => writeln!(out, "{layout}"),
+                Ok(Some(value)) => writeln!(out, "{statement} = {}", self.display(&value))
+                    .and_then(|_| match value.loss() {
+                        Some(loss) => writeln!(out, "{}: {loss}", "Warning".yellow()),
+                        None => Ok(()),
+                    }),
+                Err(e) => {
+                    if status == Status::Success {
+                        status = Status::of(&e);
+                    }
+                    failed += 1;
+                    self.report_error(statement, e.offset(range.start), src.clone());
+                    if !keep_going {
+                        break;
+                    }
+                    Ok(())
+                }
+            };
+            // Stop once stdout is closed, e.g. by `head`
+            if written.is_err() {
+                break;
+            }
+        }
+        if keep_going && failed > 0 && self.settings.format == Format::Human {
+            let statements = if failed == 1 {
+                "statement"
+            } else {
+                "statements"
+            };
+            eprintln!("{failed} {statements} of {name} failed");
+        }
+        status
+    }
+
+    /// Copies the lines of `input` to stdout, replacing the numbers selected by `selector` with the value of
+    /// `map` for them, which is parsed once and evaluated with the number as `x`. With `human`, numbers
+    /// are sizes in bytes, which may have a unit prefix like `1.5G`. Without `map`, the numbers are
+    /// replaced by their plain number, e.g. sizes by their number of bytes.
+    ///
+    /// Numbers that fail to evaluate are left as they are and reported on stderr along with their line.
+    /// Returns the status of the first failure.
+    fn map_stream(
+        &mut self,
+        input: impl BufRead,
+        map: Option<&str>,
+        selector: &Selector,
+        human: bool,
+    ) -> Result<Status> {
+        let expr = map.unwrap_or("x");
+        let parsed = match Interpreter::parse(expr) {
+            Ok(Stmt::Expr(parsed)) => parsed,
+            Ok(_) => {
+                eprintln!("--map expects an expression, not a declaration");
+                return Ok(Status::SyntaxError);
+            }
+            Err(e) => {
+                self.report_error(expr, e, expr.to_string());
+                return Ok(Status::SyntaxError);
+            }
+        };
+
+        let mut out = std::io::stdout().lock();
+        let mut status = Status::Success;
+        for (i, line) in input.lines().enumerate() {
+            let mut line = line.into_diagnostic().wrap_err("Failed to read stdin")?;
+            // Replacing from the back keeps the locations of the numbers before valid
+            for range in selector.select(&line).into_iter().rev() {
+                let text = &line[range.clone()];
+                let Some(number) = stream::parse_number(text, human) else {
+                    continue;
+                };
+                self.interpreter
+                    .define_value("x".into(), text.to_string(), number.into());
+                match self.interpreter.evaluate(&parsed) {
+                    Ok(value) => {
+                        let value = match (map, value) {
+                            (None, Value::Scalar(scalar)) => stream::unitless(&scalar).into(),
+                            (_, value) => value,
+                        };
+                        line.replace_range(range, &self.display(&value));
+                    }
+                    Err(e) => {
+                        if status == Status::Success {
+                            status = Status::of(&e);
+                        }
+                        let error = output::format_error(Format::Plain, expr, &e);
+                        eprintln!("line {}: {error}", i + 1);
+                    }
+                }
+            }
+            // Stop once stdout is closed, e.g. by `head`
+            if writeln!(out, "{line}").is_err() {
+                break;
+            }
+        }
+        Ok(status)
+    }
+
+    /// Evaluates `expr`, remembering it as the last result on success.
+    fn evaluate(&mut self, expr: &str) -> Result<Value, SyntaxError> {
+        let value = self.interpreter.interpret(expr)?;
+        self.last = Some((expr.to_string(), value.clone()));
+        Ok(value)
+    }
+
+    /// Reports `error` of evaluating `expr`, which is located in `source`, on stderr. Machine-readable
+    /// JSON errors are printed to stdout along with the results instead.
+    fn report_error(&self, expr: &str, error: SyntaxError, source: impl SourceCode + 'static) {
+        match self.settings.format {
+            Format::Human => eprintln!("{:?}", miette::Report::new(error).with_source_code(source)),
+            Format::Json => println!(
+                "{}",
+                output::format_error(self.settings.format, expr, &error)
+            ),
+            _ => eprintln!(
+                "{}",
+                output::format_error(self.settings.format, expr, &error)
+            ),
+        }
+    }
+
+    /// Evaluates `expr` and prints its result, leaving reporting errors to the caller.
+    fn eval_expr(&mut self, expr: &str) -> Result<(), SyntaxError> {
+        let value = self.evaluate(expr)?;
+        let expr = script::without_comment(expr);
+        match value {
+            _ if self.settings.format != Format::Human => {
+                println!(
+                    "{}",
+                    output::format_value(self.settings.format, expr, &value)
+                )
+            }
+            Value::Layout(layout) => print_layout(&layout),
+            Value::Table(table) => print_value_table(&table, &self.settings),
+            value => {
+                println!("{expr} = {}", self.display(&value));
+                if let Some(loss) = value.loss() {
+                    println!("{}: {loss}", "Warning".yellow());
+                }
+                match &value {
+                    Value::Scalar(scalar) => print_scalar_stats(scalar, &self.settings),
+                    Value::Bytes(bytes) => print_byte_array_stats(bytes),
+                    Value::Bitfield(bitfield) => print_bitfield(bitfield, &self.settings),
+                    Value::Diff(diff) => print_diff(diff, &self.settings),
+                    Value::Layout(_) | Value::Table(_) => {
+                        unreachable!("Layouts and tables are printed above")
+                    }
+                }
             }
-            _ => self.eval_expr(line),
         }
+        Ok(())
     }
+}
 
-    fn eval_expr(&self, expr: &str) {
-        match self
-            .interpreter
-            .interpret(expr)
-            .map_err(miette::Report::new)
-            .map_err(|e| e.with_source_code(expr.to_string()))
-        {
-            Ok(value) => println!("{expr} = {value}"),
-            Err(e) => eprintln!("{e:?}"),
+/// The exit status of the command line interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success = 0,
+    /// An expression or statement is malformed.
+    SyntaxError = 1,
+    /// A well-formed expression or statement could not be evaluated.
+    EvalError = 3,
+    /// A file could not be read or loaded.
+    IoError = 4,
+}
+
+impl Status {
+    fn of(error: &SyntaxError) -> Self {
+        match error.is_evaluation_error() {
+            true => Status::EvalError,
+            false => Status::SyntaxError,
         }
     }
 }
 
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
+#[command(
+    after_help = "Exit status: 0 on success, 1 on syntax errors, 2 on invalid arguments, \
+3 on evaluation errors and 4 if a file could not be read or loaded."
+)]
+#[command(group = ArgGroup::new("stream").args(["map", "from_human"]).multiple(true))]
 struct Args {
     expr: Option<String>,
+
+    /// Load register layouts from an SVD or TOML file, may be given multiple times
+    #[arg(long, value_name = "FILE")]
+    load_regs: Vec<PathBuf>,
+
+    /// Import the constants of a C header, may be given multiple times
+    #[arg(long, value_name = "FILE")]
+    include: Vec<PathBuf>,
+
+    /// Run the statements of a script file, separated by newlines or `;`
+    #[arg(short, long, value_name = "FILE", conflicts_with = "expr")]
+    file: Option<PathBuf>,
+
+    /// Report all failing statements of a script instead of stopping at the first one
+    #[arg(long)]
+    keep_going: bool,
+
+    /// Only check that the expression or the statements of the script are well-formed, without
+    /// evaluating them
+    #[arg(long, conflicts_with = "emit")]
+    check: bool,
+
+    /// The format to print results and errors in [default: human]
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Print results and errors as JSON, short for `--format json`
+    #[arg(long, conflicts_with = "format")]
+    json: bool,
+
+    /// Read the settings and prelude from this file instead of ~/.config/bitweiser/config.toml
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Set an option like with :set in the REPL, overriding the config file, e.g. `--set base=hex`
+    #[arg(short, long = "set", value_name = "OPTION=VALUE", value_parser = parse_setting)]
+    set: Vec<(String, String)>,
+
+    /// Print the result of the expression as a constant in the given language instead of its stats
+    #[arg(long, value_name = "LANG", requires = "expr")]
+    emit: Option<Lang>,
+
+    /// Copy the lines of stdin to stdout, replacing their numbers with the value of this expression for
+    /// them, which are named `x`, e.g. `x as auto-iec`
+    #[arg(long, value_name = "EXPR", conflicts_with_all = ["expr", "file", "check"])]
+    map: Option<String>,
+
+    /// Replace the numbers of these whitespace separated fields, counting from 1 [default: 1]
+    #[arg(long, value_name = "N", value_delimiter = ',', requires = "stream")]
+    #[arg(value_parser = clap::value_parser!(u32).range(1..))]
+    field: Vec<u32>,
+
+    /// Replace the numbers matching this regex, or its first capture group, instead of fields
+    #[arg(
+        long = "match",
+        value_name = "REGEX",
+        requires = "stream",
+        conflicts_with = "field"
+    )]
+    pattern: Option<regex::Regex>,
+
+    /// Read sizes with unit prefixes like `1.5G` or `4Ki` as numbers of bytes, which are printed as they
+    /// are unless --map converts them
+    #[arg(long, conflicts_with_all = ["expr", "file", "check"])]
+    from_human: bool,
 }
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let args = Args::parse();
+    match run(args) {
+        Ok(status) => status.into(),
+        Err(e) => {
+            eprintln!("{e:?}");
+            Status::IoError.into()
+        }
+    }
+}
+
+/// Parses an `OPTION=VALUE` argument of `--set`.
+fn parse_setting(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(option, value)| (option.trim().to_string(), value.trim().to_string()))
+        .ok_or_else(|| format!("expected OPTION=VALUE, found '{arg}'"))
+}
+
+/// Returns the settings and prelude of the config file, overridden by the command line arguments.
+fn settings(args: &Args) -> Result<(Settings, Option<String>)> {
+    let mut settings = Settings::default();
+    let prelude = match (&args.config, config::default_path()) {
+        (Some(path), _) => config::load(path, &mut settings)?,
+        (None, Some(path)) if path.exists() => config::load(&path, &mut settings)?,
+        (None, _) => None,
+    };
+
+    if args.json {
+        settings.format = Format::Json;
+    }
+    if let Some(format) = args.format {
+        settings.format = format;
+    }
+    for (option, value) in &args.set {
+        if let Err(e) = settings.set(option, value) {
+            Args::command()
+                .error(clap::error::ErrorKind::InvalidValue, e)
+                .exit();
+        }
+    }
+    Ok((settings, prelude))
+}
+
+/// Runs the command line interface, failing if a file could not be read or loaded.
+fn run(args: Args) -> Result<Status> {
+    let (settings, prelude) = settings(&args)?;
+    let mut repl = Repl::new(settings);
+    if let Some(prelude) = prelude {
+        repl.replay("prelude", &prelude)
+            .wrap_err("Failed to evaluate the prelude of the config file")?;
+    }
+    for path in &args.load_regs {
+        repl.load_regs(path)?;
+    }
+    for path in &args.include {
+        repl.include(path)?;
+    }
+
+    if let Some(expr) = &args.expr {
+        let result = match args.check {
+            true => Interpreter::check(expr),
+            false if args.emit.is_some() => repl.evaluate(expr).map(|_| ()),
+            false => repl.eval_expr(expr),
+        };
+        if let Err(e) = result {
+            let status = Status::of(&e);
+            repl.report_error(expr, e, expr.clone());
+            return Ok(status);
+        }
 
-    let repl = Repl::new();
-    match args.expr {
-        Some(expr) => repl.eval_expr(&expr),
-        None => repl.run()?,
+        // Results that cannot be emitted as a constant, like layouts, count as evaluation errors
+        if let Some(lang) = args.emit {
+            match repl.emit(lang, &[]) {
+                Ok(code) => println!("{code}"),
+                Err(e) => {
+                    eprintln!("{e:?}");
+                    return Ok(Status::EvalError);
+                }
+            }
+        }
+        return Ok(Status::Success);
     }
 
-    Ok(())
+    if args.map.is_some() || args.from_human {
+        let selector = match args.pattern {
+            Some(regex) => Selector::Matches(regex),
+            None if args.field.is_empty() => Selector::Fields(vec![1]),
+            None => Selector::Fields(args.field.iter().map(|&i| i as usize).collect()),
+        };
+        let stdin = std::io::stdin().lock();
+        return repl.map_stream(stdin, args.map.as_deref(), &selector, args.from_human);
+    }
+
+    if let Some(path) = &args.file {
+        let text = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        let name = path.display().to_string();
+        let mut out = std::io::stdout().lock();
+        return Ok(repl.run_script(&mut out, &name, &text, args.keep_going, args.check));
+    }
+    if !std::io::stdin().is_terminal() {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .into_diagnostic()
+            .wrap_err("Failed to read stdin")?;
+        let mut out = std::io::stdout().lock();
+        return Ok(repl.run_script(&mut out, "<stdin>", &text, args.keep_going, args.check));
+    }
+    if args.check {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "--check requires an expression, a script file or statements on stdin",
+            )
+            .exit();
+    }
+    repl.run()?;
+    Ok(Status::Success)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_run_script() {
+        let mut repl = Repl::new(Settings::default());
+        let mut out = Vec::new();
+        let text = "let a = 1 # one\na + 1; a << 4 # shifted\n# done";
+        let status = repl.run_script(&mut out, "test", text, false, false);
+        assert_eq!(status, Status::Success);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "let a = 1 = 1\na + 1 = 2\na << 4 = 16\n"
+        );
+    }
 }
@@ -0,0 +1,309 @@
+use clap::ValueEnum;
+use miette::Diagnostic;
+use serde_json::{json, Map, Value as Json};
+
+use crate::{
+    format,
+    interpreter::{
+        table::Table,
+        token::FullUnit,
+        value::{Scalar, Value},
+    },
+};
+
+/// The format results and errors are printed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The value along with its stats, highlighted for humans
+    #[default]
+    Human,
+    /// Only the value
+    Plain,
+    /// One JSON object per result, with the value, its unit, type and radix renderings or the error
+    Json,
+    /// One line per result, with the expression, value, unit, type, hex, octal and binary rendering
+    /// separated by tabs, where the value of numbers is the bare number
+    Tsv,
+}
+
+/// Returns the number, unit, type and hex, octal and binary renderings of a scalar, where available.
+///
+/// The radix renderings are those of the bit pattern of typed values, of non-negative untyped integers
+/// and, like in the stats, of the size in bytes of values with a unit if it is a whole number.
+fn scalar_fields(scalar: &Scalar) -> Map<String, Json> {
+    let number = match scalar.int() {
+        Some(int) => i64::try_from(int)
+            .map(Json::from)
+            .or_else(|_| u64::try_from(int).map(Json::from))
+            .unwrap_or_else(|_| int.to_string().into()),
+        None => serde_json::Number::from_f64(scalar.value()).map_or(Json::Null, Json::Number),
+    };
+    let bits = match scalar.ty() {
+        _ if scalar.unit().is_some() => {
+            let bytes = scalar.clone().convert_to(FullUnit::byte()).value();
+            let whole = bytes.fract() == 0.0 && (0.0..=u128::MAX as f64).contains(&bytes);
+            whole.then_some(bytes as u128)
+        }
+        Some(_) => scalar.bits(),
+        None => scalar.int().filter(|int| *int >= 0).map(|int| int as u128),
+    };
+
+    let mut fields = Map::new();
+    fields.insert("number".into(), number);
+    fields.insert("unit".into(), json!(scalar.unit().map(|u| u.to_string())));
+    fields.insert("type".into(), json!(scalar.ty().map(|ty| ty.to_string())));
+    fields.insert("hex".into(), json!(bits.map(|b| format!("0x{b:X}"))));
+    fields.insert("oct".into(), json!(bits.map(|b| format!("0o{b:o}"))));
+    fields.insert("bin".into(), json!(bits.map(|b| format!("0b{b:b}"))));
+    fields
+}
+
+/// Returns the JSON object describing the result `value` of `expr`.
+fn value_json(expr: &str, value: &Value) -> Json {
+    let mut object = Map::new();
+    object.insert("expr".into(), expr.into());
+    object.insert("value".into(), value.to_string().into());
+    match value {
+        Value::Scalar(scalar) => object.extend(scalar_fields(scalar)),
+        Value::Bytes(bytes) => {
+            let hex = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+            object.insert("type".into(), "bytes".into());
+            object.insert("length".into(), bytes.len().into());
+            object.insert("hex".into(), hex.into());
+        }
+        Value::Layout(layout) => {
+            object.insert("type".into(), "layout".into());
+            object.insert("name".into(), layout.name().into());
+        }
+        Value::Bitfield(bitfield) => {
+            object.extend(scalar_fields(bitfield.scalar()));
+            object.insert("type".into(), bitfield.layout().name().into());
+            let bits = bitfield.bits();
+            let fields = bitfield
+                .layout()
+                .fields()
+                .iter()
+                .map(|field| {
+                    let value = field.get(bits);
+                    let value = u64::try_from(value).map_or(value.to_string().into(), Json::from);
+                    (field.name().to_string(), value)
+                })
+                .collect::<Map<_, _>>();
+            object.insert("fields".into(), fields.into());
+        }
+        Value::Diff(diff) => {
+            object.extend(scalar_fields(diff.xor()));
+            object.insert("changed".into(), diff.changed().into());
+            object.insert("popcount".into(), diff.count().into());
+            if diff.layout().is_some() {
+                let fields = diff
+                    .changed_fields()
+                    .iter()
+                    .map(|field| field.name())
+                    .collect::<Vec<_>>();
+                object.insert("changed_fields".into(), fields.into());
+            }
+        }
+        Value::Table(table) => {
+            object.insert("type".into(), "table".into());
+            let rows = table
+                .rows()
+                .iter()
+                .map(|(index, value)| row_json(table, index, value));
+            object.insert("rows".into(), rows.collect::<Vec<_>>().into());
+        }
+    }
+    if let Some(loss) = value.loss() {
+        object.insert("warning".into(), loss.to_string().into());
+    }
+    object.into()
+}
+
+/// Returns the JSON object describing a row of `table`, i.e. the `index` bound to the variable of the
+/// table and the `value` computed from it.
+fn row_json(table: &Table, index: &Scalar, value: &Value) -> Json {
+    let index = index.int().map_or(Json::Null, |index| {
+        i64::try_from(index).map_or(index.to_string().into(), Json::from)
+    });
+    let mut row = Map::new();
+    row.insert(table.var().into(), index);
+    if let Json::Object(fields) = value_json("", value) {
+        row.extend(fields.into_iter().filter(|(key, _)| key != "expr"));
+    }
+    row.into()
+}
+
+/// Returns the rows of `table` as CSV, with a header and the index, value and radix renderings of each
+/// row.
+fn table_csv(table: &Table) -> String {
+    let header = [table.var(), "value", "hex", "oct", "bin"].map(String::from);
+    let rows = table.rows().iter().map(|(index, value)| {
+        let json = row_json(table, index, value);
+        header
+            .iter()
+            .map(|key| match &json[key] {
+                Json::Null => String::new(),
+                Json::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect()
+    });
+    let rows = std::iter::once(header.to_vec())
+        .chain(rows)
+        .collect::<Vec<_>>();
+    format::as_csv(&rows).join("\n")
+}
+
+/// Formats the result `value` of `expr`, which must not be printed in the human format.
+///
+/// Tables are printed as CSV in the plain and TSV formats.
+pub fn format_value(format: Format, expr: &str, value: &Value) -> String {
+    if let (Format::Human | Format::Plain | Format::Tsv, Value::Table(table)) = (format, value) {
+        return table_csv(table);
+    }
+    match format {
+        Format::Human | Format::Plain => value.to_string(),
+        Format::Json => value_json(expr, value).to_string(),
+        Format::Tsv => {
+            let mut json = value_json(expr, value);
+            // The unit and type have their own columns
+            if let Some(number) = json.get("number").cloned() {
+                json["value"] = number;
+            }
+            let columns = ["expr", "value", "unit", "type", "hex", "oct", "bin"].map(|key| {
+                match &json[key] {
+                    Json::Null => String::new(),
+                    Json::String(s) => tsv_escape(s),
+                    other => other.to_string(),
+                }
+            });
+            columns.join("\t")
+        }
+    }
+}
+
+/// Escapes the characters of `s` that would break up a TSV line.
+fn tsv_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+/// Returns the message of `error`, preferring the more telling one of its cause, e.g. "Unknown variable"
+/// for a syntax error.
+fn message(error: &dyn Diagnostic) -> String {
+    error
+        .source()
+        .map_or(error.to_string(), ToString::to_string)
+}
+
+/// Formats the error that evaluating `expr` failed with, including the offsets of its labels.
+///
+/// JSON errors are objects like results, while the other formats use a line like `error: 4..7: message`.
+pub fn format_error(format: Format, expr: &str, error: &dyn Diagnostic) -> String {
+    let spans = error
+        .labels()
+        .into_iter()
+        .flatten()
+        .map(|label| (label.offset(), label.len()))
+        .collect::<Vec<_>>();
+    match format {
+        Format::Json => {
+            let spans = spans
+                .iter()
+                .map(|(offset, len)| json!({ "offset": offset, "length": len }))
+                .collect::<Vec<_>>();
+            json!({ "expr": expr, "error": { "message": message(error), "spans": spans } })
+                .to_string()
+        }
+        _ => match spans.first() {
+            Some((offset, len)) => {
+                format!("error: {offset}..{}: {}", offset + len, message(error))
+            }
+            None => format!("error: {}", message(error)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn format_expr(format: Format, expr: &str) -> String {
+        match Interpreter::new().interpret(expr) {
+            Ok(value) => format_value(format, expr, &value),
+            Err(e) => format_error(format, expr, &e),
+        }
+    }
+
+    #[test]
+    fn test_format_json() {
+        assert_eq!(
+            format_expr(Format::Json, "0x1f as u8"),
+            r#"{"expr":"0x1f as u8","value":"31: u8","number":31,"unit":null,"type":"u8","hex":"0x1F","oct":"0o37","bin":"0b11111"}"#
+        );
+        assert_eq!(
+            format_expr(Format::Json, "4 KiB"),
+            r#"{"expr":"4 KiB","value":"4kiB","number":4,"unit":"kiB","type":null,"hex":"0x1000","oct":"0o10000","bin":"0b1000000000000"}"#
+        );
+        assert_eq!(
+            format_expr(Format::Json, "[1, 2]"),
+            r#"{"expr":"[1, 2]","value":"x\"0102\"","type":"bytes","length":2,"hex":"0102"}"#
+        );
+        assert_eq!(
+            format_expr(Format::Json, "1 + foo"),
+            r#"{"expr":"1 + foo","error":{"message":"Unknown variable","spans":[{"offset":4,"length":3}]}}"#
+        );
+    }
+
+    #[test]
+    fn test_format_json_diff() {
+        assert_eq!(
+            format_expr(Format::Json, "diff(0x1234, 0x1634)"),
+            r#"{"expr":"diff(0x1234, 0x1634)","value":"1 bit differs: 10","number":1024,"unit":null,"type":"u16","hex":"0x400","oct":"0o2000","bin":"0b10000000000","changed":[10],"popcount":1}"#
+        );
+    }
+
+    #[test]
+    fn test_format_tsv() {
+        assert_eq!(
+            format_expr(Format::Tsv, "-1 as i8"),
+            "-1 as i8\t-1\t\ti8\t0xFF\t0o377\t0b11111111"
+        );
+        assert_eq!(
+            format_expr(Format::Tsv, "4 KiB"),
+            "4 KiB\t4\tkiB\t\t0x1000\t0o10000\t0b1000000000000"
+        );
+        assert_eq!(format_expr(Format::Tsv, "1.5 b"), "1.5 b\t1.5\tb\t\t\t\t");
+        assert_eq!(
+            format_expr(Format::Tsv, "[1, 2]"),
+            "[1, 2]\tx\"0102\"\t\tbytes\t0102\t\t"
+        );
+        assert_eq!(
+            format_expr(Format::Tsv, "1 +"),
+            "error: 3..3: Expected expression, found 'EOF'"
+        );
+    }
+
+    #[test]
+    fn test_format_table() {
+        assert_eq!(
+            format_expr(Format::Tsv, "table(i, 0..3, 1 << i)"),
+            "i,value,hex,oct,bin\n0,1,0x1,0o1,0b1\n1,2,0x2,0o2,0b10\n2,4,0x4,0o4,0b100"
+        );
+        assert_eq!(
+            format_expr(Format::Json, "table(i, 1..=2, i * 1 KiB)"),
+            r#"{"expr":"table(i, 1..=2, i * 1 KiB)","value":"[1kiB, 2kiB]","type":"table","rows":[{"i":1,"value":"1kiB","number":1,"unit":"kiB","type":null,"hex":"0x400","oct":"0o2000","bin":"0b10000000000"},{"i":2,"value":"2kiB","number":2,"unit":"kiB","type":null,"hex":"0x800","oct":"0o4000","bin":"0b100000000000"}]}"#
+        );
+        assert_eq!(
+            format_expr(Format::Plain, "table(i, 0..0, i)"),
+            "i,value,hex,oct,bin"
+        );
+    }
+
+    #[test]
+    fn test_format_plain() {
+        assert_eq!(format_expr(Format::Plain, "1 << 4"), "16");
+    }
+}
@@ -0,0 +1,502 @@
+use std::{path::Path, sync::Arc};
+
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use roxmltree::{Document, Node};
+use thiserror::Error;
+use toml_edit::{ImDocument, Item};
+
+use crate::interpreter::{
+    layout::{Field, Layout},
+    value::ValueErrorKind,
+};
+
+/// The size of SVD registers in bits, if neither the register nor its peripheral or device specify one.
+const DEFAULT_SVD_SIZE: u32 = 32;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum RegsError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Unknown register description format, expected an .svd, .xml or .toml file")]
+    UnknownFormat,
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    #[error("Invalid TOML in {}", src.name())]
+    Toml {
+        #[source_code]
+        src: NamedSource<Arc<str>>,
+        #[label("{message}")]
+        span: Option<SourceSpan>,
+        message: String,
+    },
+    #[error("Missing <{element}> in <{parent}>")]
+    MissingElement {
+        element: &'static str,
+        parent: String,
+    },
+    #[error("Invalid number '{0}'")]
+    InvalidNumber(String),
+    #[error("Invalid bit range '{0}'")]
+    InvalidBitRange(String),
+    #[error("Unknown peripheral '{0}' to derive from")]
+    UnknownPeripheral(String),
+    #[error("Invalid value of '{0}'")]
+    InvalidEntry(String),
+    #[error("Invalid field {field}")]
+    Field {
+        field: String,
+        #[source]
+        kind: ValueErrorKind,
+    },
+    #[error("Invalid register {register}")]
+    Register {
+        register: String,
+        #[source]
+        kind: ValueErrorKind,
+    },
+}
+
+/// Loads the register layouts described by the CMSIS-SVD (`.svd` or `.xml`) or TOML (`.toml`) file at
+/// `path`.
+pub fn load(path: &Path) -> Result<Vec<Layout>, RegsError> {
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    if !matches!(extension, Some("svd" | "xml" | "toml")) {
+        return Err(RegsError::UnknownFormat);
+    }
+    let text = std::fs::read_to_string(path)?;
+    match extension {
+        Some("toml") => parse_toml(&path.display().to_string(), &text),
+        _ => parse_svd(&text),
+    }
+}
+
+/// Parses a CMSIS-SVD device description, naming the layout of each register `PERIPHERAL_REGISTER`,
+/// e.g. `RCC_CR`.
+fn parse_svd(svd: &str) -> Result<Vec<Layout>, RegsError> {
+    let doc = Document::parse(svd)?;
+    let device = doc.root_element();
+    let device_size = svd_size(device, DEFAULT_SVD_SIZE)?;
+    let peripherals = children(device, "peripherals")
+        .flat_map(|peripherals| children(peripherals, "peripheral"))
+        .collect::<Vec<_>>();
+
+    let mut layouts = Vec::new();
+    for peripheral in &peripherals {
+        let name = text(*peripheral, "name")?;
+        let size = svd_size(*peripheral, device_size)?;
+
+        // Derived peripherals share the registers of their base, unless they list their own
+        let registers = match (
+            child(*peripheral, "registers"),
+            peripheral.attribute("derivedFrom"),
+        ) {
+            (Some(registers), _) => registers,
+            (None, Some(base)) => peripherals
+                .iter()
+                .find(|p| child_text(**p, "name") == Some(base))
+                .and_then(|p| child(*p, "registers"))
+                .ok_or_else(|| RegsError::UnknownPeripheral(base.into()))?,
+            (None, None) => continue,
+        };
+
+        // Registers may also be grouped into clusters, whose names are not part of the layout names
+        for register in registers
+            .descendants()
+            .filter(|node| node.has_tag_name("register"))
+        {
+            layouts.extend(svd_register(register, name, size)?);
+        }
+    }
+    Ok(layouts)
+}
+
+/// Returns the layouts of an SVD register, one for each element of register arrays.
+fn svd_register(register: Node, peripheral: &str, size: u32) -> Result<Vec<Layout>, RegsError> {
+    let name = text(register, "name")?;
+    let size = svd_size(register, size)?;
+    let fields = children(register, "fields")
+        .flat_map(|fields| children(fields, "field"))
+        .map(svd_field)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    svd_names(register, name)?
+        .into_iter()
+        .map(|name| {
+            let name = format!("{peripheral}_{name}");
+            let mut layout = Layout::with_size(name.clone(), size);
+            for field in &fields {
+                layout
+                    .add_field(field.clone())
+                    .map_err(|kind| RegsError::Register {
+                        register: name.clone(),
+                        kind,
+                    })?;
+            }
+            Ok(layout)
+        })
+        .collect()
+}
+
+/// Returns the names of the registers described by `register`, which is an array of registers named
+/// after their indices if it has a `<dim>`, e.g. `CCR%s` or `CCR[%s]`.
+fn svd_names(register: Node, name: &str) -> Result<Vec<String>, RegsError> {
+    let Some(dim) = child_text(register, "dim") else {
+        return Ok(vec![name.into()]);
+    };
+
+    let indices = match child_text(register, "dimIndex") {
+        Some(indices) => match indices.split_once('-') {
+            Some((first, last)) => (number(first)?..=number(last)?)
+                .map(|i| i.to_string())
+                .collect(),
+            None => indices.split(',').map(|i| i.trim().to_string()).collect(),
+        },
+        None => (0..number(dim)?).map(|i| i.to_string()).collect::<Vec<_>>(),
+    };
+    Ok(indices
+        .iter()
+        .map(|i| name.replace("[%s]", i).replace("%s", i))
+        .collect())
+}
+
+/// Parses an SVD field, whose bits are given either by `<bitOffset>` and `<bitWidth>`, by `<lsb>` and
+/// `<msb>` or by a `<bitRange>` like `[7:3]`.
+fn svd_field(field: Node) -> Result<Field, RegsError> {
+    let name = text(field, "name")?;
+    let (hi, lo) = if let Some(range) = child_text(field, "bitRange") {
+        range
+            .strip_prefix('[')
+            .and_then(|range| range.strip_suffix(']'))
+            .and_then(|range| range.split_once(':'))
+            .ok_or_else(|| RegsError::InvalidBitRange(range.into()))
+            .and_then(|(msb, lsb)| Ok((bit_index(msb)?, bit_index(lsb)?)))?
+    } else if let (Some(lsb), Some(msb)) = (child_text(field, "lsb"), child_text(field, "msb")) {
+        (bit_index(msb)?, bit_index(lsb)?)
+    } else {
+        let (lo, width) = (
+            bit_index(text(field, "bitOffset")?)?,
+            text(field, "bitWidth")?,
+        );
+        let hi = u32::try_from(number(width)?)
+            .ok()
+            .filter(|width| (1..=128 - lo).contains(width))
+            .map(|width| lo + width - 1)
+            .ok_or_else(|| RegsError::InvalidBitRange(width.into()))?;
+        (hi, lo)
+    };
+
+    let mut result = Field::new(name.into(), hi, lo);
+    let values =
+        children(field, "enumeratedValues").flat_map(|values| children(values, "enumeratedValue"));
+    for value in values {
+        // Default values name all other values and values with "don't care" bits like `#1x0` name
+        // several, neither of which fit a single variant
+        let Some(Ok(bits)) = child_text(value, "value").map(number) else {
+            continue;
+        };
+        result
+            .add_variant(text(value, "name")?.into(), bits)
+            .map_err(|kind| RegsError::Field {
+                field: name.into(),
+                kind,
+            })?;
+    }
+    Ok(result)
+}
+
+/// Returns the `<size>` of an SVD element, or `default` if it inherits the size of its parent.
+fn svd_size(node: Node, default: u32) -> Result<u32, RegsError> {
+    match child_text(node, "size") {
+        Some(size) => {
+            u32::try_from(number(size)?).map_err(|_| RegsError::InvalidNumber(size.into()))
+        }
+        None => Ok(default),
+    }
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.has_tag_name(tag))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, tag).next()
+}
+
+fn child_text<'a>(node: Node<'a, '_>, tag: &'static str) -> Option<&'a str> {
+    child(node, tag)
+        .and_then(|child| child.text())
+        .map(str::trim)
+}
+
+/// Returns the text of the required child element `tag` of `node`.
+fn text<'a>(node: Node<'a, '_>, tag: &'static str) -> Result<&'a str, RegsError> {
+    child_text(node, tag).ok_or_else(|| RegsError::MissingElement {
+        element: tag,
+        parent: node.tag_name().name().into(),
+    })
+}
+
+/// Parses a number, which is decimal, hexadecimal with a `0x` prefix or binary with a `#` prefix.
+fn number(text: &str) -> Result<u128, RegsError> {
+    let text = text.trim();
+    let parsed = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u128::from_str_radix(hex, 16)
+    } else if let Some(bin) = text.strip_prefix('#') {
+        u128::from_str_radix(bin, 2)
+    } else {
+        text.parse()
+    };
+    parsed.map_err(|_| RegsError::InvalidNumber(text.into()))
+}
+
+fn bit_index(text: &str) -> Result<u32, RegsError> {
+    match number(text)? {
+        index @ 0..128 => Ok(index as u32),
+        _ => Err(RegsError::InvalidBitRange(text.into())),
+    }
+}
+
+/// Parses a TOML register description with a table per register, e.g.
+///
+/// ```toml
+/// [RCC_CFGR]
+/// size = 32
+/// fields.PLLON = 24
+/// fields.HPRE = "7..4"
+/// fields.SW = { bits = "1..0", values = { HSI = 0, HSE = 1, PLL = 2 } }
+/// ```
+///
+/// The size is optional, registers without one are as wide as their fields. Syntax errors are reported
+/// with their location in the file `name`.
+fn parse_toml(name: &str, text: &str) -> Result<Vec<Layout>, RegsError> {
+    let registers = ImDocument::parse(text).map_err(|e| RegsError::Toml {
+        src: NamedSource::new(name, Arc::from(text)),
+        span: e.span().map(SourceSpan::from),
+        message: e.message().trim().to_string(),
+    })?;
+    registers
+        .iter()
+        .map(|(name, register)| {
+            let name = name.to_string();
+            let register = register
+                .as_table_like()
+                .ok_or_else(|| RegsError::InvalidEntry(name.clone()))?;
+            let size = match register.get("size") {
+                Some(size) => size
+                    .as_integer()
+                    .and_then(|size| u32::try_from(size).ok())
+                    .ok_or_else(|| RegsError::InvalidEntry(format!("{name}.size")))?,
+                None => 0,
+            };
+
+            let mut layout = Layout::with_size(name.clone(), size);
+            let fields = match register.get("fields") {
+                Some(fields) => fields
+                    .as_table_like()
+                    .ok_or_else(|| RegsError::InvalidEntry(format!("{name}.fields")))?
+                    .iter()
+                    .collect(),
+                None => Vec::new(),
+            };
+            for (field, spec) in fields {
+                let field = toml_field(&format!("{name}.{field}"), field, spec)?;
+                layout
+                    .add_field(field)
+                    .map_err(|kind| RegsError::Register {
+                        register: name.clone(),
+                        kind,
+                    })?;
+            }
+            Ok(layout)
+        })
+        .collect()
+}
+
+/// Parses a TOML field, which is a bit index, a bit range like `"7..4"` or a table of its `bits` and
+/// named `values`.
+fn toml_field(key: &str, name: &str, spec: &Item) -> Result<Field, RegsError> {
+    let invalid = |key: String| RegsError::InvalidEntry(key);
+    let (bits, values) = match spec.as_table_like() {
+        Some(spec) => (
+            spec.get("bits")
+                .ok_or_else(|| invalid(format!("{key}.bits")))?,
+            spec.get("values"),
+        ),
+        None => (spec, None),
+    };
+
+    let (hi, lo) = match (bits.as_integer(), bits.as_str()) {
+        (Some(bit), _) => {
+            let bit = bit_index(&bit.to_string())?;
+            (bit, bit)
+        }
+        (_, Some(range)) => match range.split_once("..") {
+            Some((first, last)) => (bit_index(first)?, bit_index(last)?),
+            None => (bit_index(range)?, bit_index(range)?),
+        },
+        _ => return Err(invalid(key.into())),
+    };
+
+    let mut field = Field::new(name.into(), hi, lo);
+    let values = match values {
+        Some(values) => values
+            .as_table_like()
+            .ok_or_else(|| invalid(format!("{key}.values")))?
+            .iter()
+            .collect(),
+        None => Vec::new(),
+    };
+    for (variant, value) in values {
+        let value = value
+            .as_integer()
+            .and_then(|value| u128::try_from(value).ok())
+            .ok_or_else(|| invalid(format!("{key}.values.{variant}")))?;
+        field
+            .add_variant(variant.to_string(), value)
+            .map_err(|kind| RegsError::Field {
+                field: key.into(),
+                kind,
+            })?;
+    }
+    Ok(field)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interpreter::int_type::IntType;
+
+    const SVD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<device>
+  <name>STM32F103</name>
+  <size>32</size>
+  <peripherals>
+    <peripheral>
+      <name>RCC</name>
+      <registers>
+        <register>
+          <name>CR</name>
+          <fields>
+            <field><name>HSION</name><bitOffset>0</bitOffset><bitWidth>1</bitWidth></field>
+            <field><name>HSIRDY</name><lsb>1</lsb><msb>1</msb></field>
+            <field><name>HSITRIM</name><bitRange>[7:3]</bitRange></field>
+            <field>
+              <name>PLLON</name><bitOffset>24</bitOffset><bitWidth>1</bitWidth>
+              <enumeratedValues>
+                <enumeratedValue><name>Off</name><value>0</value></enumeratedValue>
+                <enumeratedValue><name>On</name><value>#1</value></enumeratedValue>
+                <enumeratedValue><name>Other</name><isDefault>true</isDefault></enumeratedValue>
+              </enumeratedValues>
+            </field>
+          </fields>
+        </register>
+      </registers>
+    </peripheral>
+    <peripheral derivedFrom="RCC">
+      <name>RCC2</name>
+    </peripheral>
+    <peripheral>
+      <name>TIM1</name>
+      <registers>
+        <register>
+          <dim>2</dim>
+          <name>CCR%s</name>
+          <size>0x10</size>
+        </register>
+      </registers>
+    </peripheral>
+  </peripherals>
+</device>"#;
+
+    #[test]
+    fn test_parse_svd() {
+        let layouts = parse_svd(SVD).unwrap();
+        let names = layouts.iter().map(Layout::name).collect::<Vec<_>>();
+        assert_eq!(names, ["RCC_CR", "RCC2_CR", "TIM1_CCR0", "TIM1_CCR1"]);
+
+        let cr = &layouts[0];
+        assert_eq!(cr.int_type(), IntType::unsigned(32));
+        assert_eq!(
+            cr.to_string(),
+            "layout RCC_CR { HSION: 0, HSIRDY: 1, HSITRIM: 7..3, PLLON: 24 { Off: 0, On: 1 } }"
+        );
+        assert_eq!(layouts[2].int_type(), IntType::unsigned(16));
+    }
+
+    #[test]
+    fn test_parse_svd_errors() {
+        let svd = "<device><peripherals><peripheral><registers><register/></registers></peripheral></peripherals></device>";
+        assert!(matches!(
+            parse_svd(svd),
+            Err(RegsError::MissingElement {
+                element: "name",
+                ..
+            })
+        ));
+
+        let svd = SVD.replace("<bitRange>[7:3]</bitRange>", "<bitRange>[7:3</bitRange>");
+        assert!(matches!(
+            parse_svd(&svd),
+            Err(RegsError::InvalidBitRange(_))
+        ));
+
+        let svd = SVD.replace("<name>HSIRDY</name>", "<name>HSION</name>");
+        assert!(matches!(
+            parse_svd(&svd),
+            Err(RegsError::Register {
+                kind: ValueErrorKind::DuplicateField,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let layouts = parse_toml(
+            "regs.toml",
+            r#"
+            [RCC_CFGR]
+            size = 32
+            fields.SW = { bits = "1..0", values = { HSI = 0, HSE = 1, PLL = 2 } }
+            fields.HPRE = "7..4"
+            fields.PLLON = 24
+
+            [CTRL]
+            fields.EN = 0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            layouts[0].to_string(),
+            "layout RCC_CFGR { SW: 1..0 { HSI: 0, HSE: 1, PLL: 2 }, HPRE: 7..4, PLLON: 24 }"
+        );
+        assert_eq!(layouts[0].int_type(), IntType::unsigned(32));
+        assert_eq!(layouts[1].int_type(), IntType::unsigned(8));
+
+        assert!(matches!(
+            parse_toml("regs.toml", "[R]\nfields.A = 1.5"),
+            Err(RegsError::InvalidEntry(key)) if key == "R.A"
+        ));
+        assert!(matches!(
+            parse_toml(
+                "regs.toml",
+                "[R]\nfields.A = { bits = 0, values = { X = 2 } }"
+            ),
+            Err(RegsError::Field {
+                kind: ValueErrorKind::TooWide { bits: 1 },
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_toml("regs.toml", "[R]\nfields.A = \"200..0\""),
+            Err(RegsError::InvalidBitRange(_))
+        ));
+        assert!(matches!(
+            parse_toml("regs.toml", "[R]\nfields.A = "),
+            Err(RegsError::Toml { span: Some(span), .. }) if span.offset() == 15
+        ));
+    }
+}
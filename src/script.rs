@@ -0,0 +1,155 @@
+use std::{fmt::Write, ops::Range};
+
+use crate::interpreter::{environment::Entry, Interpreter};
+
+/// Returns the locations of the statements of the script `text`, which are separated by newlines or `;`.
+///
+/// Separators inside of brackets, braces, literals and comments don't end a statement, so layout
+/// declarations may span multiple lines. A comment after a statement is not part of it, and statements
+/// consisting only of whitespace and comments, like a shebang line, are skipped.
+pub fn statements(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    // The start of the comment ending the current statement
+    let mut comment = None;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => {
+                if depth == 0 {
+                    comment.get_or_insert(i);
+                }
+                let len = bytes[i..].iter().position(|c| *c == b'\n');
+                i += len.unwrap_or(bytes.len() - i);
+                continue;
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1 + literal_len(quote, &bytes[i + 1..]);
+                continue;
+            }
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            b'\n' | b';' if depth == 0 => {
+                push_statement(text, start..comment.take().unwrap_or(i), &mut statements);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    push_statement(text, start..comment.unwrap_or(text.len()), &mut statements);
+    statements
+}
+
+/// Returns `text` without the comment after its last statement, e.g. to echo a statement without it.
+pub fn without_comment(text: &str) -> &str {
+    statements(text)
+        .last()
+        .map_or("", |range| &text[..range.end])
+}
+
+/// Adds the statement at `range` of `text` to `statements` with surrounding whitespace trimmed, unless
+/// it is empty.
+fn push_statement(text: &str, range: Range<usize>, statements: &mut Vec<Range<usize>>) {
+    let statement = &text[range.clone()];
+    let trimmed = statement.trim();
+    if trimmed.is_empty() {
+        return;
+    }
+    let start = range.start + statement.len() - statement.trim_start().len();
+    statements.push(start..start + trimmed.len());
+}
+
+/// Returns the length of the rest of a string or character literal after its opening `quote`, including
+/// the closing one.
+fn literal_len(quote: u8, rest: &[u8]) -> usize {
+    let mut escaped = false;
+    for (i, c) in rest.iter().enumerate() {
+        match c {
+            b'\n' => return i,
+            c if *c == quote && !escaped => return i + 1,
+            _ => escaped = *c == b'\\' && !escaped,
+        }
+    }
+    rest.len()
+}
+
+/// Returns a script recreating the layouts and variables of `interpreter`, with its definitions in the
+/// order they were made.
+pub fn session(interpreter: &Interpreter) -> String {
+    let mut script = String::from("# bitweiser session\n");
+    for entry in interpreter.history() {
+        // Writing to a String can't fail
+        let _ = match entry {
+            Entry::Layout(layout) => writeln!(script, "{layout}"),
+            Entry::Value { name, expr } => writeln!(script, "let {name} = {expr}"),
+        };
+    }
+    script
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn split(text: &str) -> Vec<&str> {
+        statements(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(
+            split("#!/usr/bin/env -S bitweiser -f\n1 + 2; 3\n\n  # note\n4 # four\n"),
+            ["1 + 2", "3", "4"]
+        );
+        assert_eq!(
+            split("let a = 1 # c; d\nlet b = \"#\" # e"),
+            ["let a = 1", "let b = \"#\""]
+        );
+        assert_eq!(without_comment("let a = 1 # c"), "let a = 1");
+        assert_eq!(split("\"a;b\" ; ';'"), ["\"a;b\"", "';'"]);
+        assert_eq!(
+            split("layout ctrl {\n  en: 0,\n  mode: 2..1,\n}\nctrl(1)"),
+            ["layout ctrl {\n  en: 0,\n  mode: 2..1,\n}", "ctrl(1)"]
+        );
+        assert!(split("").is_empty());
+    }
+
+    #[test]
+    fn test_session() {
+        let mut interpreter = Interpreter::new();
+        for statement in [
+            "layout CTRL { EN: 0, MODE: 2..1 }",
+            "let a = 1",
+            "let c = a + 1",
+            "let a = 5",
+            "let a = a * 2",
+            "let r = CTRL { EN: 1, MODE: a & 3 }",
+            "let i = 1",
+            "let i = 2",
+        ] {
+            interpreter.interpret(statement).unwrap();
+        }
+        let script = session(&interpreter);
+        assert_eq!(
+            script.lines().filter(|line| *line == "let i = 2").count(),
+            1
+        );
+        assert!(!script.contains("let i = 1"));
+
+        let mut loaded = Interpreter::new();
+        for range in statements(&script) {
+            loaded.interpret(&script[range]).unwrap();
+        }
+        for (name, definition) in interpreter.definitions() {
+            let value = loaded.definition(name).map(|definition| definition.value());
+            assert_eq!(value, Some(definition.value()), "{name}");
+        }
+        assert_eq!(loaded.definition("c").unwrap().value().to_string(), "2");
+    }
+}
@@ -0,0 +1,379 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::RangeInclusive,
+    str::FromStr,
+};
+
+use clap::ValueEnum;
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::{
+    format::{BinFormat, Highlight, Separator},
+    output::Format,
+};
+
+/// The bases the stats of integers are printed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Base {
+    /// Decimal, hex, octal and binary
+    #[default]
+    All,
+    Dec,
+    Hex,
+    Oct,
+    Bin,
+}
+
+impl Base {
+    /// Returns whether the stats include the rendering in `base`.
+    pub fn shows(self, base: Base) -> bool {
+        self == Base::All || self == base
+    }
+}
+
+/// The unit convention sizes are printed in, i.e. decimal (SI) or binary (IEC) prefixes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Convention {
+    /// Both decimal and binary prefixes
+    #[default]
+    Both,
+    /// Decimal prefixes, e.g. kB
+    Si,
+    /// Binary prefixes, e.g. KiB
+    Iec,
+}
+
+impl Convention {
+    /// Returns whether sizes are printed in the `convention`.
+    pub fn shows(self, convention: Convention) -> bool {
+        self == Convention::Both || self == convention
+    }
+}
+
+/// When to colour the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Colour {
+    /// If the output is a terminal and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl Colour {
+    /// Makes all coloured output follow the setting.
+    fn apply(self) {
+        match self {
+            Colour::Auto => colored::control::unset_override(),
+            Colour::Always => colored::control::set_override(true),
+            Colour::Never => colored::control::set_override(false),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Error, Diagnostic)]
+pub enum SettingError {
+    #[error("Unknown option '{0}'")]
+    #[diagnostic(help("The options are {}", OPTIONS.map(|(name, _)| name).join(", ")))]
+    UnknownOption(String),
+    #[error("Invalid value '{value}' for {option}, expected {expected}")]
+    InvalidValue {
+        option: &'static str,
+        value: String,
+        expected: String,
+    },
+}
+
+/// The options of the session and their descriptions.
+pub const OPTIONS: [(&str, &str); 11] = [
+    ("base", "The bases the stats of integers are printed in"),
+    (
+        "width",
+        "The number of bits binary renderings are padded to, or auto for whole bytes",
+    ),
+    ("group", "The number of bits between the separators of binary renderings"),
+    ("separator", "The separator between the groups of bits of binary renderings"),
+    ("ruler", "Whether to show the bit indices above binary renderings"),
+    ("highlight", "The bits of binary renderings to colour"),
+    ("units", "The unit convention sizes are printed in"),
+    (
+        "precision",
+        "The number of fractional digits of results and sizes, or auto for as many as needed",
+    ),
+    (
+        "word",
+        "The number of bits untyped integers are shown with in the stats, e.g. negative ones, or auto",
+    ),
+    ("colour", "When to colour the output"),
+    ("format", "The format results and errors are printed in"),
+];
+
+/// The settings of a session, which control how results are printed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub base: Base,
+    /// The number of bits binary renderings are padded to, instead of a multiple of 8.
+    pub width: Option<u32>,
+    /// The number of bits between the separators of binary renderings.
+    pub group: u32,
+    pub separator: Separator,
+    /// Whether to show the bit indices above binary renderings.
+    pub ruler: bool,
+    pub highlight: Highlight,
+    pub units: Convention,
+    /// The number of fractional digits of non-integral results and sizes.
+    pub precision: Option<usize>,
+    /// The word size untyped integers are shown with in the stats, so that negative ones have a bit
+    /// pattern.
+    pub word: Option<u32>,
+    pub colour: Colour,
+    pub format: Format,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            base: Base::default(),
+            width: None,
+            group: 8,
+            separator: Separator::default(),
+            ruler: false,
+            highlight: Highlight::default(),
+            units: Convention::default(),
+            precision: None,
+            word: None,
+            colour: Colour::default(),
+            format: Format::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Sets `option` to `value`, which is a name like `hex` or a number or `auto` for numeric options.
+    ///
+    /// Setting the colour takes effect immediately for all output.
+    pub fn set(&mut self, option: &str, value: &str) -> Result<(), SettingError> {
+        match option {
+            "base" => self.base = parse_enum("base", value)?,
+            "width" => self.width = parse_auto("width", value, 1..=128)?,
+            "group" => self.group = parse_group(value)?,
+            "separator" => self.separator = parse_enum("separator", value)?,
+            "ruler" => self.ruler = parse_switch("ruler", value)?,
+            "highlight" => self.highlight = parse_enum("highlight", value)?,
+            "units" => self.units = parse_enum("units", value)?,
+            "precision" => self.precision = parse_auto("precision", value, 0..=17)?,
+            "word" => self.word = parse_auto("word", value, 1..=128)?,
+            "colour" => {
+                self.colour = parse_enum("colour", value)?;
+                self.colour.apply();
+            }
+            "format" => self.format = parse_enum("format", value)?,
+            _ => return Err(SettingError::UnknownOption(option.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Returns the current value of `option`.
+    pub fn get(&self, option: &str) -> Result<String, SettingError> {
+        let value = match option {
+            "base" => enum_name(self.base),
+            "width" => auto_name(self.width),
+            "group" => self.group.to_string(),
+            "separator" => enum_name(self.separator),
+            "ruler" => switch_name(self.ruler).to_string(),
+            "highlight" => enum_name(self.highlight),
+            "units" => enum_name(self.units),
+            "precision" => auto_name(self.precision),
+            "word" => auto_name(self.word),
+            "colour" => enum_name(self.colour),
+            "format" => enum_name(self.format),
+            _ => return Err(SettingError::UnknownOption(option.to_string())),
+        };
+        Ok(value)
+    }
+
+    /// Returns the values `option` can be set to.
+    pub fn values(option: &str) -> Result<String, SettingError> {
+        let values = match option {
+            "base" => enum_names::<Base>(),
+            "width" | "word" => "auto or 1 to 128".to_string(),
+            "group" => "4, 8 or 16".to_string(),
+            "separator" => enum_names::<Separator>(),
+            "ruler" => "on or off".to_string(),
+            "highlight" => enum_names::<Highlight>(),
+            "units" => enum_names::<Convention>(),
+            "precision" => "auto or 0 to 17".to_string(),
+            "colour" => enum_names::<Colour>(),
+            "format" => enum_names::<Format>(),
+            _ => return Err(SettingError::UnknownOption(option.to_string())),
+        };
+        Ok(values)
+    }
+}
+
+impl Settings {
+    /// Returns the layout of the binary renderings of the stats.
+    pub(crate) fn bin_format(&self) -> BinFormat {
+        BinFormat {
+            width: self.width.or(self.word),
+            group: self.group as usize,
+            separator: self.separator,
+            highlight: self.highlight,
+        }
+    }
+}
+
+impl Display for Settings {
+    /// Formats the settings as one `option = value` line per option.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let lines = OPTIONS.map(|(option, _)| format!("{option} = {}", self.get(option).unwrap()));
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+fn enum_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map_or(String::new(), |value| value.get_name().to_string())
+}
+
+fn enum_names<T: ValueEnum>() -> String {
+    let names = T::value_variants()
+        .iter()
+        .map(|value| enum_name(value.clone()))
+        .collect::<Vec<_>>();
+    names.join(", ")
+}
+
+fn parse_enum<T: ValueEnum>(option: &'static str, value: &str) -> Result<T, SettingError> {
+    T::from_str(value, true).map_err(|_| SettingError::InvalidValue {
+        option,
+        value: value.to_string(),
+        expected: format!("one of {}", enum_names::<T>()),
+    })
+}
+
+fn auto_name<T: Display>(value: Option<T>) -> String {
+    value.map_or("auto".to_string(), |value| value.to_string())
+}
+
+fn parse_group(value: &str) -> Result<u32, SettingError> {
+    match value {
+        "4" | "8" | "16" => Ok(value.parse().unwrap()),
+        _ => Err(SettingError::InvalidValue {
+            option: "group",
+            value: value.to_string(),
+            expected: "4, 8 or 16".to_string(),
+        }),
+    }
+}
+
+fn switch_name(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Parses `on` or `off`, or `true` or `false`.
+fn parse_switch(option: &'static str, value: &str) -> Result<bool, SettingError> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" => Ok(true),
+        "off" | "false" => Ok(false),
+        _ => Err(SettingError::InvalidValue {
+            option,
+            value: value.to_string(),
+            expected: "on or off".to_string(),
+        }),
+    }
+}
+
+/// Parses `auto` or a number within `range`.
+fn parse_auto<T>(
+    option: &'static str,
+    value: &str,
+    range: RangeInclusive<T>,
+) -> Result<Option<T>, SettingError>
+where
+    T: FromStr + PartialOrd + Display,
+{
+    if value == "auto" {
+        return Ok(None);
+    }
+    match value.parse() {
+        Ok(num) if range.contains(&num) => Ok(Some(num)),
+        _ => Err(SettingError::InvalidValue {
+            option,
+            value: value.to_string(),
+            expected: format!("auto or {} to {}", range.start(), range.end()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_settings_set() {
+        let mut settings = Settings::default();
+        settings.set("base", "HEX").unwrap();
+        settings.set("width", "32").unwrap();
+        settings.set("precision", "3").unwrap();
+        assert_eq!(settings.base, Base::Hex);
+        assert_eq!(settings.width, Some(32));
+        assert_eq!(settings.precision, Some(3));
+
+        settings.set("width", "auto").unwrap();
+        assert_eq!(settings.width, None);
+        settings.set("group", "4").unwrap();
+        assert_eq!(settings.group, 4);
+        settings.set("separator", "_").unwrap();
+        settings.set("ruler", "on").unwrap();
+        assert_eq!(settings.separator, Separator::Underscore);
+        assert!(settings.ruler);
+        assert_eq!(
+            settings.set("ruler", "yes"),
+            Err(SettingError::InvalidValue {
+                option: "ruler",
+                value: "yes".into(),
+                expected: "on or off".into(),
+            })
+        );
+
+        assert_eq!(
+            settings.set("units", "metric"),
+            Err(SettingError::InvalidValue {
+                option: "units",
+                value: "metric".into(),
+                expected: "one of both, si, iec".into(),
+            })
+        );
+        assert_eq!(
+            settings.set("width", "0"),
+            Err(SettingError::InvalidValue {
+                option: "width",
+                value: "0".into(),
+                expected: "auto or 1 to 128".into(),
+            })
+        );
+        assert_eq!(
+            settings.set("theme", "dark"),
+            Err(SettingError::UnknownOption("theme".into()))
+        );
+    }
+
+    #[test]
+    fn test_settings_display() {
+        let mut settings = Settings::default();
+        settings.set("format", "json").unwrap();
+        assert_eq!(
+            settings.to_string(),
+            "base = all\nwidth = auto\ngroup = 8\nseparator = space\nruler = off\n\
+             highlight = none\nunits = both\nprecision = auto\nword = auto\n\
+             colour = auto\nformat = json"
+        );
+    }
+}
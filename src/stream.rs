@@ -0,0 +1,171 @@
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::interpreter::{
+    token::{FullUnit, Unit},
+    unit_prefix::UnitPrefix,
+    value::Scalar,
+};
+
+/// The parts of the lines of a stream holding the numbers to replace.
+#[derive(Debug, Clone)]
+pub enum Selector {
+    /// The whitespace separated fields with these indices, counting from 1.
+    Fields(Vec<usize>),
+    /// The matches of a regex, or of its first capture group if it has one.
+    Matches(Regex),
+}
+
+impl Selector {
+    /// Returns the locations of the selected parts of `line`, in order.
+    pub fn select(&self, line: &str) -> Vec<Range<usize>> {
+        match self {
+            Selector::Fields(indices) => fields(line)
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| indices.contains(&(i + 1)))
+                .map(|(_, field)| field)
+                .collect(),
+            Selector::Matches(regex) => regex
+                .captures_iter(line)
+                .filter_map(|captures| captures.get(1).or(captures.get(0)))
+                .map(|m| m.range())
+                .collect(),
+        }
+    }
+}
+
+/// Returns the locations of the whitespace separated fields of `line`.
+fn fields(line: &str) -> Vec<Range<usize>> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(first)) => {
+                fields.push(first..i);
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(first) = start {
+        fields.push(first..line.len());
+    }
+    fields
+}
+
+/// Parses the number `text`, i.e. an integer, possibly with a `0x`, `0o` or `0b` prefix, or a decimal
+/// fraction.
+///
+/// If `human` is set, numbers are sizes in bytes, which may have a unit prefix like `1.5G`, `4Ki` or
+/// `10kB`, where prefixes ending in `i` are binary ones.
+pub fn parse_number(text: &str, human: bool) -> Option<Scalar> {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, text),
+    };
+    let radix = match digits.get(..2) {
+        Some("0x") => Some(16),
+        Some("0o") => Some(8),
+        Some("0b") => Some(2),
+        _ => None,
+    };
+    if let Some(radix) = radix {
+        let int = i128::from_str_radix(&digits[2..], radix).ok()?;
+        return Some(Scalar::new(sign * int, human.then(FullUnit::byte)));
+    }
+
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(digits.len());
+    let (number, suffix) = digits.split_at(end);
+    if number.is_empty() || (!human && !suffix.is_empty()) {
+        return None;
+    }
+    let value = match number.parse::<i128>() {
+        Ok(int) => Scalar::new(sign * int, None),
+        Err(_) => Scalar::new(sign as f64 * number.parse::<f64>().ok()?, None),
+    };
+    if !human {
+        return Some(value);
+    }
+
+    let prefix = suffix.strip_suffix('B').unwrap_or(suffix);
+    if prefix.len() > 2 {
+        return None;
+    }
+    let prefix = UnitPrefix::try_from(prefix).ok()?;
+    let bytes = value
+        .convert_to(FullUnit::new(prefix, Unit::Byte))
+        .convert_to(FullUnit::byte());
+    Some(match bytes.int() {
+        Some(int) => Scalar::new(int, Some(FullUnit::byte())),
+        None => bytes,
+    })
+}
+
+/// Returns the number of `scalar` without its unit, e.g. to print a size in bytes as a plain number.
+pub fn unitless(scalar: &Scalar) -> Scalar {
+    match scalar.int() {
+        Some(int) => Scalar::new(int, None),
+        None => Scalar::new(scalar.value(), None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interpreter::{expr::Stmt, Interpreter};
+
+    #[test]
+    fn test_select() {
+        let line = "4096\t./src  12 x";
+        let select = |selector: Selector| {
+            selector
+                .select(line)
+                .into_iter()
+                .map(|range| &line[range])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(select(Selector::Fields(vec![1, 3, 5])), ["4096", "12"]);
+        let regex = Regex::new(r"(\d+)\s+x").unwrap();
+        assert_eq!(select(Selector::Matches(regex)), ["12"]);
+        let regex = Regex::new(r"\d+").unwrap();
+        assert_eq!(select(Selector::Matches(regex)), ["4096", "12"]);
+    }
+
+    #[test]
+    fn test_parse_number() {
+        let parse = |text, human| parse_number(text, human).map(|value| value.to_string());
+        assert_eq!(parse("4096", false).as_deref(), Some("4096"));
+        assert_eq!(parse("-0x1f", false).as_deref(), Some("-31"));
+        assert_eq!(parse("2.5", false).as_deref(), Some("2.5"));
+        assert_eq!(parse("1.5G", false), None);
+        assert_eq!(parse("./src", true), None);
+
+        assert_eq!(parse("1.5G", true).as_deref(), Some("1500000000B"));
+        assert_eq!(parse("4Ki", true).as_deref(), Some("4096B"));
+        assert_eq!(parse("10kB", true).as_deref(), Some("10000B"));
+        assert_eq!(parse("0.5KiB", true).as_deref(), Some("512B"));
+        assert_eq!(parse("4096", true).as_deref(), Some("4096B"));
+        assert_eq!(parse("1.5X", true), None);
+
+        let bytes = parse_number("1.5G", true).unwrap();
+        assert_eq!(unitless(&bytes).to_string(), "1500000000");
+    }
+
+    #[test]
+    fn test_map_human() {
+        let mut interpreter = Interpreter::new();
+        let expr = match Interpreter::parse("x as MiB") {
+            Ok(Stmt::Expr(expr)) => expr,
+            stmt => panic!("Expected an expression, got {stmt:?}"),
+        };
+        let number = parse_number("1.5G", true).unwrap();
+        interpreter.define_value("x".into(), "1.5G".into(), number.into());
+        let value = interpreter.evaluate(&expr).unwrap();
+        assert_eq!(format!("{value:.1}"), "1430.5MiB");
+    }
+}